@@ -1,6 +1,5 @@
 import { CaretRight, File as FileIcon } from 'phosphor-react';
 import { useCallback } from 'react';
-import { useTabStore } from '../../components/editor/tabStore';
 /** Left explorer for files/folders */
 import { VirtualTree } from '../../components/ui/virtual/VirtualTree';
 import type { TreeNode } from '../../components/ui/virtual/VirtualTree';
@@ -9,8 +8,8 @@ import {
   useToggleDir,
   useVisibleFileTree,
 } from '../../hooks/useIncrementalFileTree';
-import { batchedInvoke } from '../../lib/tauri/batchedCommunication';
 import { memoIcon } from '../../lib/ui/memoIcon';
+import { openFileInTab } from '../../lib/workspace/openFile';
 import { useWorkspaceRoot } from '../../lib/workspace/workspaceStore';
 
 const CaretRightMemo = memoIcon(CaretRight);
@@ -32,39 +31,11 @@ export default function ExplorerPane() {
 
   const nodes = useVisibleFileTree();
   const toggleDir = useToggleDir();
-  const openTab = useTabStore((s) => s.openTab);
 
-  const openFile = useCallback(
-    async (node: TreeNode) => {
-      if (node.kind !== 'file') return;
-
-      // Infer language from extension
-      const ext = node.name.split('.').pop()?.toLowerCase() ?? '';
-      const langMap: Record<string, string> = {
-        ts: 'typescript',
-        tsx: 'typescript',
-        js: 'javascript',
-        jsx: 'javascript',
-        json: 'json',
-        rs: 'rust',
-        css: 'css',
-        html: 'html',
-        md: 'markdown',
-        markdown: 'markdown',
-      };
-      const language = langMap[ext] ?? 'plaintext';
-
-      let code = '';
-      try {
-        code = await batchedInvoke<string>('read_file_text', { path: node.id });
-      } catch (err) {
-        console.error('[ExplorerPane] Failed to read file', err);
-      }
-
-      openTab({ id: node.id, name: node.name, language, code });
-    },
-    [openTab]
-  );
+  const openFile = useCallback(async (node: TreeNode) => {
+    if (node.kind !== 'file') return;
+    await openFileInTab(node.id, node.name);
+  }, []);
 
   // Stable renderer to avoid re-creating function every render and reduce
   // unnecessary VirtualList re-renders.
@@ -1,7 +1,7 @@
 import Editor from '@monaco-editor/react';
 import { useEffect } from 'react';
 import SkeletonPane from '../../components/common/SkeletonPane';
-import { setupLspBridge } from '../../lib/lsp/bridge';
+import { attachDocumentSync, setupLspBridge } from '../../lib/lsp/bridge';
 import { LARGE_FILE_THRESHOLD, ensureLanguage } from '../../lib/monaco/loader';
 import { type VirtualDocument, createVirtualDocument } from '../../lib/monaco/virtualDocument';
 import { useWorkspaceRoot } from '../../lib/workspace/workspaceStore';
@@ -60,8 +60,13 @@ export default function CodeEditor({
       onChange={onChange}
       onMount={(editor) => {
         virtualDoc?.attachEditor(editor);
+        const model = editor.getModel();
         if (root) {
           void setupLspBridge(root, language);
+          if (model) {
+            const sync = attachDocumentSync(root, model, language);
+            editor.onDidDispose(() => sync.dispose());
+          }
         }
       }}
       {...modelProps}
@@ -4,6 +4,7 @@
  */
 import { invoke } from '@tauri-apps/api/core';
 import { terminalLogger } from '../../../lib/tauri/consoleLogger';
+import { hitLabel, type SearchHit } from './types';
 
 /**
  * Performs a direct search query and logs the results to the terminal
@@ -14,7 +15,7 @@ export async function debugDirectSearch(rootPath: string): Promise<void> {
     terminalLogger.log(`[SEARCH-DEBUG] Running direct search test on workspace: ${rootPath}`);
 
     // Test empty query first - should return initial results
-    const emptyResults = await invoke<string[]>('query_index', {
+    const emptyResults = await invoke<SearchHit[]>('query_index', {
       params: {
         path: rootPath,
         query: '',
@@ -26,7 +27,7 @@ export async function debugDirectSearch(rootPath: string): Promise<void> {
     terminalLogger.log(`[SEARCH-DEBUG] Empty query returned ${emptyResults.length} results`);
     if (emptyResults.length > 0) {
       terminalLogger.log(
-        `[SEARCH-DEBUG] First few results: ${emptyResults.slice(0, 3).join(', ')}`
+        `[SEARCH-DEBUG] First few results: ${emptyResults.slice(0, 3).map(hitLabel).join(', ')}`
       );
     } else {
       terminalLogger.warn('[SEARCH-DEBUG] No results for empty query!');
@@ -34,7 +35,7 @@ export async function debugDirectSearch(rootPath: string): Promise<void> {
 
     // Now let's test with a sample query
     const testQuery = 'js'; // Simple query that should match JavaScript files
-    const queryResults = await invoke<string[]>('query_index', {
+    const queryResults = await invoke<SearchHit[]>('query_index', {
       params: {
         path: rootPath,
         query: testQuery,
@@ -48,7 +49,7 @@ export async function debugDirectSearch(rootPath: string): Promise<void> {
     );
     if (queryResults.length > 0) {
       terminalLogger.log(
-        `[SEARCH-DEBUG] First few results: ${queryResults.slice(0, 3).join(', ')}`
+        `[SEARCH-DEBUG] First few results: ${queryResults.slice(0, 3).map(hitLabel).join(', ')}`
       );
     } else {
       terminalLogger.warn(`[SEARCH-DEBUG] No results for query '${testQuery}'!`);
@@ -0,0 +1,19 @@
+/** Search result returned by `query_index` / `query_content_index`. */
+export interface SearchHit {
+  /** Workspace root name (unique within a multi-root workspace) */
+  root: string;
+  /** Absolute path of the root */
+  rootPath: string;
+  /** Path relative to the root */
+  path: string;
+}
+
+/** Absolute path of a hit, for opening it. */
+export function hitPath(hit: SearchHit): string {
+  return `${hit.rootPath.replace(/[\\/]+$/, '')}/${hit.path}`;
+}
+
+/** Display label of a hit: the path, prefixed with the root name. */
+export function hitLabel(hit: SearchHit): string {
+  return `${hit.root}/${hit.path}`;
+}
@@ -1,6 +1,7 @@
 import { useCallback, useEffect, useRef, useState } from 'react';
 import { batchedInvoke } from '../../../lib/tauri/batchedCommunication';
 import { terminalLogger } from '../../../lib/tauri/consoleLogger';
+import { hitLabel, type SearchHit } from './types';
 
 // Debounce helper – identical to useWorkspaceSearch
 function useDebouncedValue<T>(value: T, delay = 120): T {
@@ -13,7 +14,7 @@ function useDebouncedValue<T>(value: T, delay = 120): T {
 }
 
 interface SearchState {
-  results: string[];
+  results: SearchHit[];
   loading: boolean;
   loadMore: () => void;
   hasMore: boolean;
@@ -26,7 +27,7 @@ interface SearchState {
 export function useContentSearch(rootPath: string, query: string): SearchState {
   const PAGE_SIZE = 150;
 
-  const [results, setResults] = useState<string[]>([]);
+  const [results, setResults] = useState<SearchHit[]>([]);
   const [loading, setLoading] = useState(false);
   const [hasMore, setHasMore] = useState(false);
   const pageRef = useRef(0);
@@ -93,7 +94,7 @@ export function useContentSearch(rootPath: string, query: string): SearchState {
         terminalLogger.log(
           `[CONTENT-SEARCH] Calling query_content_index with params: ${JSON.stringify(paramsObject)}`
         );
-        const raw: string[] = await batchedInvoke('query_content_index', paramsObject);
+        const raw: SearchHit[] = await batchedInvoke('query_content_index', paramsObject);
 
         if (currentId !== requestIdRef.current) {
           terminalLogger.log(
@@ -107,7 +108,7 @@ export function useContentSearch(rootPath: string, query: string): SearchState {
         terminalLogger.log(`[CONTENT-SEARCH] Raw results received: ${raw.length} items`);
 
         if (raw.length > 0) {
-          terminalLogger.log(`[CONTENT-SEARCH] Result sample: ${raw.slice(0, 3).join(', ')}`);
+          terminalLogger.log(`[CONTENT-SEARCH] Result sample: ${raw.slice(0, 3).map(hitLabel).join(', ')}`);
         }
 
         if (page === 0) {
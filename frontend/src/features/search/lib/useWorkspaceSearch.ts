@@ -2,6 +2,7 @@ import { useCallback, useEffect, useRef, useState } from 'react';
 import { batchedInvoke } from '../../../lib/tauri/batchedCommunication';
 import { terminalLogger } from '../../../lib/tauri/consoleLogger';
 import { runTask } from '../../../workers/pool/workerPool';
+import { hitLabel, type SearchHit } from './types';
 
 // Small debounce helper – waits `delay` ms after the last call before firing
 function useDebouncedValue<T>(value: T, delay = 120): T {
@@ -14,7 +15,7 @@ function useDebouncedValue<T>(value: T, delay = 120): T {
 }
 
 interface SearchState {
-  results: string[];
+  results: SearchHit[];
   loading: boolean;
   loadMore: () => void;
   hasMore: boolean;
@@ -29,7 +30,7 @@ interface SearchState {
 export function useWorkspaceSearch(rootPath: string, query: string): SearchState {
   const PAGE_SIZE = 150;
 
-  const [results, setResults] = useState<string[]>([]);
+  const [results, setResults] = useState<SearchHit[]>([]);
   const [loading, setLoading] = useState(false);
   const [hasMore, setHasMore] = useState(false);
   const pageRef = useRef(0);
@@ -77,7 +78,7 @@ export function useWorkspaceSearch(rootPath: string, query: string): SearchState
       try {
         const startTime = Date.now();
         terminalLogger.log(`[SEARCH] Starting query execution (page ${page})`);
-        let raw: string[] = await batchedInvoke('query_index', {
+        let raw: SearchHit[] = await batchedInvoke('query_index', {
           params: {
             path: rootPath,
             query: debouncedQuery,
@@ -91,7 +92,9 @@ export function useWorkspaceSearch(rootPath: string, query: string): SearchState
 
         // Detailed logging of received data
         if (raw.length > 0) {
-          terminalLogger.log(`[SEARCH] Result sample: ${raw.slice(0, 3).join(', ')}`);
+          terminalLogger.log(
+            `[SEARCH] Result sample: ${raw.slice(0, 3).map(hitLabel).join(', ')}`
+          );
         } else {
           terminalLogger.warn('[SEARCH] Warning: Empty results array received from backend');
           terminalLogger.log(`[SEARCH] Debug info - Query: "${debouncedQuery}", Path: ${rootPath}`);
@@ -102,11 +105,14 @@ export function useWorkspaceSearch(rootPath: string, query: string): SearchState
           try {
             const rankStart = Date.now();
             terminalLogger.log('[SEARCH] Starting client-side re-ranking');
-            raw = await runTask<string[]>('heavyFilterSort', {
-              items: raw,
+            // Ranked by label, so hits from different roots stay apart.
+            const byLabel = new Map(raw.map((hit) => [hitLabel(hit), hit]));
+            const ranked = await runTask<string[]>('heavyFilterSort', {
+              items: [...byLabel.keys()],
               query: debouncedQuery,
               limit: PAGE_SIZE,
             });
+            raw = ranked.flatMap((label) => byLabel.get(label) ?? []);
             terminalLogger.log(
               `[SEARCH] Client-side re-ranking completed in ${Date.now() - rankStart}ms`
             );
@@ -1,10 +1,11 @@
 import { memo, useCallback, useEffect, useMemo } from 'react';
 import { VirtualList } from '../../../components/ui/virtual/VirtualList';
 import { terminalLogger } from '../../../lib/tauri/consoleLogger';
+import { hitLabel, type SearchHit } from '../lib/types';
 
 interface Props {
-  results: string[];
-  onSelect: (path: string) => void;
+  results: SearchHit[];
+  onSelect: (hit: SearchHit) => void;
   loading?: boolean;
   collapsed?: boolean;
   showCount?: number;
@@ -39,9 +40,7 @@ function SearchResultsDropdownInner({
   const displayResults = useCallback(() => {
     // Show loading placeholders when loading
     if (loading) {
-      return Array(showCount)
-        .fill('')
-        .map((_, i) => `loading-${i}`);
+      return Array<SearchHit | null>(showCount).fill(null);
     }
 
     // Show limited results when collapsed, all results when expanded
@@ -49,7 +48,7 @@ function SearchResultsDropdownInner({
     terminalLogger.log(
       `[SEARCH-RESULTS] Showing ${itemsToShow} of ${results.length} items (collapsed=${collapsed})`
     );
-    return results.slice(0, itemsToShow);
+    return results.slice(0, itemsToShow) as (SearchHit | null)[];
   }, [loading, results, collapsed, showCount]);
 
   // Get the actual items to display
@@ -66,7 +65,8 @@ function SearchResultsDropdownInner({
   useEffect(() => {
     terminalLogger.log(`[SEARCH-RESULTS] Display items: ${items.length} items to show`);
     if (items.length > 0) {
-      terminalLogger.log(`[SEARCH-RESULTS] First few items: ${items.slice(0, 3).join(', ')}`);
+      const sample = items.slice(0, 3).map((item) => (item ? hitLabel(item) : '…'));
+      terminalLogger.log(`[SEARCH-RESULTS] First few items: ${sample.join(', ')}`);
     } else {
       terminalLogger.log('[SEARCH-RESULTS] No items to display');
     }
@@ -96,17 +96,21 @@ function SearchResultsDropdownInner({
         items={items}
         itemSize={28}
         height={height}
-        render={(item: string) => (
+        render={(item: SearchHit | null) => (
           <button
             type="button"
-            disabled={loading}
+            disabled={!item}
             className="flex w-full items-center gap-2 truncate px-3 text-left text-sm text-neutral-200 hover:bg-neutral-700/50 disabled:cursor-wait"
-            onClick={() => !loading && onSelect(item)}
+            onClick={() => item && onSelect(item)}
           >
-            {loading ? (
-              <div className="h-4 w-full animate-pulse rounded bg-neutral-600" />
+            {item ? (
+              <>
+                <span className="truncate font-mono">{item.path}</span>
+                {/* Root tag, so equal paths in different folders stay apart */}
+                <span className="ml-auto shrink-0 text-xs text-neutral-400">{item.root}</span>
+              </>
             ) : (
-              <span className="truncate font-mono">{item}</span>
+              <div className="h-4 w-full animate-pulse rounded bg-neutral-600" />
             )}
           </button>
         )}
@@ -1,7 +1,9 @@
 import { useEffect, useMemo, useState } from 'react';
 import { terminalLogger } from '../../../../lib/tauri/consoleLogger';
+import { openFileInTab } from '../../../../lib/workspace/openFile';
 import { useWorkspaceRoot } from '../../../../lib/workspace/workspaceStore';
 import { debugDirectSearch } from '../../lib/debugSearch';
+import { hitLabel, hitPath } from '../../lib/types';
 import { useContentSearch } from '../../lib/useContentSearch';
 import { useWorkspaceSearch } from '../../lib/useWorkspaceSearch';
 import { SearchResultsDropdown } from '../SearchResultsDropdown';
@@ -92,7 +94,9 @@ export default function SearchDropdown({ open, query }: Props) {
     `[SEARCH-UI] Rendering dropdown with data: resultCount=${results.length}, loading=${loading}, hasMore=${hasMore}, collapsed=${collapsed}`
   );
   if (results.length > 0) {
-    terminalLogger.log(`[SEARCH-UI] Sample results: ${results.slice(0, 3).join(', ')}`);
+    terminalLogger.log(
+      `[SEARCH-UI] Sample results: ${results.slice(0, 3).map(hitLabel).join(', ')}`
+    );
   }
 
   return (
@@ -112,8 +116,10 @@ export default function SearchDropdown({ open, query }: Props) {
           loadMore();
         }
       }}
-      onSelect={() => {
-        /* no-op until file open implemented */
+      onSelect={(hit) => {
+        // Resolve against the hit's own root – in a multi-root workspace it
+        // is not necessarily the primary one.
+        void openFileInTab(hitPath(hit));
       }}
     />
   );
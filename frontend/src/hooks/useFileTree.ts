@@ -8,9 +8,13 @@ export interface FsNode {
   name: string;
   depth: number;
   kind: 'file' | 'dir';
+  /** Name of the workspace root the node belongs to */
+  root?: string;
 }
 
 interface FsChange {
+  /** Name of the workspace root the changed paths belong to */
+  root?: string;
   paths: string[];
   kind: string;
 }
@@ -0,0 +1,146 @@
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+/** Thin typed wrappers around the backend's Debug Adapter Protocol bridge.
+ *  Sessions are started and driven by the backend; the UI routes requests
+ *  by session id and listens for the `dap_*` events.
+ */
+
+export interface DapAdapter {
+  id: string;
+  command: string;
+  args: string[];
+  languages: string[];
+}
+
+export interface DapSessionInfo {
+  id: string;
+  root: string;
+  adapter: string;
+  /** `Capabilities` from the adapter's `initialize` response */
+  capabilities: Record<string, unknown>;
+}
+
+/** DAP `SourceBreakpoint` (1-based lines). */
+export interface DapSourceBreakpoint {
+  line: number;
+  column?: number;
+  condition?: string;
+  hitCondition?: string;
+  logMessage?: string;
+}
+
+/** DAP `Breakpoint` as verified by an adapter. */
+export interface DapBreakpoint {
+  id?: number;
+  verified: boolean;
+  line?: number;
+  message?: string;
+}
+
+export interface DapEvent {
+  session: string;
+  event: string;
+  body: unknown;
+}
+
+export interface DapBreakpointsEvent {
+  session: string;
+  path: string;
+  breakpoints: DapBreakpoint[];
+}
+
+/** Adapter → client request, answered with `respondDapRequest`. */
+export interface DapReverseRequest {
+  session: string;
+  seq: number;
+  command: string;
+  arguments: unknown;
+}
+
+export interface DapSessionEnded {
+  session: string;
+  message: string;
+}
+
+export function getDapAdapters(root: string): Promise<DapAdapter[]> {
+  return invoke('dap_adapters', { payload: { root } });
+}
+
+/** Start a session; resolves once the debuggee is launched / attached. */
+export function startDap(
+  root: string,
+  adapter: string,
+  request: 'launch' | 'attach',
+  configuration: Record<string, unknown> = {},
+): Promise<DapSessionInfo> {
+  return invoke('dap_start', { payload: { root, adapter, request, configuration } });
+}
+
+export function getDapSessions(): Promise<DapSessionInfo[]> {
+  return invoke('dap_sessions');
+}
+
+/** Send a request to a session; resolves to the response body. */
+export function dapRequest<T = unknown>(
+  session: string,
+  command: string,
+  args: Record<string, unknown> = {},
+  timeoutMs?: number,
+): Promise<T> {
+  return invoke('dap_request', { payload: { session, command, arguments: args, timeoutMs } });
+}
+
+export function respondDapRequest(
+  request: DapReverseRequest,
+  success: boolean,
+  body: unknown = {},
+  message?: string,
+): Promise<void> {
+  return invoke('respond_dap_request', {
+    payload: {
+      session: request.session,
+      requestSeq: request.seq,
+      command: request.command,
+      success,
+      body,
+      message,
+    },
+  });
+}
+
+export function stopDap(session: string, terminateDebuggee?: boolean): Promise<void> {
+  return invoke('dap_stop', { payload: { session, terminateDebuggee } });
+}
+
+/** Persisted breakpoints of a root by absolute file path. */
+export function getDapBreakpoints(root: string): Promise<Record<string, DapSourceBreakpoint[]>> {
+  return invoke('dap_breakpoints', { payload: { root } });
+}
+
+/** Replace a file's breakpoints; resolves to each running session's view by session id. */
+export function setDapBreakpoints(
+  root: string,
+  path: string,
+  breakpoints: DapSourceBreakpoint[],
+): Promise<Record<string, DapBreakpoint[]>> {
+  return invoke('dap_set_breakpoints', { payload: { root, path, breakpoints } });
+}
+
+export function onDapEvent(handler: (event: DapEvent) => void): Promise<UnlistenFn> {
+  return listen<DapEvent>('dap_event', (e) => handler(e.payload));
+}
+
+export function onDapBreakpoints(
+  handler: (event: DapBreakpointsEvent) => void,
+): Promise<UnlistenFn> {
+  return listen<DapBreakpointsEvent>('dap_breakpoints', (e) => handler(e.payload));
+}
+
+export function onDapRequest(handler: (request: DapReverseRequest) => void): Promise<UnlistenFn> {
+  return listen<DapReverseRequest>('dap_request', (e) => handler(e.payload));
+}
+
+export function onDapSessionEnded(handler: (event: DapSessionEnded) => void): Promise<UnlistenFn> {
+  return listen<DapSessionEnded>('dap_session_ended', (e) => handler(e.payload));
+}
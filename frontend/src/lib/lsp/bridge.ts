@@ -1,5 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
-import { listen } from '@tauri-apps/api/event';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import * as monaco from 'monaco-editor';
 
 /** Internal helper to access a private cache of registered providers without
@@ -10,8 +10,9 @@ type MutableLanguages = typeof monaco.languages & {
   _providers?: Record<string, monaco.IDisposable>;
 };
 
-/** Minimal LSP → Monaco adapter (request/response only, no notifications).
- *  It wires basic completion support by forwarding `textDocument/completion`.
+/** Minimal LSP → Monaco adapter.
+ *  It wires basic completion support by forwarding `textDocument/completion`,
+ *  and answers server → client requests the backend forwards as `lsp_request`.
  */
 export async function setupLspBridge(workspaceRoot: string, language: string) {
   // Guard: do not re-register multiple providers for same language
@@ -19,94 +20,233 @@ export async function setupLspBridge(workspaceRoot: string, language: string) {
   const hoverKey = `glass-lsp-${language}-hover`;
   const languagesMutable = monaco.languages as MutableLanguages;
   // Already registered?
-  if (languagesMutable._providers?.[completionKey] && languagesMutable._providers?.[hoverKey])
+  if (languagesMutable._providers?.[completionKey] || languagesMutable._providers?.[hoverKey])
     return;
 
+  // Only wire features the server advertises. `null` = capabilities unknown
+  // (server failed to start) – register everything as before.
+  const capabilities = await getLspCapabilities(workspaceRoot, language);
+  const supports = (feature: keyof LspServerCapabilities) =>
+    capabilities === null || Boolean(capabilities[feature]);
+  const providers: Record<string, monaco.IDisposable> = {};
+
   // ------------------------ Completion Provider ----------------------------
-  const completionProvider = monaco.languages.registerCompletionItemProvider(language, {
-    triggerCharacters: ['.', ':', '<', '"', "'", '/'],
-
-    async provideCompletionItems(model, position) {
-      try {
-        const params = {
-          textDocument: { uri: model.uri.toString() },
-          position: { line: position.lineNumber - 1, character: position.column - 1 },
-          context: { triggerKind: 1 },
-        };
-
-        const request = {
-          jsonrpc: '2.0',
-          id: Date.now(),
-          method: 'textDocument/completion',
-          params,
-        };
-
-        const resp: { response: { result?: unknown } } = await invoke('invoke_lsp', {
-          root: workspaceRoot,
-          request,
-        });
-
-        const lspResult = resp?.response?.result as
-          | { items?: LspCompletionItem[] }
-          | LspCompletionItem[]
-          | undefined;
-        if (!lspResult) return { suggestions: [] };
-
-        const items: LspCompletionItem[] = Array.isArray(lspResult)
-          ? lspResult
-          : (lspResult.items ?? []);
-        const suggestions = items.map(mapLspItemToMonaco);
-        return { suggestions };
-      } catch (err) {
-        console.error('[LSP completion error]', err);
-        return { suggestions: [] };
-      }
-    },
-  });
+  if (supports('completionProvider'))
+    providers[completionKey] = monaco.languages.registerCompletionItemProvider(language, {
+      triggerCharacters: ['.', ':', '<', '"', "'", '/'],
+
+      async provideCompletionItems(model, position, _context, token) {
+        try {
+          const params = {
+            textDocument: { uri: model.uri.toString() },
+            position: { line: position.lineNumber - 1, character: position.column - 1 },
+            context: { triggerKind: 1 },
+          };
+
+          const resp: { response: { result?: unknown } } = await invokeCancellable(
+            workspaceRoot,
+            'textDocument/completion',
+            params,
+            token,
+          );
+
+          const lspResult = resp?.response?.result as
+            | { items?: LspCompletionItem[] }
+            | LspCompletionItem[]
+            | undefined;
+          if (!lspResult) return { suggestions: [] };
+
+          const items: LspCompletionItem[] = Array.isArray(lspResult)
+            ? lspResult
+            : (lspResult.items ?? []);
+          const suggestions = items.map(mapLspItemToMonaco);
+          return { suggestions };
+        } catch (err) {
+          console.error('[LSP completion error]', err);
+          return { suggestions: [] };
+        }
+      },
+    });
 
   // --------------------------- Hover Provider ------------------------------
-  const hoverProvider = monaco.languages.registerHoverProvider(language, {
-    async provideHover(model, position) {
-      try {
-        const params = {
-          textDocument: { uri: model.uri.toString() },
-          position: { line: position.lineNumber - 1, character: position.column - 1 },
-        };
-
-        const request = {
-          jsonrpc: '2.0',
-          id: Date.now(),
-          method: 'textDocument/hover',
-          params,
-        };
-
-        const resp: { response: { result?: LspHover | null } } = await invoke('invoke_lsp', {
-          root: workspaceRoot,
-          request,
-        });
-
-        const result = resp.response.result;
-        if (!result || !result.contents) return { contents: [] };
-
-        const contents = hoverContentsToMarkdown(result.contents);
-        return {
-          contents: [{ value: contents }],
-        } as monaco.languages.Hover;
-      } catch (err) {
-        console.error('[LSP hover error]', err);
-        return { contents: [] };
-      }
-    },
+  if (supports('hoverProvider'))
+    providers[hoverKey] = monaco.languages.registerHoverProvider(language, {
+      async provideHover(model, position, token) {
+        try {
+          const params = {
+            textDocument: { uri: model.uri.toString() },
+            position: { line: position.lineNumber - 1, character: position.column - 1 },
+          };
+
+          const resp: { response: { result?: LspHover | null } } = await invokeCancellable(
+            workspaceRoot,
+            'textDocument/hover',
+            params,
+            token,
+          );
+
+          const result = resp.response.result;
+          if (!result || !result.contents) return { contents: [] };
+
+          const contents = hoverContentsToMarkdown(result.contents);
+          return {
+            contents: [{ value: contents }],
+          } as monaco.languages.Hover;
+        } catch (err) {
+          console.error('[LSP hover error]', err);
+          return { contents: [] };
+        }
+      },
+    });
+
+  // ------------------------ Semantic Tokens ------------------------------
+  // Always registered: the backend falls back to tree-sitter highlighting
+  // when the server has no semantic tokens.
+  const legend = await getSemanticTokensLegend();
+  const refresh = new monaco.Emitter<void>();
+  void listen<LspServerMessage>('lsp_notification', (event) => {
+    if (event.payload.method === 'workspace/semanticTokens/refresh') refresh.fire();
   });
+  providers[`glass-lsp-${language}-semanticTokens`] =
+    monaco.languages.registerDocumentSemanticTokensProvider(language, {
+      onDidChange: refresh.event,
+      getLegend: () => legend,
+      async provideDocumentSemanticTokens(model, lastResultId) {
+        try {
+          const result = await getSemanticTokens(model.uri.toString(), lastResultId ?? undefined);
+          if (result.edits)
+            return {
+              resultId: result.resultId,
+              edits: result.edits.map((e) => ({ ...e, data: Uint32Array.from(e.data) })),
+            };
+          return { resultId: result.resultId, data: Uint32Array.from(result.data ?? []) };
+        } catch (err) {
+          // Document not open on the backend (yet).
+          console.error('[LSP semantic tokens error]', err);
+          return null;
+        }
+      },
+      releaseDocumentSemanticTokens() {},
+    });
 
   languagesMutable._providers = {
     ...languagesMutable._providers,
-    [completionKey]: completionProvider,
-    [hoverKey]: hoverProvider,
+    ...providers,
   };
 
   // ----------------------- Diagnostics Listener ---------------------------
   initDiagnosticsListener();
+  initServerRequestListener();
+}
+
+let nextRequestId = 1;
+
+/** Send a request through `invoke_lsp`; if Monaco cancels `token` before the
+ *  response arrives the backend cancels it on the server (`$/cancelRequest`).
+ */
+async function invokeCancellable<T>(
+  root: string,
+  method: string,
+  params: unknown,
+  token: monaco.CancellationToken,
+): Promise<T> {
+  const id = nextRequestId++;
+  const request = { jsonrpc: '2.0', id, method, params };
+  const onCancel = token.onCancellationRequested(() => {
+    void invoke('cancel_lsp_request', { payload: { root, id } }).catch((err) =>
+      console.error('[LSP cancel error]', err),
+    );
+  });
+  try {
+    return await invoke<T>('invoke_lsp', { payload: { root, request } });
+  } finally {
+    onCancel.dispose();
+  }
+}
+
+/** Capabilities of the server handling `language` in `root`. Starts the server
+ *  if needed; resolves to `null` if it cannot be started.
+ */
+export async function getLspCapabilities(
+  root: string,
+  language: string,
+): Promise<LspServerCapabilities | null> {
+  try {
+    const resp: { capabilities: LspServerCapabilities | null } = await invoke('lsp_capabilities', {
+      payload: { root, language },
+    });
+    return resp.capabilities ?? {};
+  } catch (err) {
+    console.warn('[LSP capabilities unavailable]', err);
+    return null;
+  }
+}
+
+/** Keep the backend's copy of `model` in sync (open / change / close) so every
+ *  server handling the file sees the live buffer. Only `file:` models are
+ *  synchronised – in-memory models have no path to route by.
+ */
+export function attachDocumentSync(
+  workspaceRoot: string,
+  model: monaco.editor.ITextModel,
+  language: string,
+): monaco.IDisposable {
+  const uri = model.uri.toString();
+  if (model.uri.scheme !== 'file') return { dispose() {} };
+
+  const report = (err: unknown) => console.error('[LSP sync error]', err);
+  void invoke('lsp_open_document', {
+    payload: { root: workspaceRoot, uri, languageId: language, text: model.getValue() },
+  }).catch(report);
+
+  // A workspace edit renamed or deleted the file: the backend already moved
+  // or closed the document, so this model stops syncing.
+  let moved = false;
+  const unlistenMoved = onDocumentMoved((event) => {
+    if (event.oldUri === uri) moved = true;
+  });
+
+  const onChange = model.onDidChangeContent((event) => {
+    if (moved) return;
+    // Monaco ranges are 1-based UTF-16 columns; LSP positions are 0-based.
+    const changes = event.changes.map((c) => ({
+      range: {
+        start: { line: c.range.startLineNumber - 1, character: c.range.startColumn - 1 },
+        end: { line: c.range.endLineNumber - 1, character: c.range.endColumn - 1 },
+      },
+      text: c.text,
+    }));
+    void invoke('lsp_change_document', { payload: { uri, changes } }).catch(report);
+  });
+  const close = () => {
+    if (!moved) void invoke('lsp_close_document', { payload: { uri } }).catch(report);
+  };
+  const onDispose = model.onWillDispose(close);
+
+  return {
+    dispose() {
+      onChange.dispose();
+      onDispose.dispose();
+      void unlistenMoved.then((unlisten) => unlisten());
+      close();
+    },
+  };
+}
+
+/** Called when a workspace edit renamed (`newUri`) or deleted (`newUri: null`)
+ *  an open document, so the tab can follow the file or close.
+ */
+export function onDocumentMoved(handler: (event: LspDocumentMoved) => void): Promise<UnlistenFn> {
+  return listen<LspDocumentMoved>('lsp_document_moved', (e) => handler(e.payload));
+}
+
+/** Tell the servers of `uri` that the buffer was written to disk. */
+export async function notifyDocumentSaved(uri: string) {
+  try {
+    await invoke('lsp_save_document', { payload: { uri } });
+  } catch (err) {
+    console.error('[LSP sync error]', err);
+  }
 }
 
 // Singleton init guard
@@ -118,7 +258,7 @@ function initDiagnosticsListener() {
 
   // Fire-and-forget promise
   void listen<LspDiagnosticsPayload>('lsp_diagnostics', (event) => {
-    const { uri, diagnostics } = event.payload;
+    const { server, uri, diagnostics } = event.payload;
     const model = monaco.editor.getModel(monaco.Uri.parse(uri));
     if (!model) return;
 
@@ -132,10 +272,154 @@ function initDiagnosticsListener() {
       endColumn: d.range.end.character + 1,
     }));
 
-    monaco.editor.setModelMarkers(model, 'lsp', markers);
+    // One owner per server, so servers sharing a file do not clear each other.
+    monaco.editor.setModelMarkers(model, `lsp:${server}`, markers);
+  });
+}
+
+let serverRequestsInitialized = false;
+
+/** Answer server → client requests the backend could not handle itself, and
+ *  apply edits the backend forwards for open documents (`lsp_document_edit`).
+ *  Every request gets a response so servers never hang.
+ */
+function initServerRequestListener() {
+  if (serverRequestsInitialized) return;
+  serverRequestsInitialized = true;
+
+  void listen<LspServerMessage>('lsp_request', (event) => {
+    const { root, server, id, method } = event.payload;
+    const respond = (body: { result?: unknown; error?: { code: number; message: string } }) =>
+      invoke('respond_lsp_request', { payload: { root, server, id, ...body } }).catch((err) =>
+        console.error('[LSP respond error]', err),
+      );
+
+    switch (method) {
+      case 'window/showMessageRequest':
+        void respond({ result: null });
+        break;
+      default:
+        void respond({ error: { code: -32601, message: `Unhandled method: ${method}` } });
+    }
+  });
+
+  void listen<LspDocumentEdit>('lsp_document_edit', (event) => {
+    const { uri, edits } = event.payload;
+    const model = monaco.editor.getModel(monaco.Uri.parse(uri));
+    if (!model) return;
+    // One undo stop so the whole edit is undone at once.
+    model.pushStackElement();
+    model.pushEditOperations(
+      [],
+      edits.map((e) => ({
+        range: new monaco.Range(
+          e.range.start.line + 1,
+          e.range.start.character + 1,
+          e.range.end.line + 1,
+          e.range.end.character + 1,
+        ),
+        text: e.newText,
+      })),
+      () => null,
+    );
+    model.pushStackElement();
   });
 }
 
+/** Apply a `WorkspaceEdit` (rename, code action…) on the backend. Open
+ *  documents are updated through `lsp_document_edit`, files on disk are
+ *  written; both are undone together by `undoWorkspaceEdit`.
+ */
+export function applyWorkspaceEdit(
+  root: string,
+  edit: LspWorkspaceEdit,
+  label?: string,
+): Promise<LspWorkspaceEditResult> {
+  return invoke('apply_workspace_edit', { payload: { root, edit, label } });
+}
+
+/** Revert a workspace edit (the most recent one if `undoId` is omitted). */
+export function undoWorkspaceEdit(undoId?: number): Promise<string> {
+  return invoke('undo_workspace_edit', { payload: { id: undoId } });
+}
+
+/** Format a document with its language server or the configured external
+ *  formatter. The edits apply to `text` (default: the synced buffer) – apply
+ *  them to the model before saving for format-on-save.
+ */
+export function formatDocument(
+  root: string,
+  uri: string,
+  languageId?: string,
+  text?: string,
+): Promise<LspFormatResult> {
+  return invoke('lsp_format_document', { payload: { root, uri, languageId, text } });
+}
+
+/** Semantic tokens of an open document: the server's tokens overlaid on the
+ *  tree-sitter fallback. Pass the `resultId` of the tokens held to get
+ *  `edits` against them instead of the full `data`.
+ */
+export function getSemanticTokens(
+  uri: string,
+  previousResultId?: string,
+): Promise<LspSemanticTokens> {
+  return invoke('lsp_semantic_tokens', { payload: { uri, previousResultId } });
+}
+
+/** Legend of the tokens from `getSemanticTokens`; fixed for all servers. */
+export function getSemanticTokensLegend(): Promise<monaco.languages.SemanticTokensLegend> {
+  return invoke('lsp_semantic_tokens_legend');
+}
+
+/** Which configured language servers are installed, per workspace root, and
+ *  which languages in the workspace have no working server.
+ */
+export function getLspHealth(root: string): Promise<LspRootHealth[]> {
+  return invoke('lsp_health', { payload: { root } });
+}
+
+/** State, reported health and running progress tasks of the started servers,
+ *  optionally only those of one workspace.
+ */
+export function getLspStatus(root?: string): Promise<LspServerStatus[]> {
+  return invoke('lsp_status', { payload: { root } });
+}
+
+export interface LspProblemFilter {
+  /** Workspace root or `.glass-workspace` file */
+  root?: string;
+  /** Least severe severity to include (1 = errors only) */
+  severity?: number;
+  /** Glob over root-relative paths, e.g. `src/**` */
+  path?: string;
+  /** Server id or diagnostic `source` */
+  source?: string;
+  limit?: number;
+}
+
+/** Stored diagnostics of all servers, ordered by path, severity and position. */
+export function getLspProblems(filter: LspProblemFilter = {}): Promise<LspProblem[]> {
+  return invoke('lsp_problems', { payload: filter });
+}
+
+/** Problem counts per file, for explorer decorations. */
+export function getLspProblemCounts(filter: LspProblemFilter = {}): Promise<LspFileProblemCounts[]> {
+  return invoke('lsp_problem_counts', { payload: filter });
+}
+
+/** Start / stop recording all LSP traffic; resolves to the recording file. */
+export function setLspRecording(enabled: boolean, path?: string): Promise<string | null> {
+  return invoke('lsp_set_recording', { payload: { enabled, path } });
+}
+
+/** Messages of the current (or last) recording, oldest first. */
+export function getLspRecording(
+  filter: { root?: string; server?: string; limit?: number } = {},
+): Promise<LspRecordedMessage[]> {
+  return invoke('lsp_recording', { payload: filter });
+}
+
 function lspSeverityToMonaco(sev: number | undefined): monaco.MarkerSeverity {
   switch (sev) {
     case 1:
@@ -168,7 +452,7 @@ interface LspRange {
   end: LspPosition;
 }
 
-interface LspDiagnostic {
+export interface LspDiagnostic {
   range: LspRange;
   message: string;
   severity?: number;
@@ -176,10 +460,164 @@ interface LspDiagnostic {
 }
 
 interface LspDiagnosticsPayload {
+  root: string;
+  server: string;
   uri: string;
   diagnostics: LspDiagnostic[];
 }
 
+/** Subset of `ServerCapabilities` the editor checks before enabling features. */
+interface LspServerCapabilities {
+  completionProvider?: unknown;
+  hoverProvider?: unknown;
+  definitionProvider?: unknown;
+  documentFormattingProvider?: unknown;
+  semanticTokensProvider?: unknown;
+}
+
+interface LspServerMessage {
+  root: string;
+  server: string;
+  id?: number | string;
+  method: string;
+  params: unknown;
+}
+
+interface LspTextEdit {
+  range: LspRange;
+  newText: string;
+}
+
+interface LspWorkspaceEdit {
+  changes?: Record<string, LspTextEdit[]>;
+  documentChanges?: unknown[];
+}
+
+interface LspDocumentEdit {
+  uri: string;
+  version: number;
+  edits: LspTextEdit[];
+}
+
+/** Payload of the `lsp_document_moved` event. */
+export interface LspDocumentMoved {
+  oldUri: string;
+  /** `null` if the document was deleted */
+  newUri: string | null;
+}
+
+export interface LspWorkspaceEditResult {
+  applied: boolean;
+  failureReason?: string;
+  failedChange?: number;
+  files: {
+    uri: string;
+    operation: 'edit' | 'create' | 'rename' | 'delete';
+    status: 'applied' | 'forwarded' | 'skipped' | 'failed';
+    error?: string;
+  }[];
+  undoId?: number;
+}
+
+export interface LspFormatResult {
+  edits: LspTextEdit[];
+  /** Server or external formatter that ran; `null` if only `.editorconfig`
+   *  whitespace rules applied. */
+  formatter: string | null;
+}
+
+/** `SemanticTokens` (`data`) or `SemanticTokensDelta` (`edits`). */
+export interface LspSemanticTokens {
+  resultId: string;
+  data?: number[];
+  edits?: { start: number; deleteCount: number; data: number[] }[];
+  /** Server whose tokens are included; `null` for the fallback alone. */
+  server: string | null;
+}
+
+export interface LspServerHealth {
+  id: string;
+  command: string;
+  languages: string[];
+  status: 'installed' | 'missing' | 'broken';
+  path: string | null;
+  source: 'config' | 'workspace' | 'path' | 'toolchain' | null;
+  version: string | null;
+  error: string | null;
+  /** Set if the server was started for this root */
+  state: 'starting' | 'running' | 'restarting' | 'failed' | 'stopped' | null;
+}
+
+export interface LspRootHealth {
+  root: string;
+  servers: LspServerHealth[];
+  missingLanguages: {
+    language: string;
+    files: number;
+    reason: 'notConfigured' | 'notInstalled';
+    /** Configured servers that would handle the language once installed */
+    servers: string[];
+  }[];
+}
+
+export interface LspProblem {
+  root: string;
+  server: string;
+  uri: string;
+  /** Root-relative path; absolute outside the root, null for non-file URIs */
+  path: string | null;
+  diagnostic: LspDiagnostic;
+}
+
+export interface LspFileProblemCounts {
+  uri: string;
+  path: string | null;
+  errors: number;
+  warnings: number;
+  information: number;
+  hints: number;
+}
+
+export interface LspProgressTask {
+  token: string | number;
+  title: string;
+  message: string | null;
+  /** 0–100, if the server reports it */
+  percentage: number | null;
+  cancellable: boolean;
+  /** Created by the server but not begun yet (no title so far) */
+  pending: boolean;
+  /** Milliseconds since the Unix epoch */
+  started: number;
+}
+
+/** Payload of the `lsp_progress` event. */
+export interface LspProgressEvent extends LspProgressTask {
+  root: string;
+  server: string;
+  kind: 'create' | 'begin' | 'report' | 'end';
+}
+
+export interface LspServerStatus {
+  root: string;
+  server: string;
+  state: 'starting' | 'running' | 'restarting' | 'failed' | 'stopped';
+  restarts: number;
+  lastExit: string | null;
+  /** Last `experimental/serverStatus` notification */
+  reported: { health: 'ok' | 'warning' | 'error'; quiescent: boolean; message: string | null } | null;
+  progress: LspProgressTask[];
+}
+
+export interface LspRecordedMessage {
+  /** Milliseconds since the Unix epoch */
+  time: number;
+  direction: 'send' | 'receive';
+  root: string;
+  server: string;
+  message: unknown;
+}
+
 type LspMarkupContent = { kind: 'markdown' | 'plaintext'; value: string };
 type LspMarkedString = string | { language: string; value: string };
 
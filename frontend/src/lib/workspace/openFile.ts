@@ -0,0 +1,32 @@
+import { useTabStore } from '../../components/editor/tabStore';
+import { batchedInvoke } from '../tauri/batchedCommunication';
+
+const LANGUAGES: Record<string, string> = {
+  ts: 'typescript',
+  tsx: 'typescript',
+  js: 'javascript',
+  jsx: 'javascript',
+  json: 'json',
+  rs: 'rust',
+  css: 'css',
+  html: 'html',
+  md: 'markdown',
+  markdown: 'markdown',
+};
+
+/** Monaco language id for a file name, inferred from its extension. */
+export function languageForFile(name: string): string {
+  const ext = name.split('.').pop()?.toLowerCase() ?? '';
+  return LANGUAGES[ext] ?? 'plaintext';
+}
+
+/** Read the file at absolute `path` and open it in an editor tab. */
+export async function openFileInTab(path: string, name = path.split(/[\\/]/).pop() ?? path) {
+  let code = '';
+  try {
+    code = await batchedInvoke<string>('read_file_text', { path });
+  } catch (err) {
+    console.error('[openFile] Failed to read file', err);
+  }
+  useTabStore.getState().openTab({ id: path, name, language: languageForFile(name), code });
+}
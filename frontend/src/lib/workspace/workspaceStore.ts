@@ -55,6 +55,13 @@ export async function openWorkspace(): Promise<string | null> {
       console.log('Log error:', e);
     }
 
+    // Shut down the previous workspace's language servers (fire-and-forget).
+    if (isSwitch && currentRoot !== selected) {
+      invoke('stop_lsp', { payload: { root: currentRoot } }).catch((e) =>
+        console.log('LSP stop error:', e)
+      );
+    }
+
     useWorkspaceStore.getState().setRootPath(selected);
     return selected;
   }
@@ -6,6 +6,7 @@
 //  • Skips binary / large files (>1 MB) and hidden paths.
 //  • Uses Rayon for parallel indexing and query scoring.
 //  • Persists only in-memory for now; disk snapshot can be added later.
+//  • Changed files are re-indexed individually via `update_paths` (fs watcher).
 
 use anyhow::Error as AnyError;
 use once_cell::sync::Lazy;
@@ -14,13 +15,21 @@ use serde::Deserialize;
 use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
 use std::sync::{Mutex, MutexGuard};
+use std::thread;
 use std::time::SystemTime;
 use tauri::command;
 use walkdir::{DirEntry, WalkDir};
 
+use super::fs::{files_under, is_hidden_rel};
+use super::search_query::{SearchHit, SearchQuery};
+use super::workspace::{self, WorkspaceRoot};
+
 const MAX_FILE_SIZE: u64 = 1024 * 1024; // 1 MB per file guard
 const DEFAULT_PAGE_SIZE: usize = 150;
+/// Candidates read at once to confirm phrases.
+const VERIFY_CHUNK: usize = 64;
 
 #[derive(Clone)]
 struct FileEntry {
@@ -61,6 +70,32 @@ fn extract_trigrams(text: &str) -> Vec<u32> {
     v
 }
 
+/// Read and trigram-index a single file. `None` for big, binary or empty files.
+fn index_file(root: &Path, path: &Path) -> Option<FileEntry> {
+    let meta = fs::metadata(path).ok()?;
+    if !meta.is_file() || meta.len() > MAX_FILE_SIZE {
+        return None; // Skip big files for now
+    }
+    let bytes = fs::read(path).ok()?;
+    // Quick binary check – allow ASCII or valid UTF-8
+    if !bytes.is_ascii() && std::str::from_utf8(&bytes).is_err() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&bytes);
+    let trigrams = extract_trigrams(&text);
+    if trigrams.is_empty() {
+        return None;
+    }
+    Some(FileEntry {
+        path: path
+            .strip_prefix(root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned(),
+        trigrams,
+    })
+}
+
 /// Return mutable reference to fresh or cached index for `root`.
 fn ensure_index<'a>(
     indices: &'a mut MutexGuard<'_, Vec<ContentIndex>>,
@@ -84,35 +119,8 @@ fn ensure_index<'a>(
         .filter_entry(|e| !is_hidden(e))
         .par_bridge()
         .filter_map(Result::ok)
-        .filter_map(|entry| {
-            let path = entry.path();
-            if !entry.file_type().is_file() {
-                return None;
-            }
-            if let Ok(meta) = entry.metadata() {
-                if meta.len() > MAX_FILE_SIZE {
-                    return None; // Skip big files for now
-                }
-            }
-            let bytes = fs::read(path).ok()?;
-            // Quick binary check – allow ASCII or valid UTF-8
-            if !bytes.is_ascii() && std::str::from_utf8(&bytes).is_err() {
-                return None;
-            }
-            let text = String::from_utf8_lossy(&bytes);
-            let trigrams = extract_trigrams(&text);
-            if trigrams.is_empty() {
-                return None;
-            }
-            Some(FileEntry {
-                path: path
-                    .strip_prefix(root)
-                    .unwrap_or(path)
-                    .to_string_lossy()
-                    .into_owned(),
-                trigrams,
-            })
-        })
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| index_file(root, entry.path()))
         .collect();
 
     // Replace old index for this root
@@ -125,6 +133,94 @@ fn ensure_index<'a>(
     Ok(indices.last_mut().unwrap())
 }
 
+/// Paths from the watcher, applied by one worker thread so the watcher never
+/// waits for file reads or an index rebuild holding the lock.
+static UPDATES: Lazy<Sender<Vec<PathBuf>>> = Lazy::new(|| {
+    let (tx, rx) = mpsc::channel::<Vec<PathBuf>>();
+    thread::Builder::new()
+        .name("content-index-updates".into())
+        .spawn(move || {
+            for paths in rx {
+                apply_updates(&paths);
+            }
+        })
+        .expect("failed to spawn content index worker");
+    tx
+});
+
+/// Re-index changed files of every loaded index (called by the fs watcher),
+/// in the background. Deleted or no-longer-indexable files are dropped.
+pub fn update_paths(paths: &[PathBuf]) {
+    if CONTENT_INDICES.lock().unwrap().is_empty() {
+        return;
+    }
+    let _ = UPDATES.send(paths.to_vec());
+}
+
+/// Re-index `paths` (files or directories) in the index of the deepest root
+/// containing each. Files are read outside the index lock.
+fn apply_updates(paths: &[PathBuf]) {
+    for path in paths {
+        // Deepest root for nested folders, like `workspace::root_for_path`.
+        let Some(root) = CONTENT_INDICES
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|i| path.starts_with(&i.root))
+            .max_by_key(|i| i.root.components().count())
+            .map(|i| i.root.clone())
+        else {
+            continue;
+        };
+        let Ok(rel) = path.strip_prefix(&root) else {
+            continue;
+        };
+        if is_hidden_rel(rel) {
+            continue; // hidden paths are never indexed
+        }
+        let entries: Vec<FileEntry> = files_under(path)
+            .iter()
+            .filter_map(|file| index_file(&root, file))
+            .collect();
+
+        let mut indices = CONTENT_INDICES.lock().unwrap();
+        let Some(idx) = indices.iter_mut().find(|i| i.root == root) else {
+            continue;
+        };
+        // Everything at or below `path`, so deleted directories leave nothing.
+        idx.files.retain(|f| !Path::new(&f.path).starts_with(rel));
+        idx.files.extend(entries);
+    }
+}
+
+/// Relative paths of files in `root` that may contain at least one of
+/// `needles` (all of the needle's trigrams present). Needles shorter than
+/// three bytes cannot be filtered and match every file.
+pub fn files_containing_any(root: &Path, needles: &[String]) -> Result<Vec<String>, AnyError> {
+    let needle_trigrams: Vec<Vec<u32>> = needles.iter().map(|n| extract_trigrams(n)).collect();
+    let mut indices = CONTENT_INDICES.lock().unwrap();
+    let idx = ensure_index(&mut indices, root)?;
+    Ok(idx
+        .files
+        .par_iter()
+        .filter(|file| {
+            needle_trigrams
+                .iter()
+                .any(|t| intersection_size(t, &file.trigrams) == t.len())
+        })
+        .map(|file| file.path.clone())
+        .collect())
+}
+
+/// Whether the file at `path` contains every one of `needles` verbatim.
+fn contains_all(path: &Path, needles: &[&str]) -> bool {
+    let Ok(bytes) = fs::read(path) else {
+        return false;
+    };
+    let text = String::from_utf8_lossy(&bytes);
+    needles.iter().all(|n| text.contains(n))
+}
+
 // Intersection size between 2 sorted trigram vecs
 fn intersection_size(a: &[u32], b: &[u32]) -> usize {
     let mut i = 0;
@@ -149,15 +245,16 @@ fn intersection_size(a: &[u32], b: &[u32]) -> usize {
 // ---------------------------------------------------------------------------------------------
 
 #[command]
-/// Build (or rebuild) the content index. Returns number of indexed files.
+/// Build (or rebuild) the content index for every root of `path` (a directory or
+/// `.glass-workspace` file). Returns number of indexed files.
 pub async fn build_content_index(path: String) -> tauri::Result<usize> {
-    let root = PathBuf::from(path);
-    if !root.exists() {
-        return Ok(0);
-    }
+    let roots = workspace::resolve_roots(Path::new(&path))?;
     let mut indices = CONTENT_INDICES.lock().unwrap();
-    let idx = ensure_index(&mut indices, &root)?;
-    Ok(idx.files.len())
+    let mut total = 0;
+    for root in &roots {
+        total += ensure_index(&mut indices, &root.path)?.files.len();
+    }
+    Ok(total)
 }
 
 #[derive(Deserialize)]
@@ -169,8 +266,11 @@ pub struct ContentQuery {
 }
 
 #[command]
-/// Query content index using trigram filter; returns file paths that likely contain the query.
-pub async fn query_content_index(params: ContentQuery) -> tauri::Result<Vec<String>> {
+/// Query content index using trigram filter; returns file paths (tagged by root) that contain
+/// every term and phrase of the query. The query supports the filter syntax from
+/// [`SearchQuery`]: terms need all of their trigrams, phrases and terms shorter than three
+/// bytes are confirmed by reading the file.
+pub async fn query_content_index(params: ContentQuery) -> tauri::Result<Vec<SearchHit>> {
     let ContentQuery {
         path,
         query,
@@ -178,49 +278,131 @@ pub async fn query_content_index(params: ContentQuery) -> tauri::Result<Vec<Stri
         limit,
     } = params;
 
-    let root = PathBuf::from(path);
-    if !root.exists() || query.len() < 3 {
+    let roots = workspace::resolve_roots(Path::new(&path))?;
+    if roots.is_empty() {
         return Ok(Vec::new());
     }
 
-    let mut indices = CONTENT_INDICES.lock().unwrap();
-    let idx = ensure_index(&mut indices, &root)?;
+    let parsed = SearchQuery::parse(&query);
 
-    // Prepare query trigrams
-    let query_trigrams = extract_trigrams(&query);
-    if query_trigrams.is_empty() {
+    // Every term and phrase must match. Each needs all of its own trigrams;
+    // phrases and needles too short for a trigram are then confirmed against
+    // the file text.
+    let needles: Vec<(&str, Vec<u32>)> = parsed
+        .terms
+        .iter()
+        .chain(&parsed.phrases)
+        .map(|n| (n.as_str(), extract_trigrams(n)))
+        .collect();
+    if needles.is_empty() {
         return Ok(Vec::new());
     }
-
-    // Score in parallel -------------------------------------------------------
-    let mut scored: Vec<(&String, usize)> = idx
-        .files
-        .par_iter()
-        .filter_map(|file| {
-            let s = intersection_size(&query_trigrams, &file.trigrams);
-            if s == 0 {
-                None
-            } else {
-                Some((&file.path, s))
-            }
-        })
+    let verbatim: Vec<&str> = parsed
+        .terms
+        .iter()
+        .filter(|t| t.len() < 3)
+        .chain(&parsed.phrases)
+        .map(String::as_str)
         .collect();
 
-    // Higher intersection first, shorter path tie-break, then lexicographic
-    scored.sort_by(|a, b| {
-        b.1.cmp(&a.1)
-            .then_with(|| a.0.len().cmp(&b.0.len()))
-            .then_with(|| a.0.cmp(b.0))
-    });
+    let mut candidates: Vec<(&WorkspaceRoot, String)> = {
+        let mut indices = CONTENT_INDICES.lock().unwrap();
+        for root in &roots {
+            ensure_index(&mut indices, &root.path)?;
+        }
+        roots
+            .iter()
+            .filter_map(|r| indices.iter().find(|i| i.root == r.path).map(|i| (r, i)))
+            .flat_map(|(r, idx)| idx.files.iter().map(move |f| (r, f)))
+            .par_bridge()
+            .filter(|(_, file)| parsed.matches_path(&file.path))
+            .filter(|(_, file)| {
+                needles
+                    .iter()
+                    .all(|(_, t)| intersection_size(t, &file.trigrams) == t.len())
+            })
+            .map(|(r, file)| (r, file.path.clone()))
+            .collect()
+    };
+
+    // Shorter path first, then lexicographic
+    candidates.sort_by(|a, b| a.1.len().cmp(&b.1.len()).then_with(|| a.1.cmp(&b.1)));
 
     let off = offset.unwrap_or(0);
     let lim = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if verbatim.is_empty() {
+        return Ok(candidates
+            .iter()
+            .skip(off)
+            .take(lim)
+            .map(|(r, p)| SearchHit::new(r, p))
+            .collect());
+    }
 
-    let sliced: Vec<String> = scored
+    // Read files only until the requested page is complete.
+    let mut hits = Vec::new();
+    for chunk in candidates.chunks(VERIFY_CHUNK) {
+        let confirmed: Vec<&(&WorkspaceRoot, String)> = chunk
+            .par_iter()
+            .filter(|(r, p)| contains_all(&r.path.join(p), &verbatim))
+            .collect();
+        hits.extend(confirmed);
+        if hits.len() >= off + lim {
+            break;
+        }
+    }
+    Ok(hits
         .into_iter()
         .skip(off)
         .take(lim)
-        .map(|(p, _)| p.clone())
-        .collect();
-    Ok(sliced)
+        .map(|(r, p)| SearchHit::new(r, p))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn every_term_and_phrase_must_match() {
+        let dir = test_support::temp_root("content", "and");
+        for (name, text) in [
+            ("only_foo.txt", "foo only\n"),
+            ("both.txt", "foo and bar\n"),
+            ("phrase.txt", "bar foo\n"),
+            // Every trigram of `bar foo`, but not the phrase.
+            ("scattered.txt", "bar fo foo\n"),
+        ] {
+            fs::write(dir.join(name), text).unwrap();
+        }
+        let search = |query: &str| {
+            let params = ContentQuery {
+                path: dir.to_string_lossy().into_owned(),
+                query: query.to_string(),
+                offset: None,
+                limit: None,
+            };
+            async move {
+                let mut paths: Vec<String> = query_content_index(params)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|hit| hit.path)
+                    .collect();
+                paths.sort();
+                paths
+            }
+        };
+
+        assert_eq!(
+            search("foo bar").await,
+            ["both.txt", "phrase.txt", "scattered.txt"]
+        );
+        assert_eq!(search("\"bar foo\"").await, ["phrase.txt"]);
+        // Too short for a trigram, still required.
+        assert_eq!(search("foo \"an\"").await, ["both.txt"]);
+        assert_eq!(search("foo -ext:txt").await, Vec::<String>::new());
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
@@ -0,0 +1,497 @@
+//! `.editorconfig` resolution (<https://editorconfig.org>).
+//!
+//! For a file, every `.editorconfig` from its directory upwards is read until
+//! one declares `root = true`. Sections whose glob matches the file apply
+//! from the outermost file inwards and top to bottom within a file, so later
+//! sections override earlier ones; `unset` removes a property again.
+//! Files are tiny and re-read on every lookup, so edits apply immediately.
+//!
+//! `resolve_editorconfig` returns the properties for a path; the save path
+//! (`write_file_text`) and the formatter pipeline apply them with
+//! [`EditorConfig::normalize`] and [`EditorConfig::encode`]; files are read
+//! back with [`read_text`].
+
+use anyhow::{anyhow, Context, Error as AnyError, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// `indent_style`
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum IndentStyle {
+    Tab,
+    Space,
+}
+
+/// `end_of_line`
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum EndOfLine {
+    Lf,
+    Crlf,
+    Cr,
+}
+
+impl EndOfLine {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EndOfLine::Lf => "\n",
+            EndOfLine::Crlf => "\r\n",
+            EndOfLine::Cr => "\r",
+        }
+    }
+}
+
+/// `charset`
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Charset {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-8-bom")]
+    Utf8Bom,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    #[serde(rename = "latin1")]
+    Latin1,
+}
+
+/// Properties in effect for one file; `None` where nothing applies.
+#[derive(Serialize, Default, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EditorConfig {
+    pub indent_style: Option<IndentStyle>,
+    /// Columns per indentation level (`indent_size = tab` resolved).
+    pub indent_size: Option<u32>,
+    pub tab_width: Option<u32>,
+    pub end_of_line: Option<EndOfLine>,
+    pub charset: Option<Charset>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+    /// Every property in effect as written, including ones not modelled
+    /// above (e.g. `max_line_length`).
+    pub properties: BTreeMap<String, String>,
+}
+
+#[tauri::command]
+/// Return the `.editorconfig` properties in effect for `path`.
+pub async fn resolve_editorconfig(path: String) -> tauri::Result<EditorConfig> {
+    Ok(resolve(Path::new(&path)))
+}
+
+/// One `[glob]` section with its properties (keys lowercased).
+struct Section {
+    glob: Option<GlobMatcher>,
+    properties: Vec<(String, String)>,
+}
+
+/// Properties for `path` (absolute) from the `.editorconfig` files above it.
+pub fn resolve(path: &Path) -> EditorConfig {
+    let mut files = Vec::new();
+    for dir in path.ancestors().skip(1) {
+        let Ok(text) = fs::read_to_string(dir.join(".editorconfig")) else {
+            continue;
+        };
+        let (root, sections) = parse(&text, dir);
+        files.push(sections);
+        if root {
+            break;
+        }
+    }
+
+    let mut properties: BTreeMap<String, String> = BTreeMap::new();
+    for sections in files.iter().rev() {
+        let matching = sections
+            .iter()
+            .filter(|s| s.glob.as_ref().is_some_and(|g| g.is_match(path)));
+        for (key, value) in matching.flat_map(|s| &s.properties) {
+            if value == "unset" {
+                properties.remove(key);
+            } else {
+                properties.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    from_properties(properties)
+}
+
+/// Typed view of the raw properties, with the spec's defaults between
+/// `indent_style`, `indent_size` and `tab_width`.
+fn from_properties(properties: BTreeMap<String, String>) -> EditorConfig {
+    let get = |key: &str| properties.get(key).map(String::as_str);
+    let number = |key: &str| {
+        get(key)
+            .and_then(|v| v.parse::<u32>().ok())
+            .filter(|n| *n > 0)
+    };
+    let flag = |key: &str| match get(key) {
+        Some("true") => Some(true),
+        Some("false") => Some(false),
+        _ => None,
+    };
+
+    let indent_style = match get("indent_style") {
+        Some("tab") => Some(IndentStyle::Tab),
+        Some("space") => Some(IndentStyle::Space),
+        _ => None,
+    };
+    let size_is_tab = get("indent_size") == Some("tab")
+        || (indent_style == Some(IndentStyle::Tab) && get("indent_size").is_none());
+    let tab_width = number("tab_width");
+    let indent_size = if size_is_tab {
+        tab_width
+    } else {
+        number("indent_size")
+    };
+    let end_of_line = match get("end_of_line") {
+        Some("lf") => Some(EndOfLine::Lf),
+        Some("crlf") => Some(EndOfLine::Crlf),
+        Some("cr") => Some(EndOfLine::Cr),
+        _ => None,
+    };
+    let charset = match get("charset") {
+        Some("utf-8") => Some(Charset::Utf8),
+        Some("utf-8-bom") => Some(Charset::Utf8Bom),
+        Some("utf-16le") => Some(Charset::Utf16Le),
+        Some("utf-16be") => Some(Charset::Utf16Be),
+        Some("latin1") => Some(Charset::Latin1),
+        _ => None,
+    };
+    EditorConfig {
+        indent_style,
+        indent_size,
+        tab_width: tab_width.or(indent_size),
+        end_of_line,
+        charset,
+        trim_trailing_whitespace: flag("trim_trailing_whitespace"),
+        insert_final_newline: flag("insert_final_newline"),
+        properties,
+    }
+}
+
+/// Read `path` as text, decoded per its byte-order mark or `charset` (see
+/// [`EditorConfig::decode`]) – the way the editor opens files.
+pub fn read_text(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    resolve(path).decode(&bytes)
+}
+
+impl EditorConfig {
+    /// Apply `end_of_line`, `trim_trailing_whitespace` and
+    /// `insert_final_newline` to `text`. Without `end_of_line`, line breaks
+    /// are kept and a final newline uses the text's first line break.
+    pub fn normalize(&self, text: &str) -> String {
+        let eol = match self.end_of_line {
+            Some(eol) => eol.as_str(),
+            None => lines(text)
+                .map(|(_, ending)| ending)
+                .find(|ending| !ending.is_empty())
+                .unwrap_or("\n"),
+        };
+        let mut out = String::with_capacity(text.len());
+        for (content, ending) in lines(text) {
+            if self.trim_trailing_whitespace == Some(true) {
+                out.push_str(content.trim_end());
+            } else {
+                out.push_str(content);
+            }
+            if !ending.is_empty() {
+                out.push_str(if self.end_of_line.is_some() {
+                    eol
+                } else {
+                    ending
+                });
+            }
+        }
+        match self.insert_final_newline {
+            Some(true) if !out.is_empty() && !out.ends_with(['\n', '\r']) => out.push_str(eol),
+            Some(false) => {
+                let len = out.trim_end_matches(['\n', '\r']).len();
+                out.truncate(len);
+            }
+            _ => {}
+        }
+        out
+    }
+
+    /// Encode `text` in `charset` (UTF-8 if unset); UTF-16 gets a BOM.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let bytes = match self.charset {
+            None | Some(Charset::Utf8) => text.as_bytes().to_vec(),
+            Some(Charset::Utf8Bom) => [UTF8_BOM, text.as_bytes()].concat(),
+            Some(Charset::Utf16Le) => std::iter::once('\u{feff}')
+                .chain(text.chars())
+                .collect::<String>()
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes)
+                .collect(),
+            Some(Charset::Utf16Be) => std::iter::once('\u{feff}')
+                .chain(text.chars())
+                .collect::<String>()
+                .encode_utf16()
+                .flat_map(u16::to_be_bytes)
+                .collect(),
+            Some(Charset::Latin1) => text
+                .chars()
+                .map(|ch| u8::try_from(ch).map_err(|_| anyhow!("`{ch}` cannot be saved as latin1")))
+                .collect::<Result<_>>()?,
+        };
+        Ok(bytes)
+    }
+
+    /// Decode file contents: by byte-order mark first, then as latin1 if that
+    /// is the `charset`, else as UTF-8.
+    pub fn decode(&self, bytes: &[u8]) -> Result<String> {
+        let utf16 = |bytes: &[u8], from: fn([u8; 2]) -> u16| {
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|pair| from([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16(&units).map_err(AnyError::from)
+        };
+        if let Some(rest) = bytes.strip_prefix(UTF8_BOM) {
+            return String::from_utf8(rest.to_vec()).map_err(AnyError::from);
+        }
+        if let Some(rest) = bytes.strip_prefix(&[0xff, 0xfe]) {
+            return utf16(rest, u16::from_le_bytes);
+        }
+        if let Some(rest) = bytes.strip_prefix(&[0xfe, 0xff]) {
+            return utf16(rest, u16::from_be_bytes);
+        }
+        match self.charset {
+            Some(Charset::Latin1) => Ok(bytes.iter().map(|&b| char::from(b)).collect()),
+            _ => String::from_utf8(bytes.to_vec()).map_err(AnyError::from),
+        }
+    }
+}
+
+const UTF8_BOM: &[u8] = &[0xef, 0xbb, 0xbf];
+
+/// Lines of `text` with their line break (`\n`, `\r\n` or `\r`; empty for an
+/// unterminated last line).
+fn lines(text: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let (line, next) = match rest.find(['\n', '\r']) {
+            Some(i) => {
+                let len = if rest[i..].starts_with("\r\n") { 2 } else { 1 };
+                ((&rest[..i], &rest[i..i + len]), &rest[i + len..])
+            }
+            None => ((rest, ""), ""),
+        };
+        rest = next;
+        Some(line)
+    })
+}
+
+/// Parse a `.editorconfig` in `dir`: whether it is a root file, and its
+/// sections with globs anchored at `dir`. Invalid lines are skipped.
+fn parse(text: &str, dir: &Path) -> (bool, Vec<Section>) {
+    let mut root = false;
+    let mut sections: Vec<Section> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(pattern) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push(Section {
+                glob: section_glob(pattern, dir),
+                properties: Vec::new(),
+            });
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+        // Values are case-insensitive except for unknown properties, which
+        // are kept for the frontend as written.
+        let value = match key.as_str() {
+            "indent_style"
+            | "indent_size"
+            | "tab_width"
+            | "end_of_line"
+            | "charset"
+            | "trim_trailing_whitespace"
+            | "insert_final_newline"
+            | "root" => value.to_lowercase(),
+            _ => value.to_string(),
+        };
+        match sections.last_mut() {
+            Some(section) => section.properties.push((key, value)),
+            None if key == "root" => root = value == "true",
+            None => {}
+        }
+    }
+    (root, sections)
+}
+
+/// Glob for a section header in `dir`: patterns containing `/` are relative
+/// to `dir`, others match file names at any depth below it.
+fn section_glob(pattern: &str, dir: &Path) -> Option<GlobMatcher> {
+    // `**` matches across `/` anywhere, `globset` only treats it as such as
+    // a whole path component – rewrite the common `dir/**.ext` form.
+    let pattern = expand_ranges(pattern)
+        .replace("**", "**/*")
+        .replace("**/*/", "**/");
+    let dir = globset::escape(&dir.to_string_lossy());
+    let dir = dir.trim_end_matches('/');
+    let glob = match pattern.strip_prefix('/') {
+        Some(rel) => format!("{dir}/{rel}"),
+        None if pattern.contains('/') => format!("{dir}/{pattern}"),
+        None => format!("{dir}/**/{pattern}"),
+    };
+    GlobBuilder::new(&glob)
+        .literal_separator(true)
+        .build()
+        .ok()
+        .map(|g| g.compile_matcher())
+}
+
+/// Rewrite numeric ranges like `{1..3}` as alternatives (`{1,2,3}`), which
+/// `globset` understands. Large ranges are left alone.
+fn expand_ranges(pattern: &str) -> String {
+    let mut out = String::with_capacity(pattern.len());
+    let mut rest = pattern;
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}').map(|i| open + i) else {
+            break;
+        };
+        out.push_str(&rest[..open]);
+        let inner = &rest[open + 1..close];
+        let range = inner
+            .split_once("..")
+            .and_then(|(a, b)| Some((a.parse::<i64>().ok()?, b.parse::<i64>().ok()?)));
+        match range {
+            Some((a, b)) if a <= b && b - a <= 1000 => {
+                let items: Vec<String> = (a..=b).map(|n| n.to_string()).collect();
+                out.push('{');
+                out.push_str(&items.join(","));
+                out.push('}');
+            }
+            _ => out.push_str(&rest[open..=close]),
+        }
+        rest = &rest[close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_cascade_from_the_root_file_inwards() {
+        let dir = std::env::temp_dir().join(format!("glass-editorconfig-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("repo/web/src")).unwrap();
+        let dir = dir.canonicalize().unwrap();
+        // Above the root file – never read.
+        fs::write(dir.join(".editorconfig"), "[*]\ncharset = latin1\n").unwrap();
+        fs::write(
+            dir.join("repo/.editorconfig"),
+            "root = true\n\n\
+             [*]\nindent_style = space\nindent_size = 4\nend_of_line = LF\nmax_line_length = 100\n\
+             [*.{md,txt}]\ntrim_trailing_whitespace = false\n\
+             [Makefile]\nindent_style = tab\nindent_size = tab\ntab_width = 8\n\
+             [/web/**.ts]\nindent_size = 2\ninsert_final_newline = true\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("repo/web/.editorconfig"),
+            "[*.ts]\nend_of_line = unset\n",
+        )
+        .unwrap();
+
+        let ts = resolve(&dir.join("repo/web/src/app.ts"));
+        assert_eq!(ts.indent_style, Some(IndentStyle::Space));
+        assert_eq!((ts.indent_size, ts.tab_width), (Some(2), Some(2)));
+        assert_eq!(ts.end_of_line, None);
+        assert_eq!(ts.insert_final_newline, Some(true));
+        assert_eq!(ts.charset, None);
+        assert_eq!(ts.properties["max_line_length"], "100");
+
+        let make = resolve(&dir.join("repo/sub/Makefile"));
+        assert_eq!(make.indent_style, Some(IndentStyle::Tab));
+        assert_eq!((make.indent_size, make.tab_width), (Some(8), Some(8)));
+        assert_eq!(make.end_of_line, Some(EndOfLine::Lf));
+
+        // `/web/**.ts` is anchored at the file's directory.
+        let other = resolve(&dir.join("repo/lib/web/x.ts"));
+        assert_eq!(other.indent_size, Some(4));
+        assert_eq!(
+            resolve(&dir.join("repo/notes.md")).trim_trailing_whitespace,
+            Some(false)
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn files_are_read_in_their_charset() {
+        let dir = std::env::temp_dir().join(format!("glass-charset-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(".editorconfig"),
+            "root = true\n[*.txt]\ncharset = latin1\n",
+        )
+        .unwrap();
+        fs::write(dir.join("latin1.txt"), [b'c', b'a', b'f', 0xe9]).unwrap();
+        fs::write(dir.join("utf8.md"), "café").unwrap();
+
+        assert_eq!(read_text(&dir.join("latin1.txt")).unwrap(), "café");
+        assert_eq!(read_text(&dir.join("utf8.md")).unwrap(), "café");
+        assert!(read_text(&dir.join("missing.txt")).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn normalize_and_encode_apply_the_properties() {
+        let config = EditorConfig {
+            end_of_line: Some(EndOfLine::Crlf),
+            trim_trailing_whitespace: Some(true),
+            insert_final_newline: Some(true),
+            charset: Some(Charset::Utf16Le),
+            ..EditorConfig::default()
+        };
+        let text = config.normalize("a  \nb\t\r\nc");
+        assert_eq!(text, "a\r\nb\r\nc\r\n");
+        let bytes = config.encode(&text).unwrap();
+        assert_eq!(&bytes[..4], &[0xff, 0xfe, b'a', 0]);
+        assert_eq!(config.decode(&bytes).unwrap(), text);
+
+        // Without `end_of_line` line breaks are kept.
+        let keep = EditorConfig {
+            insert_final_newline: Some(false),
+            ..EditorConfig::default()
+        };
+        assert_eq!(keep.normalize("a \r\nb\n\n"), "a \r\nb");
+        assert_eq!(
+            EditorConfig {
+                insert_final_newline: Some(true),
+                ..EditorConfig::default()
+            }
+            .normalize("a\r\nb"),
+            "a\r\nb\r\n"
+        );
+
+        let latin1 = EditorConfig {
+            charset: Some(Charset::Latin1),
+            ..EditorConfig::default()
+        };
+        assert_eq!(latin1.encode("é").unwrap(), [0xe9]);
+        assert_eq!(latin1.decode(&[0xe9]).unwrap(), "é");
+        assert!(latin1.encode("€").is_err());
+    }
+}
@@ -1,13 +1,16 @@
 //! File-system related Tauri commands
-//! Currently only exposes `read_dir_snapshot`, which returns a flattened tree
-//! suitable for virtual rendering on the frontend.
+//! Exposes `read_dir_snapshot`, which returns a flattened tree suitable for
+//! virtual rendering on the frontend, plus lazy children loading, file reads
+//! and writes (honouring `.editorconfig`) and a debounced watcher. Snapshot
+//! and watch accept a directory or a `.glass-workspace` file; nodes and
+//! change events are tagged by root name.
 
 use anyhow::Error as AnyError;
 use dirs_next::cache_dir;
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Component, Path, PathBuf};
 use std::sync::Mutex;
@@ -16,6 +19,10 @@ use tauri::{Emitter, Manager, Runtime, Window};
 use walkdir::{DirEntry, WalkDir};
 use xxhash_rust::xxh3::xxh3_64;
 
+use super::editorconfig;
+use super::workspace::{self, WorkspaceRoot};
+use crate::lsp::workspace_edit::write_atomic;
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct FsNode {
@@ -23,11 +30,16 @@ pub struct FsNode {
     pub name: String,
     pub depth: usize,
     pub kind: String, // "file" | "dir"
+    /// Name of the workspace root the node belongs to
+    #[serde(default)]
+    pub root: String,
 }
 
 #[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct FsChange {
+    /// Name of the workspace root the changed paths belong to
+    pub root: String,
     pub paths: Vec<String>,
     pub kind: String,
 }
@@ -55,22 +67,54 @@ fn cache_file_for_root(root: &Path) -> Option<std::path::PathBuf> {
 #[tauri::command]
 /// Return a flattened directory snapshot up to the requested depth.
 /// depth = 0 => only root path itself
+///
+/// For a `.glass-workspace` file every root becomes a depth-0 `dir` node with
+/// its own snapshot nested one level below.
 pub async fn read_dir_snapshot(path: String, depth: usize) -> tauri::Result<Vec<FsNode>> {
-    let root = PathBuf::from(path);
-    if !root.exists() {
-        return Ok(vec![]);
+    let path = PathBuf::from(path);
+    let roots = workspace::resolve_roots(&path)?;
+    if !workspace::is_workspace_file(&path) {
+        return Ok(roots
+            .first()
+            .map(|root| snapshot_root(root, depth))
+            .unwrap_or_default());
+    }
+
+    let mut result = Vec::new();
+    for root in &roots {
+        result.push(FsNode {
+            id: root.path.to_string_lossy().into_owned(),
+            name: root.name.clone(),
+            depth: 0,
+            kind: "dir".into(),
+            root: root.name.clone(),
+        });
+        result.extend(snapshot_root(root, depth).into_iter().map(|mut node| {
+            node.depth += 1;
+            node
+        }));
     }
+    Ok(result)
+}
+
+/// Snapshot of a single root, served from the on-disk cache when fresh.
+fn snapshot_root(ws_root: &WorkspaceRoot, depth: usize) -> Vec<FsNode> {
+    let root = &ws_root.path;
 
     // Try fast path via cached snapshot
-    if let Ok(meta) = fs::metadata(&root) {
+    if let Ok(meta) = fs::metadata(root) {
         if let Ok(modified) = meta.modified() {
             if let Ok(sec) = modified.duration_since(SystemTime::UNIX_EPOCH) {
                 let mtime = sec.as_secs();
-                if let Some(cache_path) = cache_file_for_root(&root) {
+                if let Some(cache_path) = cache_file_for_root(root) {
                     if let Ok(bytes) = fs::read(&cache_path) {
                         if let Ok(cache) = serde_json::from_slice::<SnapshotCache>(&bytes) {
                             if cache.mtime == mtime {
-                                return Ok(cache.nodes);
+                                let mut nodes = cache.nodes;
+                                for node in &mut nodes {
+                                    node.root.clone_from(&ws_root.name);
+                                }
+                                return nodes;
                             }
                         }
                     }
@@ -81,7 +125,7 @@ pub async fn read_dir_snapshot(path: String, depth: usize) -> tauri::Result<Vec<
 
     let mut result = Vec::new();
 
-    for entry in WalkDir::new(&root)
+    for entry in WalkDir::new(root)
         .max_depth(depth + 1) // WalkDir depth is 1-based
         .into_iter()
         .filter_entry(|e| !is_hidden(e))
@@ -89,7 +133,7 @@ pub async fn read_dir_snapshot(path: String, depth: usize) -> tauri::Result<Vec<
     {
         let rel_path = entry
             .path()
-            .strip_prefix(&root)
+            .strip_prefix(root)
             .unwrap_or_else(|_| Path::new(""));
         let depth = rel_path.components().count();
         let name = entry.file_name().to_string_lossy().into_owned();
@@ -106,15 +150,16 @@ pub async fn read_dir_snapshot(path: String, depth: usize) -> tauri::Result<Vec<
             } else {
                 "file".into()
             },
+            root: ws_root.name.clone(),
         });
     }
 
     // Persist to cache for next launch
-    if let Ok(meta) = fs::metadata(&root) {
+    if let Ok(meta) = fs::metadata(root) {
         if let Ok(modified) = meta.modified() {
             if let Ok(sec) = modified.duration_since(SystemTime::UNIX_EPOCH) {
                 let mtime = sec.as_secs();
-                if let Some(cache_path) = cache_file_for_root(&root) {
+                if let Some(cache_path) = cache_file_for_root(root) {
                     // Attempt to write but ignore errors
                     let _ =
                         fs::create_dir_all(cache_path.parent().unwrap_or_else(|| Path::new("/")));
@@ -130,7 +175,7 @@ pub async fn read_dir_snapshot(path: String, depth: usize) -> tauri::Result<Vec<
         }
     }
 
-    Ok(result)
+    result
 }
 
 #[tauri::command]
@@ -145,6 +190,12 @@ pub async fn read_dir_children(path: String) -> tauri::Result<Vec<FsNode>> {
         return Ok(vec![]);
     }
 
+    // Tag children with the owning root of the current workspace, if any
+    let roots = workspace::current_roots();
+    let root_name = workspace::root_for_path(&roots, &root)
+        .map(|r| r.name.clone())
+        .unwrap_or_default();
+
     let mut nodes = Vec::new();
 
     for entry in stdfs::read_dir(&root).map_err(AnyError::from)? {
@@ -165,6 +216,7 @@ pub async fn read_dir_children(path: String) -> tauri::Result<Vec<FsNode>> {
             } else {
                 "file".into()
             },
+            root: root_name.clone(),
         });
     }
 
@@ -172,11 +224,23 @@ pub async fn read_dir_children(path: String) -> tauri::Result<Vec<FsNode>> {
 }
 
 #[tauri::command]
-/// Read the entire file as UTF-8 text and return it to the frontend.
+/// Read the entire file as text and return it to the frontend, decoded per
+/// its byte-order mark or `.editorconfig` `charset` (UTF-8 otherwise).
 /// Frontend should handle large files carefully – this is a simple helper for small/medium code files.
 pub async fn read_file_text(path: String) -> tauri::Result<String> {
-    use std::fs;
-    let text = fs::read_to_string(&path).map_err(AnyError::from)?;
+    Ok(editorconfig::read_text(Path::new(&path))?)
+}
+
+#[tauri::command]
+/// Save `text` to `path`, applying the file's `.editorconfig` on the way
+/// (line endings, trailing whitespace, final newline, charset). The file is
+/// written with [`write_atomic`], so a failed save does not leave it half
+/// written. Returns the text as written so the editor can update its buffer.
+pub async fn write_file_text(path: String, text: String) -> tauri::Result<String> {
+    let config = editorconfig::resolve(Path::new(&path));
+    let text = config.normalize(&text);
+    let bytes = config.encode(&text)?;
+    write_atomic(Path::new(&path), &bytes)?;
     Ok(text)
 }
 
@@ -185,7 +249,7 @@ pub async fn read_file_text(path: String) -> tauri::Result<String> {
 const IGNORED_DIRS: &[&str] = &["node_modules", ".git", "target"];
 
 /// Fast check whether a path lives inside an ignored directory.
-fn should_ignore(path: &Path) -> bool {
+pub fn should_ignore(path: &Path) -> bool {
     for comp in path.components() {
         if let Component::Normal(os) = comp {
             if let Some(s) = os.to_str() {
@@ -198,16 +262,47 @@ fn should_ignore(path: &Path) -> bool {
     false
 }
 
+/// Whether a path relative to a workspace root has a hidden (`.`-prefixed)
+/// component. The indexers' full walks skip those, so watcher updates must too.
+pub fn is_hidden_rel(rel: &Path) -> bool {
+    rel.components()
+        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+}
+
+/// Files a watcher event for `path` stands for: the file itself, every
+/// non-hidden, non-ignored file below a directory, or nothing if it is gone.
+pub fn files_under(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return path
+            .is_file()
+            .then(|| path.to_path_buf())
+            .into_iter()
+            .collect();
+    }
+    WalkDir::new(path)
+        .into_iter()
+        .filter_entry(|e| {
+            e.depth() == 0
+                || !(e.file_name().to_string_lossy().starts_with('.') || should_ignore(e.path()))
+        })
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .map(DirEntry::into_path)
+        .collect()
+}
+
 // -----------------------------
 // File-system watcher
 // -----------------------------
 
 static WATCHERS: Lazy<Mutex<Vec<RecommendedWatcher>>> = Lazy::new(|| Mutex::new(Vec::new()));
-// Accumulate events across short window to avoid flooding the frontend.
-static EVENT_ACCUM: Lazy<Mutex<(HashSet<String>, Instant)>> =
-    Lazy::new(|| Mutex::new((HashSet::new(), Instant::now())));
+// Accumulate events (grouped by root name) across short window to avoid flooding the frontend.
+type EventAccum = (HashMap<String, HashSet<String>>, Instant);
+static EVENT_ACCUM: Lazy<Mutex<EventAccum>> =
+    Lazy::new(|| Mutex::new((HashMap::new(), Instant::now())));
 
 /// Flush accumulated fs paths if the debounce window has elapsed.
+/// Emits one `fs:change` event per workspace root.
 fn flush_changes<R: Runtime>(app: &tauri::AppHandle<R>) {
     const DEBOUNCE_MS: u128 = 120;
     let mut guard = EVENT_ACCUM.lock().unwrap();
@@ -218,20 +313,68 @@ fn flush_changes<R: Runtime>(app: &tauri::AppHandle<R>) {
     if guard.0.is_empty() {
         return;
     }
-    let paths: Vec<String> = guard.0.drain().collect();
+    let batches: Vec<(String, HashSet<String>)> = guard.0.drain().collect();
     guard.1 = Instant::now();
     drop(guard);
-    let payload = FsChange {
-        paths,
-        kind: "Batch".into(),
-    };
-    let _ = app.emit("fs:change", payload);
+    for (root, paths) in batches {
+        let payload = FsChange {
+            root,
+            paths: paths.into_iter().collect(),
+            kind: "Batch".into(),
+        };
+        let _ = app.emit("fs:change", payload);
+    }
+}
+
+/// Filter a raw watcher event and add its paths to the accumulator.
+fn accumulate_event<R: Runtime>(
+    app: &tauri::AppHandle<R>,
+    roots: &[WorkspaceRoot],
+    event: notify::Event,
+) {
+    // Filter out ignored directories to cut down chatter
+    let filtered: Vec<PathBuf> = event
+        .paths
+        .into_iter()
+        .filter(|p| !should_ignore(p))
+        .collect();
+
+    if filtered.is_empty() {
+        return;
+    }
+
+    // Keep backend indices fresh without a full rebuild
+    super::symbol_indexer::update_paths(&filtered);
+    super::content_indexer::update_paths(&filtered);
+    let tasks_app = app.clone();
+    super::task_scanner::update_paths(&filtered, move || {
+        let _ = tasks_app.emit("tasks:change", ());
+    });
+
+    // Add unique paths to accumulator, grouped by owning root
+    {
+        let mut guard = EVENT_ACCUM.lock().unwrap();
+        for p in &filtered {
+            let root = workspace::root_for_path(roots, p)
+                .map(|r| r.name.clone())
+                .unwrap_or_default();
+            guard
+                .0
+                .entry(root)
+                .or_default()
+                .insert(p.to_string_lossy().into_owned());
+        }
+    }
+
+    flush_changes(app);
 }
 
 #[tauri::command]
+/// Watch every root of `path` (a directory or `.glass-workspace` file) and emit
+/// debounced `fs:change` events tagged by root.
 pub async fn start_fs_watch<R: Runtime>(window: Window<R>, path: String) -> tauri::Result<()> {
-    let root = PathBuf::from(&path);
-    if !root.exists() {
+    let roots = workspace::resolve_roots(Path::new(&path))?;
+    if roots.is_empty() {
         return Ok(());
     }
 
@@ -240,29 +383,10 @@ pub async fn start_fs_watch<R: Runtime>(window: Window<R>, path: String) -> taur
 
     // build watcher – prefer native platform watcher; fall back to poll watcher w/ low frequency
     let app_handle_cb = app_handle_main.clone();
+    let roots_cb = roots.clone();
     let callback = move |res: Result<notify::Event, notify::Error>| {
         if let Ok(event) = res {
-            // Filter out ignored directories to cut down chatter
-            let filtered: Vec<String> = event
-                .paths
-                .into_iter()
-                .filter(|p| !should_ignore(p))
-                .map(|p| p.to_string_lossy().into_owned())
-                .collect();
-
-            if filtered.is_empty() {
-                return;
-            }
-
-            // Add unique paths to accumulator
-            {
-                let mut guard = EVENT_ACCUM.lock().unwrap();
-                for p in &filtered {
-                    guard.0.insert(p.clone());
-                }
-            }
-
-            flush_changes(&app_handle_cb);
+            accumulate_event(&app_handle_cb, &roots_cb, event);
         }
     };
 
@@ -273,27 +397,10 @@ pub async fn start_fs_watch<R: Runtime>(window: Window<R>, path: String) -> taur
         Err(e) => {
             eprintln!("Native watcher unavailable, falling back to polling: {e}");
             let app_handle_poll = app_handle_main.clone();
+            let roots_poll = roots.clone();
             let poll_cb = move |res: Result<notify::Event, notify::Error>| {
                 if let Ok(event) = res {
-                    let filtered: Vec<String> = event
-                        .paths
-                        .into_iter()
-                        .filter(|p| !should_ignore(p))
-                        .map(|p| p.to_string_lossy().into_owned())
-                        .collect();
-
-                    if filtered.is_empty() {
-                        return;
-                    }
-
-                    {
-                        let mut guard = EVENT_ACCUM.lock().unwrap();
-                        for p in &filtered {
-                            guard.0.insert(p.clone());
-                        }
-                    }
-
-                    flush_changes(&app_handle_poll);
+                    accumulate_event(&app_handle_poll, &roots_poll, event);
                 }
             };
             RecommendedWatcher::new(
@@ -304,11 +411,50 @@ pub async fn start_fs_watch<R: Runtime>(window: Window<R>, path: String) -> taur
         }
     };
 
-    watcher
-        .watch(&root, RecursiveMode::Recursive)
-        .map_err(AnyError::from)?;
+    for root in &roots {
+        watcher
+            .watch(&root.path, RecursiveMode::Recursive)
+            .map_err(AnyError::from)?;
+    }
 
     WATCHERS.lock().unwrap().push(watcher);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn save_applies_editorconfig_and_replaces_the_file() {
+        let dir = test_support::temp_root("fs", "save");
+        fs::write(
+            dir.join(".editorconfig"),
+            "root = true\n[*.txt]\nend_of_line = crlf\ntrim_trailing_whitespace = true\n\
+             insert_final_newline = true\ncharset = latin1\n",
+        )
+        .unwrap();
+        let path = dir.join("notes.txt");
+        let path_str = path.to_string_lossy().into_owned();
+
+        let saved = write_file_text(path_str.clone(), "café  \nx".into())
+            .await
+            .unwrap();
+        assert_eq!(saved, "café\r\nx\r\n");
+        assert_eq!(fs::read(&path).unwrap(), b"caf\xe9\r\nx\r\n");
+        assert_eq!(read_file_text(path_str.clone()).await.unwrap(), saved);
+
+        // Text the charset cannot hold fails before anything is written.
+        assert!(write_file_text(path_str, "€".into()).await.is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"caf\xe9\r\nx\r\n");
+        let mut names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        names.sort();
+        assert_eq!(names, [".editorconfig", "notes.txt"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
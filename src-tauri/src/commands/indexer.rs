@@ -5,8 +5,10 @@
 // memory and serialises them to the OS cache directory. Subsequent
 // launches restore instantly if the workspace mtime hasn’t changed.
 //
-// The search is a case-insensitive substring filter limited to the
-// first 1000 matches ordered by path length → lexicographic.
+// The search is a case-insensitive fuzzy filter ordered by score →
+// path length → lexicographic. Queries are parsed by `search_query`,
+// so path/ext/lang filters, globs and negations narrow the candidates
+// before scoring.
 //
 // Further iterations can extend this with trigram indexes, content
 // search and incremental updates.
@@ -27,6 +29,9 @@ use tauri::command;
 use walkdir::{DirEntry, WalkDir};
 use xxhash_rust::xxh3::xxh3_64;
 
+use super::search_query::{SearchHit, SearchQuery};
+use super::workspace::{self, WorkspaceRoot};
+
 const DEFAULT_PAGE_SIZE: usize = 150;
 
 #[derive(Serialize, Deserialize)]
@@ -42,7 +47,7 @@ struct Index {
     paths: Vec<String>,
 }
 
-/// Global cache of loaded indices (multi-root ready)
+/// Global cache of loaded indices, one per workspace root
 static INDICES: Lazy<Mutex<Vec<Index>>> = Lazy::new(|| Mutex::new(Vec::new()));
 
 fn cache_file_for_root(root: &Path) -> Option<PathBuf> {
@@ -81,7 +86,7 @@ fn ensure_index<'a>(
     }
 
     // Remove any stale copies for this root before (re)building.
-    indices.retain(|idx| idx.root == root && idx.mtime == mtime);
+    indices.retain(|idx| idx.root != root);
 
     // Attempt to load snapshot from cache
     if let Some(cache_path) = cache_file_for_root(root) {
@@ -141,23 +146,28 @@ fn ensure_index<'a>(
 }
 
 #[command]
-/// Build (or rebuild) the index for `path`. Returns number of files indexed.
+/// Build (or rebuild) the index for `path` (a directory or `.glass-workspace` file).
+/// Returns number of files indexed across all roots.
 pub async fn build_index(path: String) -> tauri::Result<usize> {
     info!("[WORKSPACE-SEARCH] Building index for path: {}", &path);
-    let path_clone = path.clone(); // Clone before moving
-    let root = PathBuf::from(path);
-    if !root.exists() {
-        warn!("[WORKSPACE-SEARCH] Path does not exist: {}", path_clone);
+    let roots = workspace::resolve_roots(Path::new(&path))?;
+    if roots.is_empty() {
+        warn!("[WORKSPACE-SEARCH] Path does not exist: {}", path);
         return Ok(0);
     }
     let mut indices = INDICES.lock().unwrap();
-    let idx = ensure_index(&mut indices, &root)?;
-    info!(
-        "[WORKSPACE-SEARCH] Index built with {} files for {}",
-        idx.paths.len(),
-        path_clone
-    );
-    Ok(idx.paths.len())
+    let mut total = 0;
+    for root in &roots {
+        let idx = ensure_index(&mut indices, &root.path)?;
+        info!(
+            "[WORKSPACE-SEARCH] Index built with {} files for root '{}' ({})",
+            idx.paths.len(),
+            root.name,
+            root.path.display()
+        );
+        total += idx.paths.len();
+    }
+    Ok(total)
 }
 
 #[derive(Deserialize)]
@@ -168,9 +178,10 @@ pub struct QueryParams {
     limit: Option<usize>,
 }
 
-/// Query the index with pagination.
+/// Query the index with pagination. Results from all workspace roots are
+/// ranked together and tagged with their root.
 #[command]
-pub async fn query_index(params: QueryParams) -> tauri::Result<Vec<String>> {
+pub async fn query_index(params: QueryParams) -> tauri::Result<Vec<SearchHit>> {
     let QueryParams {
         path,
         query,
@@ -183,23 +194,28 @@ pub async fn query_index(params: QueryParams) -> tauri::Result<Vec<String>> {
         &query, &path, offset, limit
     );
 
-    // Clone path before moving it
-    let path_clone = path.clone();
-    let root = PathBuf::from(path);
-    if !root.exists() {
-        warn!(
-            "[WORKSPACE-SEARCH] Search path does not exist: {}",
-            path_clone
-        );
+    let roots = workspace::resolve_roots(Path::new(&path))?;
+    if roots.is_empty() {
+        warn!("[WORKSPACE-SEARCH] Search path does not exist: {}", path);
         return Ok(Vec::new());
     }
 
     let mut indices = INDICES.lock().unwrap();
-    let idx = ensure_index(&mut indices, &root)?;
+    for root in &roots {
+        ensure_index(&mut indices, &root.path)?;
+    }
+    // (root, index) pairs for every root of this workspace
+    let sources: Vec<(&WorkspaceRoot, &Index)> = roots
+        .iter()
+        .filter_map(|r| indices.iter().find(|i| i.root == r.path).map(|i| (r, i)))
+        .collect();
 
-    // For empty queries, return a limited set of initial results
-    if query.trim().is_empty() {
-        debug!("[WORKSPACE-SEARCH] Empty query, returning initial results");
+    let parsed = SearchQuery::parse(&query);
+
+    // For queries without free text (empty or filters only), return a limited
+    // set of initial results
+    if !parsed.has_text() {
+        debug!("[WORKSPACE-SEARCH] No free text, returning initial results");
         // Return initial files (sorted by path length) to show something
         // when the search box is first opened - respect the requested limit
         let limit_count = limit.unwrap_or(150);
@@ -207,19 +223,28 @@ pub async fn query_index(params: QueryParams) -> tauri::Result<Vec<String>> {
         // Add more detailed logging
         info!(
             "[WORKSPACE-SEARCH] Total indexed files: {}",
-            idx.paths.len()
+            sources.iter().map(|(_, i)| i.paths.len()).sum::<usize>()
         );
 
         // Sort paths by length so shortest (likely most relevant) paths come first
-        let mut sorted_paths = idx.paths.clone();
-        sorted_paths.sort_by_key(|path| path.len());
+        let mut sorted_paths: Vec<(&WorkspaceRoot, &String)> = sources
+            .iter()
+            .flat_map(|(r, idx)| idx.paths.iter().map(move |p| (*r, p)))
+            .filter(|(_, p)| parsed.matches_path(p))
+            .collect();
+        sorted_paths.sort_by_key(|(_, path)| path.len());
 
         // Take the first few sorted paths
-        let initial_results: Vec<String> = sorted_paths.iter().take(limit_count).cloned().collect();
+        let initial_results: Vec<SearchHit> = sorted_paths
+            .into_iter()
+            .skip(offset.unwrap_or(0))
+            .take(limit_count)
+            .map(|(r, p)| SearchHit::new(r, p))
+            .collect();
 
         // Log each result for debugging
-        for (i, path) in initial_results.iter().enumerate() {
-            debug!("[WORKSPACE-SEARCH] Result {}: {}", i, path);
+        for (i, hit) in initial_results.iter().enumerate() {
+            debug!("[WORKSPACE-SEARCH] Result {}: {}/{}", i, hit.root, hit.path);
         }
 
         info!(
@@ -230,33 +255,42 @@ pub async fn query_index(params: QueryParams) -> tauri::Result<Vec<String>> {
     }
 
     let matcher = SkimMatcherV2::default();
-    let q_lower = query.to_lowercase();
+    let terms: Vec<String> = parsed.terms.iter().map(|t| t.to_lowercase()).collect();
+    let phrases: Vec<String> = parsed.phrases.iter().map(|p| p.to_lowercase()).collect();
 
-    // Parallel scoring for large corpora
-    let mut scored: Vec<(&String, i64)> = idx
-        .paths
+    // Parallel scoring for large corpora – every term must match (fuzzy) and
+    // every phrase must appear verbatim in the path.
+    let mut scored: Vec<(&WorkspaceRoot, &String, i64)> = sources
         .par_iter()
-        .filter_map(|p| {
-            matcher.fuzzy_match(p, &q_lower).map(|score| {
-                let bonus = if p.ends_with(".rs")
-                    || p.ends_with(".ts")
-                    || p.ends_with(".tsx")
-                    || p.ends_with(".js")
-                {
-                    100
-                } else {
-                    0
-                };
-                (p, score + bonus)
-            })
+        .flat_map(|(r, idx)| idx.paths.par_iter().map(move |p| (*r, p)))
+        .filter(|(_, p)| parsed.matches_path(p))
+        .filter(|(_, p)| {
+            let lower = p.to_lowercase();
+            phrases.iter().all(|ph| lower.contains(ph.as_str()))
+        })
+        .filter_map(|(r, p)| {
+            let mut score = phrases.len() as i64 * 100;
+            for term in &terms {
+                score += matcher.fuzzy_match(p, term)?;
+            }
+            let bonus = if p.ends_with(".rs")
+                || p.ends_with(".ts")
+                || p.ends_with(".tsx")
+                || p.ends_with(".js")
+            {
+                100
+            } else {
+                0
+            };
+            Some((r, p, score + bonus))
         })
         .collect();
 
     // Highest score first, then shorter path, then lexicographic
     scored.sort_by(|a, b| {
-        b.1.cmp(&a.1)
-            .then_with(|| a.0.len().cmp(&b.0.len()))
-            .then_with(|| a.0.cmp(b.0))
+        b.2.cmp(&a.2)
+            .then_with(|| a.1.len().cmp(&b.1.len()))
+            .then_with(|| a.1.cmp(b.1))
     });
 
     let off = offset.unwrap_or(0);
@@ -265,11 +299,11 @@ pub async fn query_index(params: QueryParams) -> tauri::Result<Vec<String>> {
     // Count results before consuming the iterator
     let scored_len = scored.len();
 
-    let sliced: Vec<String> = scored
+    let sliced: Vec<SearchHit> = scored
         .into_iter()
         .skip(off)
         .take(lim)
-        .map(|(p, _)| p.clone())
+        .map(|(r, p, _)| SearchHit::new(r, p))
         .collect();
 
     info!(
@@ -4,7 +4,11 @@
 //! been removed. The frontend now invokes each command directly via JSON IPC,
 //! keeping the backend clear of unnecessary abstraction.
 
+// `.editorconfig` resolution, shared by the formatter and save paths
+pub mod editorconfig;
 pub mod fs;
+// Multi-root workspace model (`.glass-workspace` files)
+pub mod workspace;
 
 // ------------------------------
 // New workspace indexer commands
@@ -14,3 +18,9 @@ pub mod indexer;
 // Content search indexer
 pub mod content_indexer;
 pub mod logger;
+// Query language shared by both indexers
+pub mod search_query;
+// Tree-sitter symbol indexer ("Go to symbol")
+pub mod symbol_indexer;
+// TODO / FIXME scanner built on the content index
+pub mod task_scanner;
@@ -0,0 +1,375 @@
+// Search query language shared by the workspace indexers
+// ------------------------------------------------------
+// Parses the text typed into "Go to file…" and content search into
+// free-text terms plus path filters, e.g.
+//
+//     ext:rs path:src/commands lang:ts -test "exact phrase" src/**/*.tsx
+//
+// Supported syntax (whitespace separated, everything is AND-ed):
+//  • `foo`            free-text term (fuzzy for paths; for content, all of
+//                     its trigrams – terms under three bytes verbatim)
+//  • `"foo bar"`      quoted phrase, matched verbatim (spaces included)
+//  • `path:src/cmd`   relative path must contain `src/cmd`
+//  • `ext:rs`         file extension filter (repeatable → OR)
+//  • `lang:ts`        language filter, expands to the language's extensions
+//  • `src/**/*.rs`    glob include (any term containing `*`, `?` or `[`);
+//                     globs without a `/` are matched against the file name
+//  • `-term`          negation of any of the above. Negated free-text terms
+//                     and phrases exclude files whose *path* contains them.
+//
+// Filter values may be quoted as well (`path:"my dir"`). Path, extension and
+// glob filters are case-insensitive; terms and phrases keep their case so the
+// (case-sensitive) trigram index can use them as typed.
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::Serialize;
+
+use super::workspace::WorkspaceRoot;
+
+/// Known languages for `lang:` filters – (names, extensions).
+/// Unknown language names fall back to being treated as an extension.
+const LANGUAGES: &[(&[&str], &[&str])] = &[
+    (&["rust", "rs"], &["rs"]),
+    (&["typescript", "ts"], &["ts", "tsx", "mts", "cts"]),
+    (&["javascript", "js"], &["js", "jsx", "mjs", "cjs"]),
+    (&["python", "py"], &["py", "pyi"]),
+    (&["go", "golang"], &["go"]),
+    (&["c"], &["c", "h"]),
+    (&["cpp", "c++"], &["cpp", "cc", "cxx", "hpp", "hh", "hxx"]),
+    (&["java"], &["java"]),
+    (&["json"], &["json", "jsonc"]),
+    (&["toml"], &["toml"]),
+    (&["yaml", "yml"], &["yaml", "yml"]),
+    (&["markdown", "md"], &["md", "mdx"]),
+    (&["html"], &["html", "htm"]),
+    (&["css"], &["css", "scss", "less"]),
+    (&["shell", "sh", "bash"], &["sh", "bash", "zsh"]),
+];
+
+/// Extensions belonging to the language `name` (case-insensitive).
+pub fn extensions_for_language(name: &str) -> Vec<String> {
+    let name = name.to_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(names, _)| names.contains(&name.as_str()))
+        .map(|(_, exts)| exts.iter().map(|e| e.to_string()).collect())
+        .unwrap_or_else(|| vec![name])
+}
+
+/// Search result tagged with the workspace root it was found in.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    /// Root name, unique within the workspace.
+    pub root: String,
+    /// Absolute path of the root – join with `path` to open the file.
+    pub root_path: String,
+    /// Path relative to the root.
+    pub path: String,
+}
+
+impl SearchHit {
+    pub fn new(root: &WorkspaceRoot, path: &str) -> Self {
+        SearchHit {
+            root: root.name.clone(),
+            root_path: root.path.to_string_lossy().into_owned(),
+            path: path.to_string(),
+        }
+    }
+}
+
+/// Parsed search query. Build with [`SearchQuery::parse`].
+#[derive(Default)]
+pub struct SearchQuery {
+    /// Free-text terms, original case.
+    pub terms: Vec<String>,
+    /// Quoted phrases, original case.
+    pub phrases: Vec<String>,
+    exclude_terms: Vec<String>,
+    paths: Vec<String>,
+    exclude_paths: Vec<String>,
+    extensions: Vec<String>,
+    exclude_extensions: Vec<String>,
+    include_globs: Option<GlobSet>,
+    exclude_globs: Option<GlobSet>,
+}
+
+/// Raw token produced by [`tokenize`]; `quoted` marks values that came from
+/// a `"…"` section so they are never interpreted as filters or globs.
+struct Token {
+    negated: bool,
+    key: Option<String>,
+    value: String,
+    quoted: bool,
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        let Some(&first) = chars.peek() else { break };
+
+        let negated = first == '-';
+        if negated {
+            chars.next();
+        }
+
+        let mut word = String::new();
+        let mut key = None;
+        let mut quoted = false;
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            chars.next();
+            if c == '"' {
+                // Quoted section runs to the closing quote (or end of input).
+                quoted = true;
+                for q in chars.by_ref() {
+                    if q == '"' {
+                        break;
+                    }
+                    word.push(q);
+                }
+            } else if c == ':' && key.is_none() && !quoted && is_filter_key(&word) {
+                key = Some(std::mem::take(&mut word).to_lowercase());
+            } else {
+                word.push(c);
+            }
+        }
+
+        if word.is_empty() {
+            continue; // lone `-`, `""` or `ext:` without value
+        }
+        tokens.push(Token {
+            negated,
+            key,
+            value: word,
+            quoted,
+        });
+    }
+    tokens
+}
+
+fn is_filter_key(word: &str) -> bool {
+    matches!(word.to_lowercase().as_str(), "path" | "ext" | "lang")
+}
+
+fn is_glob(value: &str) -> bool {
+    value.contains(['*', '?', '['])
+}
+
+fn normalize_path(path: &str) -> String {
+    path.replace('\\', "/").to_lowercase()
+}
+
+/// Compile path globs relative to a root. Patterns without a `/` match the
+/// file name at any depth; invalid patterns are skipped. `None` if there are
+/// no patterns.
+pub fn build_globset(patterns: &[String], case_insensitive: bool) -> Option<GlobSet> {
+    if patterns.is_empty() {
+        return None;
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pat in patterns {
+        // `*.rs` should match at any depth – anchor name-only globs anywhere.
+        let pat = if pat.contains('/') {
+            pat.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{pat}")
+        };
+        if let Ok(glob) = GlobBuilder::new(&pat)
+            .case_insensitive(case_insensitive)
+            .literal_separator(true)
+            .build()
+        {
+            builder.add(glob);
+        }
+    }
+    builder.build().ok()
+}
+
+impl SearchQuery {
+    /// Parse the raw search box input. Never fails – anything that does not
+    /// look like a filter is treated as a free-text term.
+    pub fn parse(input: &str) -> Self {
+        let mut query = SearchQuery::default();
+        let mut include_globs = Vec::new();
+        let mut exclude_globs = Vec::new();
+
+        for token in tokenize(input) {
+            let Token {
+                negated,
+                key,
+                value,
+                quoted,
+            } = token;
+            match key.as_deref() {
+                Some("path") => {
+                    let v = normalize_path(&value);
+                    if negated {
+                        query.exclude_paths.push(v);
+                    } else {
+                        query.paths.push(v);
+                    }
+                }
+                Some("ext") => {
+                    let v = value.trim_start_matches('.').to_lowercase();
+                    if negated {
+                        query.exclude_extensions.push(v);
+                    } else {
+                        query.extensions.push(v);
+                    }
+                }
+                Some("lang") => {
+                    let exts = extensions_for_language(&value);
+                    if negated {
+                        query.exclude_extensions.extend(exts);
+                    } else {
+                        query.extensions.extend(exts);
+                    }
+                }
+                _ if !quoted && is_glob(&value) => {
+                    let v = value.replace('\\', "/");
+                    if negated {
+                        exclude_globs.push(v);
+                    } else {
+                        include_globs.push(v);
+                    }
+                }
+                _ if negated => query.exclude_terms.push(value.to_lowercase()),
+                _ if quoted => query.phrases.push(value),
+                _ => query.terms.push(value),
+            }
+        }
+
+        query.include_globs = build_globset(&include_globs, true);
+        query.exclude_globs = build_globset(&exclude_globs, true);
+        query
+    }
+
+    /// Whether the query contains any free text (terms or phrases).
+    pub fn has_text(&self) -> bool {
+        !self.terms.is_empty() || !self.phrases.is_empty()
+    }
+
+    /// Whether `rel_path` (relative to the workspace root) passes all path,
+    /// extension, glob and negation filters. Free-text terms are not checked
+    /// here – callers score those with their own matcher.
+    pub fn matches_path(&self, rel_path: &str) -> bool {
+        let path = normalize_path(rel_path);
+
+        if !self.paths.iter().all(|p| path.contains(p.as_str())) {
+            return false;
+        }
+        if self.exclude_paths.iter().any(|p| path.contains(p.as_str())) {
+            return false;
+        }
+
+        if !self.extensions.is_empty() || !self.exclude_extensions.is_empty() {
+            let file_name = path.rsplit('/').next().unwrap_or(&path);
+            let ext = file_name.rsplit_once('.').map(|(_, e)| e).unwrap_or("");
+            if !self.extensions.is_empty() && !self.extensions.iter().any(|e| e == ext) {
+                return false;
+            }
+            if self.exclude_extensions.iter().any(|e| e == ext) {
+                return false;
+            }
+        }
+
+        if let Some(globs) = &self.include_globs {
+            if !globs.is_match(&path) {
+                return false;
+            }
+        }
+        if let Some(globs) = &self.exclude_globs {
+            if globs.is_match(&path) {
+                return false;
+            }
+        }
+
+        !self.exclude_terms.iter().any(|t| path.contains(t.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenizer_splits_terms_phrases_filters_and_negations() {
+        let tokens = tokenize(r#"foo "bar baz" -qux PATH:"my dir" ext: -"no:key" url:x"#);
+        let tokens: Vec<_> = tokens
+            .iter()
+            .map(|t| (t.negated, t.key.as_deref(), t.value.as_str(), t.quoted))
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                (false, None, "foo", false),
+                (false, None, "bar baz", true),
+                (true, None, "qux", false),
+                (false, Some("path"), "my dir", true),
+                // `ext:` without a value is dropped.
+                (true, None, "no:key", true),
+                // Unknown keys stay part of the term.
+                (false, None, "url:x", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_sorts_tokens_into_text_and_filters() {
+        let query = SearchQuery::parse(r#"Foo "Exact *Phrase" -Skip ext:.RS lang:ts path:Src"#);
+        assert_eq!(query.terms, ["Foo"]);
+        // Quoted values are never globs, and keep their case.
+        assert_eq!(query.phrases, ["Exact *Phrase"]);
+        assert_eq!(query.exclude_terms, ["skip"]);
+        assert_eq!(query.extensions, ["rs", "ts", "tsx", "mts", "cts"]);
+        assert_eq!(query.paths, ["src"]);
+        assert!(query.has_text());
+        assert!(!SearchQuery::parse("ext:rs -foo").has_text());
+        // Unknown languages are treated as an extension.
+        assert_eq!(SearchQuery::parse("lang:Zig").extensions, ["zig"]);
+    }
+
+    #[test]
+    fn path_filters_combine() {
+        let query = SearchQuery::parse(r"path:src -path:gen ext:rs -lang:py -skip");
+        assert!(query.matches_path("src/main.rs"));
+        assert!(query.matches_path(r"Src\Lib.RS"));
+        assert!(!query.matches_path("tests/main.rs"));
+        assert!(!query.matches_path("src/gen/main.rs"));
+        assert!(!query.matches_path("src/main.py"));
+        assert!(!query.matches_path("src/skipped.rs"));
+
+        // Repeated extensions are OR-ed, negated ones always exclude.
+        let query = SearchQuery::parse("ext:rs ext:toml -ext:lock");
+        assert!(query.matches_path("Cargo.toml"));
+        assert!(!query.matches_path("Cargo.lock"));
+        assert!(!query.matches_path("README.md"));
+    }
+
+    #[test]
+    fn globs_match_names_at_any_depth_and_paths_from_the_root() {
+        let query = SearchQuery::parse("*.TSX -**/legacy/**");
+        assert!(query.matches_path("app.tsx"));
+        assert!(query.matches_path("src/ui/App.tsx"));
+        assert!(!query.matches_path("src/legacy/App.tsx"));
+        assert!(!query.matches_path("src/app.ts"));
+
+        let query = SearchQuery::parse("src/*.rs");
+        assert!(query.matches_path("src/main.rs"));
+        assert!(!query.matches_path("src/bin/main.rs"));
+        assert!(!query.matches_path("lib/src/main.rs"));
+
+        // Case-sensitive for the registry's file patterns.
+        let set = build_globset(&["*.rs".into()], false).unwrap();
+        assert!(set.is_match("a/b.rs") && !set.is_match("a/b.RS"));
+        assert!(build_globset(&[], true).is_none());
+    }
+}
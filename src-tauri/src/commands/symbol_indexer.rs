@@ -0,0 +1,964 @@
+// Symbol indexer for Glass-IDE ("Go to symbol in workspace")
+// -----------------------------------------------------------
+// Extracts definitions (functions, types, methods, constants…) from source
+// files using tree-sitter grammars compiled into the backend.
+// Design notes:
+//  • Supported languages: Rust, TypeScript/TSX (also used for JS), Python, Go.
+//  • One in-memory index per workspace root, keyed by relative file path.
+//  • Each symbol carries kind, container (enclosing type/impl/module) and
+//    LSP-style ranges with UTF-16 columns, so results can be handed to the
+//    editor or an LSP client unchanged.
+//  • Function bodies are not descended into – locals are not indexed.
+//  • Kept fresh incrementally: the fs watcher calls `update_paths`, which
+//    re-parses on a worker thread, and `build_symbol_index` only re-parses
+//    files whose mtime changed.
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use log::{debug, info};
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::SystemTime;
+use tauri::command;
+use tree_sitter::{Language, Node, Parser};
+use walkdir::{DirEntry, WalkDir};
+
+use super::fs::{files_under, is_hidden_rel, should_ignore};
+use super::workspace::{self, WorkspaceRoot};
+
+const MAX_FILE_SIZE: u64 = 1024 * 1024; // 1 MB per file guard
+const DEFAULT_PAGE_SIZE: usize = 150;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SymbolKind {
+    Module,
+    Class,
+    Struct,
+    Interface,
+    Enum,
+    EnumMember,
+    Function,
+    Method,
+    Field,
+    Constant,
+    Variable,
+    TypeAlias,
+    Macro,
+}
+
+impl SymbolKind {
+    /// Lowercase name used for `kinds` filters.
+    fn as_str(self) -> &'static str {
+        match self {
+            SymbolKind::Module => "module",
+            SymbolKind::Class => "class",
+            SymbolKind::Struct => "struct",
+            SymbolKind::Interface => "interface",
+            SymbolKind::Enum => "enum",
+            SymbolKind::EnumMember => "enummember",
+            SymbolKind::Function => "function",
+            SymbolKind::Method => "method",
+            SymbolKind::Field => "field",
+            SymbolKind::Constant => "constant",
+            SymbolKind::Variable => "variable",
+            SymbolKind::TypeAlias => "typealias",
+            SymbolKind::Macro => "macro",
+        }
+    }
+
+    /// Numeric `SymbolKind` as defined by the LSP specification.
+    pub fn lsp_kind(self) -> u32 {
+        match self {
+            SymbolKind::Module => 2,
+            SymbolKind::Class => 5,
+            SymbolKind::Method => 6,
+            SymbolKind::Field => 8,
+            SymbolKind::Enum => 10,
+            SymbolKind::Interface => 11,
+            SymbolKind::Function | SymbolKind::Macro => 12,
+            SymbolKind::Variable => 13,
+            SymbolKind::Constant => 14,
+            SymbolKind::EnumMember => 22,
+            SymbolKind::Struct => 23,
+            SymbolKind::TypeAlias => 26,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: u32,
+    /// UTF-16 code unit offset, as in LSP
+    pub character: u32,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Name of the enclosing symbol (type, impl target, module…), if any
+    pub container: Option<String>,
+    /// Full extent of the definition
+    pub range: Range,
+    /// Extent of the name identifier
+    pub selection_range: Range,
+}
+
+/// Languages with a grammar compiled into the backend.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Lang {
+    Rust,
+    TypeScript,
+    Tsx,
+    Python,
+    Go,
+}
+
+impl Lang {
+    pub fn from_path(path: &Path) -> Option<Lang> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "rs" => Some(Lang::Rust),
+            "ts" | "mts" | "cts" => Some(Lang::TypeScript),
+            // The TSX grammar is a superset that also handles plain JS/JSX.
+            "tsx" | "js" | "jsx" | "mjs" | "cjs" => Some(Lang::Tsx),
+            "py" | "pyi" => Some(Lang::Python),
+            "go" => Some(Lang::Go),
+            _ => None,
+        }
+    }
+
+    /// Grammar for an LSP language id.
+    pub fn from_language_id(language: &str) -> Option<Lang> {
+        match language {
+            "rust" => Some(Lang::Rust),
+            "typescript" => Some(Lang::TypeScript),
+            "typescriptreact" | "javascript" | "javascriptreact" => Some(Lang::Tsx),
+            "python" => Some(Lang::Python),
+            "go" => Some(Lang::Go),
+            _ => None,
+        }
+    }
+
+    pub fn grammar(self) -> Language {
+        match self {
+            Lang::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Lang::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Lang::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Lang::Python => tree_sitter_python::LANGUAGE.into(),
+            Lang::Go => tree_sitter_go::LANGUAGE.into(),
+        }
+    }
+}
+
+struct FileSymbols {
+    mtime: u128,
+    symbols: Vec<Symbol>,
+}
+
+struct SymbolIndex {
+    root: PathBuf,
+    files: HashMap<String, FileSymbols>, // relative path → symbols
+}
+
+static SYMBOL_INDICES: Lazy<Mutex<Vec<SymbolIndex>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+// ---------------------------------------------------------------------------------------------
+// Extraction
+// ---------------------------------------------------------------------------------------------
+
+/// Byte offsets of every line start, for byte → UTF-16 column conversion.
+struct LineIndex<'a> {
+    text: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(text: &'a str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex { text, starts }
+    }
+
+    fn position(&self, point: tree_sitter::Point) -> Position {
+        let line_start = self.starts.get(point.row).copied().unwrap_or(0);
+        let end = (line_start + point.column).min(self.text.len());
+        let character = self
+            .text
+            .get(line_start..end)
+            .map(|s| s.encode_utf16().count())
+            .unwrap_or(point.column);
+        Position {
+            line: point.row as u32,
+            character: character as u32,
+        }
+    }
+
+    fn range(&self, node: Node) -> Range {
+        Range {
+            start: self.position(node.start_position()),
+            end: self.position(node.end_position()),
+        }
+    }
+}
+
+/// What a syntax node contributes to the symbol tree.
+enum NodeRole<'t> {
+    /// A definition named by `name`. `descend` is false for function-like
+    /// symbols whose bodies only hold locals.
+    Symbol {
+        kind: SymbolKind,
+        name: Node<'t>,
+        descend: bool,
+    },
+    /// Not a symbol itself, but names the (type) container of its
+    /// children, e.g. Rust `impl Foo`.  Go receivers are resolved separately.
+    Container(String),
+    None,
+}
+
+fn node_text<'s>(node: Node, src: &'s str) -> &'s str {
+    node.utf8_text(src.as_bytes()).unwrap_or("")
+}
+
+/// Strip generics / pointer markers from a type name (`Foo<T>` → `Foo`).
+fn base_type_name(text: &str) -> String {
+    text.trim_start_matches(['&', '*'])
+        .split(['<', '['])
+        .next()
+        .unwrap_or(text)
+        .trim()
+        .to_string()
+}
+
+fn classify<'t>(lang: Lang, node: Node<'t>, in_type: bool, src: &str) -> NodeRole<'t> {
+    let name = node.child_by_field_name("name");
+    let symbol = |kind: SymbolKind, descend: bool| match name {
+        Some(name) => NodeRole::Symbol {
+            kind,
+            name,
+            descend,
+        },
+        None => NodeRole::None,
+    };
+    let callable = if in_type {
+        SymbolKind::Method
+    } else {
+        SymbolKind::Function
+    };
+
+    match lang {
+        Lang::Rust => match node.kind() {
+            "function_item" | "function_signature_item" => symbol(callable, false),
+            "struct_item" | "union_item" => symbol(SymbolKind::Struct, true),
+            "enum_item" => symbol(SymbolKind::Enum, true),
+            "enum_variant" => symbol(SymbolKind::EnumMember, false),
+            "trait_item" => symbol(SymbolKind::Interface, true),
+            "mod_item" => symbol(SymbolKind::Module, true),
+            "const_item" | "static_item" => symbol(SymbolKind::Constant, false),
+            "type_item" => symbol(SymbolKind::TypeAlias, false),
+            "macro_definition" => symbol(SymbolKind::Macro, false),
+            "field_declaration" => symbol(SymbolKind::Field, false),
+            "impl_item" => match node.child_by_field_name("type") {
+                Some(ty) => NodeRole::Container(base_type_name(node_text(ty, src))),
+                None => NodeRole::None,
+            },
+            _ => NodeRole::None,
+        },
+        Lang::TypeScript | Lang::Tsx => match node.kind() {
+            "function_declaration" | "generator_function_declaration" => symbol(callable, false),
+            "class_declaration" | "abstract_class_declaration" | "class" => {
+                symbol(SymbolKind::Class, true)
+            }
+            "interface_declaration" => symbol(SymbolKind::Interface, true),
+            "enum_declaration" => symbol(SymbolKind::Enum, false),
+            "type_alias_declaration" => symbol(SymbolKind::TypeAlias, false),
+            "internal_module" | "module" => symbol(SymbolKind::Module, true),
+            "method_definition" | "method_signature" | "abstract_method_signature" => {
+                symbol(SymbolKind::Method, false)
+            }
+            "public_field_definition" | "property_signature" => symbol(SymbolKind::Field, false),
+            "variable_declarator" => {
+                let is_fn = node
+                    .child_by_field_name("value")
+                    .map(|v| matches!(v.kind(), "arrow_function" | "function_expression"))
+                    .unwrap_or(false);
+                let is_const = node
+                    .parent()
+                    .and_then(|p| p.child(0))
+                    .map(|kw| kw.kind() == "const")
+                    .unwrap_or(false);
+                // Destructuring patterns have no single name – skip them.
+                if name.map(|n| n.kind() != "identifier").unwrap_or(true) {
+                    NodeRole::None
+                } else if is_fn {
+                    symbol(callable, false)
+                } else if is_const {
+                    symbol(SymbolKind::Constant, false)
+                } else {
+                    symbol(SymbolKind::Variable, false)
+                }
+            }
+            _ => NodeRole::None,
+        },
+        Lang::Python => match node.kind() {
+            "function_definition" => symbol(callable, false),
+            "class_definition" => symbol(SymbolKind::Class, true),
+            _ => NodeRole::None,
+        },
+        Lang::Go => match node.kind() {
+            "function_declaration" => symbol(SymbolKind::Function, false),
+            "method_declaration" => symbol(SymbolKind::Method, false),
+            "type_spec" => {
+                let kind = match node.child_by_field_name("type").map(|t| t.kind()) {
+                    Some("struct_type") => SymbolKind::Struct,
+                    Some("interface_type") => SymbolKind::Interface,
+                    _ => SymbolKind::TypeAlias,
+                };
+                symbol(kind, true)
+            }
+            "type_alias" => symbol(SymbolKind::TypeAlias, false),
+            "const_spec" => symbol(SymbolKind::Constant, false),
+            "var_spec" => symbol(SymbolKind::Variable, false),
+            "field_declaration" => symbol(SymbolKind::Field, false),
+            "method_elem" | "method_spec" => symbol(SymbolKind::Method, false),
+            _ => NodeRole::None,
+        },
+    }
+}
+
+/// Receiver type of a Go method (`func (s *Server) Run()` → `Server`).
+fn go_receiver(node: Node, src: &str) -> Option<String> {
+    let receiver = node.child_by_field_name("receiver")?;
+    let mut stack = vec![receiver];
+    while let Some(n) = stack.pop() {
+        if n.kind() == "type_identifier" {
+            return Some(node_text(n, src).to_string());
+        }
+        let mut cursor = n.walk();
+        stack.extend(
+            n.named_children(&mut cursor)
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev(),
+        );
+    }
+    None
+}
+
+fn collect_symbols(
+    lang: Lang,
+    node: Node,
+    src: &str,
+    lines: &LineIndex,
+    container: Option<&str>,
+    in_type: bool,
+    out: &mut Vec<Symbol>,
+) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match classify(lang, child, in_type, src) {
+            NodeRole::Symbol {
+                kind,
+                name,
+                descend,
+            } => {
+                let own_container = match (lang, child.kind()) {
+                    (Lang::Go, "method_declaration") => go_receiver(child, src),
+                    _ => container.map(str::to_string),
+                };
+                let name_text = node_text(name, src).to_string();
+                out.push(Symbol {
+                    name: name_text.clone(),
+                    kind,
+                    container: own_container,
+                    range: lines.range(child),
+                    selection_range: lines.range(name),
+                });
+                if descend {
+                    let is_type = !matches!(kind, SymbolKind::Module);
+                    collect_symbols(lang, child, src, lines, Some(&name_text), is_type, out);
+                }
+            }
+            NodeRole::Container(name) => {
+                collect_symbols(lang, child, src, lines, Some(&name), true, out);
+            }
+            NodeRole::None => collect_symbols(lang, child, src, lines, container, in_type, out),
+        }
+    }
+}
+
+/// Parse `text` as the language implied by `path` and return its symbols.
+/// Returns `None` for unsupported languages.
+pub fn extract_symbols(path: &Path, text: &str) -> Option<Vec<Symbol>> {
+    let lang = Lang::from_path(path)?;
+    let mut parser = Parser::new();
+    parser.set_language(&lang.grammar()).ok()?;
+    let tree = parser.parse(text, None)?;
+    let lines = LineIndex::new(text);
+    let mut out = Vec::new();
+    collect_symbols(lang, tree.root_node(), text, &lines, None, false, &mut out);
+    Some(out)
+}
+
+// ---------------------------------------------------------------------------------------------
+// Index maintenance
+// ---------------------------------------------------------------------------------------------
+
+fn is_hidden(entry: &DirEntry) -> bool {
+    entry
+        .file_name()
+        .to_str()
+        .map(|s| s.starts_with('.'))
+        .unwrap_or(false)
+}
+
+fn file_mtime(meta: &fs::Metadata) -> u128 {
+    meta.modified()
+        .ok()
+        .and_then(|m| m.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+/// Parse a single file from disk. `None` if unsupported, too large or unreadable.
+fn index_file(path: &Path) -> Option<FileSymbols> {
+    Lang::from_path(path)?;
+    let meta = fs::metadata(path).ok()?;
+    if !meta.is_file() || meta.len() > MAX_FILE_SIZE {
+        return None;
+    }
+    let text = fs::read_to_string(path).ok()?;
+    Some(FileSymbols {
+        mtime: file_mtime(&meta),
+        symbols: extract_symbols(path, &text)?,
+    })
+}
+
+/// Create or refresh the index for `root`: only files that are new or whose
+/// mtime changed are re-parsed; deleted files are dropped.
+fn refresh_index(indices: &mut Vec<SymbolIndex>, root: &Path) -> usize {
+    let pos = match indices.iter().position(|idx| idx.root == root) {
+        Some(pos) => pos,
+        None => {
+            indices.push(SymbolIndex {
+                root: root.to_path_buf(),
+                files: HashMap::new(),
+            });
+            indices.len() - 1
+        }
+    };
+    let idx = &mut indices[pos];
+
+    let candidates: Vec<(String, PathBuf, u128)> = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| !is_hidden(e) && !should_ignore(e.path()))
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file() && Lang::from_path(e.path()).is_some())
+        .filter_map(|e| {
+            let rel = e
+                .path()
+                .strip_prefix(root)
+                .ok()?
+                .to_string_lossy()
+                .into_owned();
+            let mtime = e.metadata().map(|m| file_mtime(&m)).unwrap_or(0);
+            Some((rel, e.path().to_path_buf(), mtime))
+        })
+        .collect();
+
+    let stale: Vec<&(String, PathBuf, u128)> = candidates
+        .iter()
+        .filter(|(rel, _, mtime)| idx.files.get(rel).map(|f| f.mtime) != Some(*mtime))
+        .collect();
+    let parsed: Vec<(String, Option<FileSymbols>)> = stale
+        .par_iter()
+        .map(|(rel, abs, _)| (rel.clone(), index_file(abs)))
+        .collect();
+
+    let live: std::collections::HashSet<&String> = candidates.iter().map(|(r, _, _)| r).collect();
+    idx.files.retain(|rel, _| live.contains(rel));
+    for (rel, symbols) in parsed {
+        match symbols {
+            Some(s) => {
+                idx.files.insert(rel, s);
+            }
+            None => {
+                idx.files.remove(&rel);
+            }
+        }
+    }
+    debug!(
+        "[SYMBOLS] Refreshed {} ({} re-parsed)",
+        root.display(),
+        stale.len()
+    );
+    idx.files.values().map(|f| f.symbols.len()).sum()
+}
+
+/// Paths from the watcher, applied by one worker thread so the watcher never
+/// waits for tree-sitter and updates to a file land in order.
+static UPDATES: Lazy<Sender<Vec<PathBuf>>> = Lazy::new(|| {
+    let (tx, rx) = mpsc::channel::<Vec<PathBuf>>();
+    thread::Builder::new()
+        .name("symbol-index-updates".into())
+        .spawn(move || {
+            for paths in rx {
+                apply_updates(&paths);
+            }
+        })
+        .expect("failed to spawn symbol index worker");
+    tx
+});
+
+/// Incrementally apply file-system changes reported by the watcher to every
+/// loaded symbol index, in the background. Paths outside indexed roots are
+/// ignored.
+pub fn update_paths(paths: &[PathBuf]) {
+    let paths: Vec<PathBuf> = paths
+        .iter()
+        // Directories, and deleted paths that may have been directories.
+        .filter(|p| Lang::from_path(p).is_some() || !p.is_file())
+        .cloned()
+        .collect();
+    if paths.is_empty() || SYMBOL_INDICES.lock().unwrap().is_empty() {
+        return;
+    }
+    let _ = UPDATES.send(paths);
+}
+
+/// Re-parse `paths` (files or directories) and store them in the index of
+/// the deepest root containing each. Parsing happens outside the index lock.
+fn apply_updates(paths: &[PathBuf]) {
+    for path in paths {
+        let Some(root) = SYMBOL_INDICES
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|i| path.starts_with(&i.root))
+            .max_by_key(|i| i.root.components().count())
+            .map(|i| i.root.clone())
+        else {
+            continue;
+        };
+        let Ok(rel) = path.strip_prefix(&root) else {
+            continue;
+        };
+        if is_hidden_rel(rel) {
+            continue; // skipped by the full walk as well
+        }
+        let parsed: Vec<(String, FileSymbols)> = files_under(path)
+            .iter()
+            .filter_map(|file| {
+                let rel = file.strip_prefix(&root).ok()?;
+                Some((rel.to_string_lossy().into_owned(), index_file(file)?))
+            })
+            .collect();
+
+        let mut indices = SYMBOL_INDICES.lock().unwrap();
+        let Some(idx) = indices.iter_mut().find(|i| i.root == root) else {
+            continue;
+        };
+        // Everything at or below `path`, so deleted directories leave nothing.
+        idx.files
+            .retain(|file, _| !Path::new(file).starts_with(rel));
+        idx.files.extend(parsed);
+    }
+}
+
+/// Fuzzy score of a symbol name against a lowercased query; exact and prefix
+/// matches on the bare name rank first. An empty query matches everything.
+fn score_name(matcher: &SkimMatcherV2, name: &str, q: &str) -> Option<i64> {
+    if q.is_empty() {
+        return Some(0);
+    }
+    let score = matcher.fuzzy_match(name, q)?;
+    let lower = name.to_lowercase();
+    let bonus = if lower == q {
+        200
+    } else if lower.starts_with(q) {
+        100
+    } else {
+        0
+    };
+    Some(score + bonus)
+}
+
+/// Run `f` against the index for `root`, building it first if needed.
+fn with_index<T>(root: &Path, f: impl FnOnce(&SymbolIndex) -> T) -> T {
+    let mut indices = SYMBOL_INDICES.lock().unwrap();
+    if !indices.iter().any(|i| i.root == root) {
+        refresh_index(&mut indices, root);
+    }
+    let idx = indices.iter().find(|i| i.root == root).unwrap();
+    f(idx)
+}
+
+/// Best `limit` fuzzy matches for `query` across `roots`, as (root,
+/// relative path, symbol). Roots without an index are indexed first.
+pub fn search_roots(
+    roots: &[PathBuf],
+    query: &str,
+    limit: usize,
+) -> Vec<(PathBuf, String, Symbol)> {
+    let matcher = SkimMatcherV2::default();
+    let q = query.trim().to_lowercase();
+    let mut indices = SYMBOL_INDICES.lock().unwrap();
+    for root in roots {
+        if !indices.iter().any(|i| i.root == *root) {
+            refresh_index(&mut indices, root);
+        }
+    }
+    let mut scored: Vec<(&PathBuf, &String, &Symbol, i64)> = roots
+        .iter()
+        .filter_map(|r| indices.iter().find(|i| i.root == *r))
+        .flat_map(|idx| {
+            idx.files
+                .iter()
+                .flat_map(move |(p, f)| f.symbols.iter().map(move |s| (&idx.root, p, s)))
+        })
+        .filter_map(|(r, p, s)| score_name(&matcher, &s.name, &q).map(|score| (r, p, s, score)))
+        .collect();
+    scored.sort_by(|a, b| {
+        b.3.cmp(&a.3)
+            .then_with(|| a.2.name.len().cmp(&b.2.name.len()))
+            .then_with(|| a.1.cmp(b.1))
+    });
+    scored
+        .into_iter()
+        .take(limit)
+        .map(|(r, p, s, _)| (r.clone(), p.clone(), s.clone()))
+        .collect()
+}
+
+/// All symbols within `root` named exactly `name`.
+pub fn lookup_exact(root: &Path, name: &str) -> Vec<(String, Symbol)> {
+    with_index(root, |idx| {
+        idx.files
+            .iter()
+            .flat_map(|(p, f)| f.symbols.iter().map(move |s| (p, s)))
+            .filter(|(_, s)| s.name == name)
+            .map(|(p, s)| (p.clone(), s.clone()))
+            .collect()
+    })
+}
+
+// ---------------------------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------------------------
+
+#[command]
+/// Build (or incrementally refresh) the symbol index for every root of `path`.
+/// Returns the number of indexed symbols.
+pub async fn build_symbol_index(path: String) -> tauri::Result<usize> {
+    let roots = workspace::resolve_roots(Path::new(&path))?;
+    let mut indices = SYMBOL_INDICES.lock().unwrap();
+    let total = roots
+        .iter()
+        .map(|root| refresh_index(&mut indices, &root.path))
+        .sum();
+    info!("[SYMBOLS] Indexed {} symbols for {}", total, path);
+    Ok(total)
+}
+
+#[derive(Deserialize)]
+pub struct SymbolQuery {
+    path: String,
+    query: String,
+    /// Optional kind filter, e.g. `["function", "method"]` (case-insensitive)
+    kinds: Option<Vec<String>>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolHit {
+    pub root: String,
+    pub root_path: String,
+    /// File path relative to the root
+    pub path: String,
+    #[serde(flatten)]
+    pub symbol: Symbol,
+}
+
+#[command]
+/// Fuzzy-search symbol names across the workspace.
+pub async fn query_symbols(params: SymbolQuery) -> tauri::Result<Vec<SymbolHit>> {
+    let SymbolQuery {
+        path,
+        query,
+        kinds,
+        offset,
+        limit,
+    } = params;
+
+    let roots = workspace::resolve_roots(Path::new(&path))?;
+    let mut indices = SYMBOL_INDICES.lock().unwrap();
+    for root in &roots {
+        if !indices.iter().any(|i| i.root == root.path) {
+            refresh_index(&mut indices, &root.path);
+        }
+    }
+    let sources: Vec<(&WorkspaceRoot, &SymbolIndex)> = roots
+        .iter()
+        .filter_map(|r| indices.iter().find(|i| i.root == r.path).map(|i| (r, i)))
+        .collect();
+
+    let matcher = SkimMatcherV2::default();
+    let q = query.trim().to_lowercase();
+    let kinds: Option<Vec<String>> =
+        kinds.map(|ks| ks.into_iter().map(|k| k.to_lowercase()).collect());
+
+    let mut scored: Vec<(&WorkspaceRoot, &String, &Symbol, i64)> = sources
+        .par_iter()
+        .flat_map(|(r, idx)| {
+            idx.files
+                .par_iter()
+                .flat_map(move |(p, f)| f.symbols.par_iter().map(move |s| (*r, p, s)))
+        })
+        .filter(|(_, _, s)| match &kinds {
+            Some(ks) => ks.iter().any(|k| k == s.kind.as_str()),
+            None => true,
+        })
+        .filter_map(|(r, p, s)| score_name(&matcher, &s.name, &q).map(|score| (r, p, s, score)))
+        .collect();
+
+    // Highest score first, then shorter name, then path
+    scored.sort_by(|a, b| {
+        b.3.cmp(&a.3)
+            .then_with(|| a.2.name.len().cmp(&b.2.name.len()))
+            .then_with(|| a.1.cmp(b.1))
+    });
+
+    let sliced = scored
+        .into_iter()
+        .skip(offset.unwrap_or(0))
+        .take(limit.unwrap_or(DEFAULT_PAGE_SIZE))
+        .map(|(r, p, s, _)| SymbolHit {
+            root: r.name.clone(),
+            root_path: r.path.to_string_lossy().into_owned(),
+            path: p.clone(),
+            symbol: s.clone(),
+        })
+        .collect();
+    Ok(sliced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (kind, name, container) of every symbol in `text`.
+    fn outline(file: &str, text: &str) -> Vec<(&'static str, String, Option<String>)> {
+        extract_symbols(Path::new(file), text)
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.kind.as_str(), s.name, s.container))
+            .collect()
+    }
+
+    fn sym(
+        kind: &'static str,
+        name: &str,
+        container: Option<&str>,
+    ) -> (&'static str, String, Option<String>) {
+        (kind, name.to_string(), container.map(str::to_string))
+    }
+
+    #[test]
+    fn rust_symbols_nest_under_modules_types_and_impls() {
+        let text = concat!(
+            "mod net {\n",
+            "    pub struct Server { port: u16 }\n",
+            "    impl Server {\n",
+            "        pub fn run(&self) { let local = 1; }\n",
+            "    }\n",
+            "}\n",
+            "enum Mode { Fast }\n",
+            "const LIMIT: usize = 1;\n",
+            "macro_rules! m { () => {} }\n",
+        );
+        assert_eq!(
+            outline("a.rs", text),
+            [
+                sym("module", "net", None),
+                sym("struct", "Server", Some("net")),
+                sym("field", "port", Some("Server")),
+                sym("method", "run", Some("Server")),
+                sym("enum", "Mode", None),
+                sym("enummember", "Fast", Some("Mode")),
+                sym("constant", "LIMIT", None),
+                sym("macro", "m", None),
+            ]
+        );
+        assert!(extract_symbols(Path::new("a.txt"), text).is_none());
+    }
+
+    #[test]
+    fn ranges_use_utf16_columns() {
+        let symbols = extract_symbols(Path::new("a.rs"), "/* é😀 */ fn f() {\n}\n").unwrap();
+        let f = &symbols[0];
+        // `é` is one UTF-16 unit (two bytes), `😀` two units (four bytes).
+        assert_eq!((f.range.start.line, f.range.start.character), (0, 10));
+        assert_eq!((f.range.end.line, f.range.end.character), (1, 1));
+        let name = &f.selection_range;
+        assert_eq!((name.start.character, name.end.character), (13, 14));
+    }
+
+    #[test]
+    fn typescript_symbols_distinguish_functions_constants_and_members() {
+        let text = concat!(
+            "export class Greeter {\n",
+            "  name: string;\n",
+            "  greet(): void {}\n",
+            "}\n",
+            "interface Shape { area(): number; }\n",
+            "const PI = 3.14;\n",
+            "let count = 0;\n",
+            "const add = (a: number) => a;\n",
+            "const { x } = obj;\n",
+            "function main() {}\n",
+        );
+        assert_eq!(
+            outline("a.ts", text),
+            [
+                sym("class", "Greeter", None),
+                sym("field", "name", Some("Greeter")),
+                sym("method", "greet", Some("Greeter")),
+                sym("interface", "Shape", None),
+                sym("method", "area", Some("Shape")),
+                sym("constant", "PI", None),
+                sym("variable", "count", None),
+                sym("function", "add", None),
+                sym("function", "main", None),
+            ]
+        );
+        // JSX goes through the TSX grammar.
+        let tsx = outline("a.jsx", "function App() { return <div />; }\n");
+        assert_eq!(tsx, [sym("function", "App", None)]);
+    }
+
+    #[test]
+    fn python_methods_belong_to_their_class_and_locals_are_skipped() {
+        let text = concat!(
+            "class Shape:\n",
+            "    def area(self):\n",
+            "        def inner():\n",
+            "            pass\n",
+            "        return 0\n",
+            "\n",
+            "def main():\n",
+            "    pass\n",
+        );
+        assert_eq!(
+            outline("a.py", text),
+            [
+                sym("class", "Shape", None),
+                sym("method", "area", Some("Shape")),
+                sym("function", "main", None),
+            ]
+        );
+        let symbols = extract_symbols(Path::new("a.py"), text).unwrap();
+        assert_eq!(
+            (symbols[1].range.start.line, symbols[1].range.end.line),
+            (1, 4)
+        );
+    }
+
+    #[test]
+    fn go_methods_take_their_receiver_as_container() {
+        let text = concat!(
+            "package main\n",
+            "\n",
+            "type Server struct {\n",
+            "\tPort int\n",
+            "}\n",
+            "\n",
+            "type Runner interface {\n",
+            "\tRun() error\n",
+            "}\n",
+            "\n",
+            "func (s *Server) Run() error { return nil }\n",
+            "\n",
+            "func main() {}\n",
+            "\n",
+            "const Limit = 1\n",
+        );
+        assert_eq!(
+            outline("a.go", text),
+            [
+                sym("struct", "Server", None),
+                sym("field", "Port", Some("Server")),
+                sym("interface", "Runner", None),
+                sym("method", "Run", Some("Runner")),
+                sym("method", "Run", Some("Server")),
+                sym("function", "main", None),
+                sym("constant", "Limit", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn watcher_updates_go_to_the_deepest_root() {
+        let outer = std::env::temp_dir().join(format!("glass-symbols-{}", std::process::id()));
+        let inner = outer.join("inner");
+        fs::create_dir_all(&inner).unwrap();
+        {
+            let mut indices = SYMBOL_INDICES.lock().unwrap();
+            refresh_index(&mut indices, &outer);
+            refresh_index(&mut indices, &inner);
+        }
+        let file = inner.join("a.rs");
+        fs::write(&file, "fn added() {}\n").unwrap();
+        apply_updates(std::slice::from_ref(&file));
+
+        let names = |root: &Path, rel: &str| {
+            let indices = SYMBOL_INDICES.lock().unwrap();
+            let idx = indices.iter().find(|i| i.root == root).unwrap();
+            idx.files
+                .get(rel)
+                .map(|f| f.symbols.iter().map(|s| s.name.clone()).collect::<Vec<_>>())
+        };
+        assert_eq!(names(&inner, "a.rs"), Some(vec!["added".to_string()]));
+        assert_eq!(names(&outer, "inner/a.rs"), None);
+
+        // Deleted files are dropped.
+        fs::remove_file(&file).unwrap();
+        apply_updates(&[file]);
+        assert_eq!(names(&inner, "a.rs"), None);
+
+        // Hidden paths are skipped, deleted directories dropped.
+        fs::create_dir_all(inner.join("src")).unwrap();
+        fs::create_dir_all(inner.join(".cache")).unwrap();
+        fs::write(inner.join("src/b.rs"), "fn nested() {}\n").unwrap();
+        fs::write(inner.join(".cache/c.rs"), "fn hidden() {}\n").unwrap();
+        apply_updates(&[inner.join("src"), inner.join(".cache/c.rs")]);
+        assert_eq!(names(&inner, "src/b.rs"), Some(vec!["nested".to_string()]));
+        assert_eq!(names(&inner, ".cache/c.rs"), None);
+        fs::remove_dir_all(inner.join("src")).unwrap();
+        apply_updates(&[inner.join("src")]);
+        assert_eq!(names(&inner, "src/b.rs"), None);
+
+        SYMBOL_INDICES
+            .lock()
+            .unwrap()
+            .retain(|i| !i.root.starts_with(&outer));
+        let _ = fs::remove_dir_all(&outer);
+    }
+}
@@ -0,0 +1,346 @@
+// TODO / FIXME task scanner for Glass-IDE
+// ---------------------------------------
+// Lists tech-debt markers such as `TODO(alice): drop this hack` across the
+// workspace for the tasks panel.
+// Design notes:
+//  • Tags are configurable per scan (default: TODO, FIXME, HACK, XXX, BUG).
+//  • Candidate files come from the content trigram index, so only files that
+//    can contain a tag are read line by line.
+//  • A tag matches as a whole, case-sensitive word followed by `(`, `:`,
+//    whitespace or end of line. `TAG(author)` captures the author.
+//  • Results are cached per root and refreshed by the fs watcher
+//    (`update_paths`) on a worker thread, which calls back when any task
+//    list changed.
+
+use anyhow::Error as AnyError;
+use log::info;
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread;
+use tauri::command;
+
+use super::content_indexer;
+use super::editorconfig;
+use super::fs::{files_under, is_hidden_rel};
+use super::workspace::{self, WorkspaceRoot};
+
+const DEFAULT_TAGS: &[&str] = &["TODO", "FIXME", "HACK", "XXX", "BUG"];
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskItem {
+    pub root: String,
+    pub root_path: String,
+    /// File path relative to the root
+    pub path: String,
+    /// 0-based line number
+    pub line: usize,
+    pub tag: String,
+    /// Author from `TAG(author)`, if present
+    pub author: Option<String>,
+    pub text: String,
+}
+
+/// Task lists for one root, keyed by relative path (files without tasks are omitted).
+struct TaskCache {
+    root: WorkspaceRoot,
+    tags: Vec<String>,
+    files: HashMap<String, Vec<TaskItem>>,
+}
+
+static TASK_CACHES: Lazy<Mutex<Vec<TaskCache>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Find the first tag occurrence in `line`, returning (tag, author, text).
+fn parse_line(line: &str, tags: &[String]) -> Option<(String, Option<String>, String)> {
+    for tag in tags {
+        for (pos, _) in line.match_indices(tag.as_str()) {
+            let before = line[..pos].chars().next_back();
+            if before.is_some_and(is_word_char) {
+                continue;
+            }
+            let mut rest = &line[pos + tag.len()..];
+            match rest.chars().next() {
+                None => {}
+                Some(c) if c == '(' || c == ':' || c.is_whitespace() => {}
+                Some(_) => continue, // e.g. `TODOS`, `FIXMEd`
+            }
+
+            let mut author = None;
+            if let Some(after_paren) = rest.strip_prefix('(') {
+                if let Some(end) = after_paren.find(')') {
+                    let name = after_paren[..end].trim();
+                    if !name.is_empty() {
+                        author = Some(name.to_string());
+                    }
+                    rest = &after_paren[end + 1..];
+                }
+            }
+            let text = rest
+                .trim_start_matches([':', '-'])
+                .trim()
+                .trim_end_matches("*/")
+                .trim_end_matches("-->")
+                .trim();
+            return Some((tag.clone(), author, text.to_string()));
+        }
+    }
+    None
+}
+
+fn scan_file(root: &WorkspaceRoot, rel: &str, tags: &[String]) -> Vec<TaskItem> {
+    let Ok(text) = editorconfig::read_text(&root.path.join(rel)) else {
+        return Vec::new();
+    };
+    text.lines()
+        .enumerate()
+        .filter_map(|(line, content)| {
+            let (tag, author, text) = parse_line(content, tags)?;
+            Some(TaskItem {
+                root: root.name.clone(),
+                root_path: root.path.to_string_lossy().into_owned(),
+                path: rel.to_string(),
+                line,
+                tag,
+                author,
+                text,
+            })
+        })
+        .collect()
+}
+
+fn scan_root(root: &WorkspaceRoot, tags: &[String]) -> Result<TaskCache, AnyError> {
+    let candidates = content_indexer::files_containing_any(&root.path, tags)?;
+    let files: HashMap<String, Vec<TaskItem>> = candidates
+        .par_iter()
+        .map(|rel| (rel.clone(), scan_file(root, rel, tags)))
+        .filter(|(_, tasks)| !tasks.is_empty())
+        .collect();
+    Ok(TaskCache {
+        root: root.clone(),
+        tags: tags.to_vec(),
+        files,
+    })
+}
+
+/// A watcher batch and the callback to run if it changed any task list.
+type Update = (Vec<PathBuf>, Box<dyn FnOnce() + Send>);
+
+/// Paths from the watcher, rescanned by one worker thread so the watcher
+/// never waits for file reads.
+static UPDATES: Lazy<Sender<Update>> = Lazy::new(|| {
+    let (tx, rx) = mpsc::channel::<Update>();
+    thread::Builder::new()
+        .name("task-scan-updates".into())
+        .spawn(move || {
+            for (paths, on_change) in rx {
+                if apply_updates(&paths) {
+                    on_change();
+                }
+            }
+        })
+        .expect("failed to spawn task scan worker");
+    tx
+});
+
+/// Rescan changed files for every cached root in the background, calling
+/// `on_change` if any task list changed so the caller can notify the frontend.
+pub fn update_paths(paths: &[PathBuf], on_change: impl FnOnce() + Send + 'static) {
+    if TASK_CACHES.lock().unwrap().is_empty() {
+        return;
+    }
+    let _ = UPDATES.send((paths.to_vec(), Box::new(on_change)));
+}
+
+/// Rescan `paths` (files or directories) in the cache of the deepest root
+/// containing each. Returns `true` if any task list changed.
+fn apply_updates(paths: &[PathBuf]) -> bool {
+    let mut changed = false;
+    for path in paths {
+        // Deepest root for nested folders, like `workspace::root_for_path`.
+        let Some((root, tags)) = TASK_CACHES
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|c| path.starts_with(&c.root.path))
+            .max_by_key(|c| c.root.path.components().count())
+            .map(|c| (c.root.clone(), c.tags.clone()))
+        else {
+            continue;
+        };
+        let Ok(rel) = path.strip_prefix(&root.path) else {
+            continue;
+        };
+        if is_hidden_rel(rel) {
+            continue; // hidden paths are never scanned
+        }
+        let scanned: Vec<(String, Vec<TaskItem>)> = files_under(path)
+            .iter()
+            .filter_map(|file| {
+                let rel = file.strip_prefix(&root.path).ok()?;
+                let rel = rel.to_string_lossy().into_owned();
+                let tasks = scan_file(&root, &rel, &tags);
+                (!tasks.is_empty()).then_some((rel, tasks))
+            })
+            .collect();
+
+        let mut caches = TASK_CACHES.lock().unwrap();
+        let Some(cache) = caches.iter_mut().find(|c| c.root == root) else {
+            continue;
+        };
+        // Everything at or below `path`, so deleted directories leave nothing.
+        let before = cache.files.len();
+        cache
+            .files
+            .retain(|file, _| !Path::new(file).starts_with(rel));
+        if cache.files.len() != before || !scanned.is_empty() {
+            changed = true;
+        }
+        cache.files.extend(scanned);
+    }
+    changed
+}
+
+// ---------------------------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+pub struct TaskParams {
+    path: String,
+    /// Tags to look for; defaults to TODO, FIXME, HACK, XXX, BUG
+    tags: Option<Vec<String>>,
+}
+
+#[command]
+/// Return all tasks for every root of `path`, ordered by root, path and line.
+/// Served from cache unless the tag set changed.
+pub async fn scan_tasks(params: TaskParams) -> tauri::Result<Vec<TaskItem>> {
+    let TaskParams { path, tags } = params;
+    let tags: Vec<String> = tags
+        .unwrap_or_else(|| DEFAULT_TAGS.iter().map(|t| t.to_string()).collect())
+        .into_iter()
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    let roots = workspace::resolve_roots(Path::new(&path))?;
+    let mut result = Vec::new();
+    for root in &roots {
+        let cached = {
+            let caches = TASK_CACHES.lock().unwrap();
+            caches.iter().any(|c| c.root == *root && c.tags == tags)
+        };
+        if !cached {
+            let cache = scan_root(root, &tags)?;
+            info!(
+                "[TASKS] Found tasks in {} files under {}",
+                cache.files.len(),
+                root.path.display()
+            );
+            let mut caches = TASK_CACHES.lock().unwrap();
+            caches.retain(|c| c.root.path != root.path);
+            caches.push(cache);
+        }
+
+        let caches = TASK_CACHES.lock().unwrap();
+        if let Some(cache) = caches.iter().find(|c| c.root == *root) {
+            let mut tasks: Vec<TaskItem> = cache.files.values().flatten().cloned().collect();
+            tasks.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+            result.extend(tasks);
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use std::fs;
+
+    fn tags() -> Vec<String> {
+        DEFAULT_TAGS.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_line_finds_tags_and_authors() {
+        let parse = |line: &str| parse_line(line, &tags());
+        assert_eq!(
+            parse("// TODO: drop this hack"),
+            Some(("TODO".into(), None, "drop this hack".into()))
+        );
+        assert_eq!(
+            parse("# FIXME(alice): handle errors"),
+            Some(("FIXME".into(), Some("alice".into()), "handle errors".into()))
+        );
+        assert_eq!(
+            parse("/* HACK */"),
+            Some(("HACK".into(), None, String::new()))
+        );
+        assert_eq!(
+            parse("let x = 1; // XXX"),
+            Some(("XXX".into(), None, String::new()))
+        );
+        // Not whole words, or not uppercase.
+        assert_eq!(parse("// TODOS are tracked elsewhere"), None);
+        assert_eq!(parse("let MY_TODO = 1;"), None);
+        assert_eq!(parse("// todo: lowercase"), None);
+    }
+
+    #[test]
+    fn updates_add_and_remove_tasks() {
+        let dir = test_support::temp_root("tasks", "update");
+        let root = WorkspaceRoot {
+            name: "tasks".into(),
+            path: dir.clone(),
+        };
+        TASK_CACHES.lock().unwrap().push(TaskCache {
+            root: root.clone(),
+            tags: tags(),
+            files: HashMap::new(),
+        });
+        let files = || {
+            let caches = TASK_CACHES.lock().unwrap();
+            let cache = caches.iter().find(|c| c.root == root).unwrap();
+            let mut files: Vec<String> = cache.files.keys().cloned().collect();
+            files.sort();
+            files
+        };
+
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        fs::write(dir.join("src/a.rs"), "// TODO: a\n").unwrap();
+        fs::write(dir.join("src/b.rs"), "// FIXME: b\n").unwrap();
+        fs::write(dir.join("top.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.join(".git/notes"), "TODO: hidden\n").unwrap();
+        assert!(apply_updates(&[
+            dir.join("src"),
+            dir.join("top.rs"),
+            dir.join(".git/notes")
+        ]));
+        assert_eq!(files(), ["src/a.rs", "src/b.rs"]);
+
+        // A file without tasks changes nothing.
+        assert!(!apply_updates(&[dir.join("top.rs")]));
+
+        fs::write(dir.join("src/a.rs"), "fn a() {}\n").unwrap();
+        assert!(apply_updates(&[dir.join("src/a.rs")]));
+        assert_eq!(files(), ["src/b.rs"]);
+
+        // Deleting the directory drops the tasks below it.
+        fs::remove_dir_all(dir.join("src")).unwrap();
+        assert!(apply_updates(&[dir.join("src")]));
+        assert!(files().is_empty());
+
+        TASK_CACHES.lock().unwrap().retain(|c| c.root != root);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,192 @@
+//! Multi-root workspace model.
+//!
+//! A workspace is either a plain directory (single root, named after the
+//! folder) or a `.glass-workspace` JSON file listing several folders:
+//!
+//! ```json
+//! {
+//!   "folders": [
+//!     { "name": "app", "path": "src-tauri" },
+//!     { "path": "../shared" }
+//!   ]
+//! }
+//! ```
+//!
+//! Relative folder paths resolve against the directory containing the file.
+//! Every command that takes a workspace `path` (snapshot, watch, indexers,
+//! search) accepts either form and tags its results with the root name.
+
+use anyhow::{anyhow, Error as AnyError};
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{command, AppHandle};
+
+/// File extension of workspace files.
+pub const WORKSPACE_EXTENSION: &str = "glass-workspace";
+
+/// On-disk folder entry.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WorkspaceFolder {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub path: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct WorkspaceFile {
+    #[serde(default)]
+    folders: Vec<WorkspaceFolder>,
+}
+
+/// Resolved workspace root: unique display name + absolute path.
+#[derive(Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceRoot {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Resolved workspace as returned to the frontend.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Workspace {
+    /// Path of the `.glass-workspace` file, `None` for a plain directory.
+    pub file: Option<PathBuf>,
+    pub roots: Vec<WorkspaceRoot>,
+}
+
+/// Workspace most recently opened via [`open_workspace`].
+static CURRENT: Lazy<Mutex<Option<Workspace>>> = Lazy::new(|| Mutex::new(None));
+
+/// Whether `path` points at a workspace file rather than a directory.
+pub fn is_workspace_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e == WORKSPACE_EXTENSION)
+            .unwrap_or(false)
+}
+
+fn folder_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string_lossy().into_owned())
+}
+
+/// Load a workspace from a `.glass-workspace` file or a plain directory.
+/// Folders that do not exist are skipped with a warning.
+pub fn load_workspace(path: &Path) -> Result<Workspace, AnyError> {
+    if !is_workspace_file(path) {
+        if !path.is_dir() {
+            return Err(anyhow!("Workspace path does not exist: {}", path.display()));
+        }
+        return Ok(Workspace {
+            file: None,
+            roots: vec![WorkspaceRoot {
+                name: folder_name(path),
+                path: path.to_path_buf(),
+            }],
+        });
+    }
+
+    let bytes = fs::read(path)?;
+    let file: WorkspaceFile = serde_json::from_slice(&bytes)?;
+    let base = path.parent().unwrap_or_else(|| Path::new("/"));
+
+    let mut roots: Vec<WorkspaceRoot> = Vec::new();
+    for folder in file.folders {
+        let dir = base.join(&folder.path);
+        let dir = dir.canonicalize().unwrap_or(dir);
+        if !dir.is_dir() {
+            warn!("[WORKSPACE] Skipping missing folder: {}", dir.display());
+            continue;
+        }
+        if roots.iter().any(|r| r.path == dir) {
+            continue;
+        }
+        // Names must be unique since results are tagged by them.
+        let base_name = folder.name.unwrap_or_else(|| folder_name(&dir));
+        let mut name = base_name.clone();
+        let mut n = 2;
+        while roots.iter().any(|r| r.name == name) {
+            name = format!("{base_name}-{n}");
+            n += 1;
+        }
+        roots.push(WorkspaceRoot { name, path: dir });
+    }
+
+    Ok(Workspace {
+        file: Some(path.to_path_buf()),
+        roots,
+    })
+}
+
+/// Resolve the roots for a command `path` argument. Returns an empty list
+/// (rather than an error) for missing paths so commands can early-return.
+pub fn resolve_roots(path: &Path) -> Result<Vec<WorkspaceRoot>, AnyError> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(load_workspace(path)?.roots)
+}
+
+/// Roots of the currently open workspace (empty if none was opened).
+pub fn current_roots() -> Vec<WorkspaceRoot> {
+    CURRENT
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|ws| ws.roots.clone())
+        .unwrap_or_default()
+}
+
+/// Find the root (out of `roots`) that contains `path`, preferring the
+/// deepest match for nested folders.
+pub fn root_for_path<'a>(roots: &'a [WorkspaceRoot], path: &Path) -> Option<&'a WorkspaceRoot> {
+    roots
+        .iter()
+        .filter(|r| path.starts_with(&r.path))
+        .max_by_key(|r| r.path.components().count())
+}
+
+// ---------------------------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------------------------
+
+#[command]
+/// Open a workspace (directory or `.glass-workspace` file) and make it current.
+/// Language servers of roots that are no longer part of it are shut down in
+/// the background.
+pub async fn open_workspace(app: AppHandle, path: String) -> tauri::Result<Workspace> {
+    let ws = load_workspace(Path::new(&path))?;
+    info!(
+        "[WORKSPACE] Opened {} with {} root(s)",
+        path,
+        ws.roots.len()
+    );
+    *CURRENT.lock().unwrap() = Some(ws.clone());
+    let roots = ws.roots.clone();
+    tokio::spawn(async move { crate::lsp::retain_roots(&app, &roots).await });
+    Ok(ws)
+}
+
+#[command]
+/// Write a `.glass-workspace` file listing `folders` and open it.
+pub async fn save_workspace(
+    app: AppHandle,
+    path: String,
+    folders: Vec<WorkspaceFolder>,
+) -> tauri::Result<Workspace> {
+    let mut file_path = PathBuf::from(&path);
+    if file_path.extension().and_then(|e| e.to_str()) != Some(WORKSPACE_EXTENSION) {
+        file_path.set_extension(WORKSPACE_EXTENSION);
+    }
+    let bytes = serde_json::to_vec_pretty(&WorkspaceFile { folders }).map_err(AnyError::from)?;
+    fs::write(&file_path, bytes).map_err(AnyError::from)?;
+    open_workspace(app, file_path.to_string_lossy().into_owned()).await
+}
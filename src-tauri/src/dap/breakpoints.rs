@@ -0,0 +1,123 @@
+//! Breakpoints persisted per workspace root.
+//!
+//! Stored in `<root>/.glass/breakpoints.json`, keyed by file:
+//!
+//! ```json
+//! {
+//!   "files": {
+//!     "src/main.rs": [{ "line": 12 }, { "line": 30, "condition": "i > 3" }],
+//!     "/usr/lib/python3/json/decoder.py": [{ "line": 337 }]
+//!   }
+//! }
+//! ```
+//!
+//! Files inside the root are stored relative to it (with `/` separators), so
+//! the file can be committed and survives moving the checkout.
+
+use anyhow::{Context, Result};
+use log::warn;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Serialises read-modify-write cycles on the breakpoint files.
+static LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// DAP `SourceBreakpoint`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceBreakpoint {
+    /// 1-based
+    pub line: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hit_condition: Option<String>,
+    /// Makes the breakpoint a logpoint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct BreakpointFile {
+    #[serde(default)]
+    files: BTreeMap<String, Vec<SourceBreakpoint>>,
+}
+
+/// Path of the breakpoint file for `root`.
+pub fn file_path(root: &Path) -> PathBuf {
+    root.join(".glass").join("breakpoints.json")
+}
+
+/// Key of `file` in the breakpoint file.
+fn key(root: &Path, file: &Path) -> String {
+    match file.strip_prefix(root) {
+        Ok(rel) => rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        Err(_) => file.to_string_lossy().into_owned(),
+    }
+}
+
+fn read(root: &Path) -> BreakpointFile {
+    let path = file_path(root);
+    let Ok(bytes) = fs::read(&path) else {
+        return BreakpointFile::default();
+    };
+    serde_json::from_slice(&bytes).unwrap_or_else(|err| {
+        warn!(
+            "[DAP] Ignoring invalid breakpoints {}: {err}",
+            path.display()
+        );
+        BreakpointFile::default()
+    })
+}
+
+/// Breakpoints of `root` by absolute file path.
+pub fn load(root: &Path) -> BTreeMap<PathBuf, Vec<SourceBreakpoint>> {
+    let _guard = LOCK.lock().unwrap();
+    read(root)
+        .files
+        .into_iter()
+        .map(|(key, breakpoints)| (root.join(key), breakpoints))
+        .collect()
+}
+
+/// Replace the breakpoints of `file` (all of them, as in `setBreakpoints`);
+/// an empty list removes the file's entry.
+pub fn save(root: &Path, file: &Path, breakpoints: &[SourceBreakpoint]) -> Result<()> {
+    let _guard = LOCK.lock().unwrap();
+    let mut stored = read(root);
+    let key = key(root, file);
+    if breakpoints.is_empty() {
+        stored.files.remove(&key);
+    } else {
+        stored.files.insert(key, breakpoints.to_vec());
+    }
+
+    let path = file_path(root);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let text = serde_json::to_string_pretty(&stored)?;
+    fs::write(&path, text + "\n")
+        .with_context(|| format!("Failed to write breakpoints {}", path.display()))
+}
+
+/// `setBreakpoints` arguments for `file`.
+pub fn arguments(file: &Path, breakpoints: &[SourceBreakpoint]) -> Value {
+    let name = file.file_name().map(|n| n.to_string_lossy().into_owned());
+    json!({
+        "source": { "name": name, "path": file },
+        "breakpoints": breakpoints,
+        "lines": breakpoints.iter().map(|b| b.line).collect::<Vec<_>>(),
+    })
+}
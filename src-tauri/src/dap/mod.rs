@@ -0,0 +1,305 @@
+//! Debug Adapter Protocol bridge.
+//!
+//! The counterpart of [`crate::lsp`] for debuggers: `dap_start` spawns the
+//! configured adapter (see [`registry`]) over stdio – framed like LSP, see
+//! [`crate::lsp::framing`] – and runs the start-up sequence with the
+//! frontend's `launch` / `attach` configuration. Every session gets an id;
+//! the frontend routes requests to it with `dap_request` (`threads`,
+//! `stackTrace`, `continue`, `evaluate`…), answers reverse requests via
+//! `respond_dap_request` and ends it with `dap_stop`. Adapter events are
+//! emitted as `dap_event`, see [`session`] for all events.
+//!
+//! Breakpoints belong to the workspace, not to a session: they are persisted
+//! per root (see [`breakpoints`]), sent to every new session before
+//! `configurationDone`, and `dap_set_breakpoints` updates the file and all
+//! running sessions of the root at once.
+//!
+//! Sessions are removed once their adapter exits and disconnected on app exit.
+
+pub mod breakpoints;
+pub mod registry;
+mod session;
+#[cfg(test)]
+mod tests;
+
+use anyhow::anyhow;
+use log::warn;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tauri::{command, AppHandle, Runtime};
+use tokio::sync::Mutex;
+use tokio::time::Duration;
+
+use self::breakpoints::SourceBreakpoint;
+use self::session::{Session, SessionInfo, REQUEST_TIMEOUT};
+
+/// Running sessions by id.
+static SESSIONS: Lazy<Mutex<HashMap<String, Arc<Session>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+static NEXT_SESSION: AtomicU64 = AtomicU64::new(1);
+
+async fn find_session(id: &str) -> tauri::Result<Arc<Session>> {
+    SESSIONS
+        .lock()
+        .await
+        .get(id)
+        .cloned()
+        .ok_or_else(|| tauri::Error::Anyhow(anyhow!("No debug session `{id}`")))
+}
+
+async fn root_sessions(root: &str) -> Vec<Arc<Session>> {
+    SESSIONS
+        .lock()
+        .await
+        .values()
+        .filter(|session| session.root == root)
+        .cloned()
+        .collect()
+}
+
+/// Disconnect every session – called on app exit.
+pub async fn shutdown_all() {
+    let sessions: Vec<Arc<Session>> = SESSIONS.lock().await.drain().map(|(_, s)| s).collect();
+    let tasks: Vec<_> = sessions
+        .into_iter()
+        .map(|session| tokio::spawn(async move { session.stop(None).await }))
+        .collect();
+    for task in tasks {
+        let _ = task.await;
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Tauri commands – sessions
+// ----------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DapStartRequest {
+    /// Workspace root the debuggee belongs to
+    root: String,
+    /// Adapter id from the registry, e.g. `debugpy`
+    adapter: String,
+    /// `launch` or `attach`
+    request: String,
+    /// Adapter-specific launch / attach arguments (`program`, `args`, `pid`…)
+    #[serde(default)]
+    configuration: Value,
+}
+
+#[command]
+/// Start a debug session: spawn the adapter, `initialize`, `launch` /
+/// `attach`, send the root's breakpoints and `configurationDone`.
+pub async fn dap_start<R: Runtime>(
+    app: AppHandle<R>,
+    payload: DapStartRequest,
+) -> tauri::Result<SessionInfo> {
+    if !matches!(payload.request.as_str(), "launch" | "attach") {
+        return Err(tauri::Error::Anyhow(anyhow!(
+            "Unknown debug request `{}`, expected `launch` or `attach`",
+            payload.request
+        )));
+    }
+    let root = Path::new(&payload.root);
+    let config = registry::resolve(root, &payload.adapter).map_err(tauri::Error::Anyhow)?;
+    let id = NEXT_SESSION.fetch_add(1, Ordering::Relaxed).to_string();
+    let session =
+        Session::spawn(&app, id.clone(), &payload.root, config).map_err(tauri::Error::Anyhow)?;
+    SESSIONS.lock().await.insert(id.clone(), session.clone());
+
+    let watched = session.clone();
+    tokio::spawn(async move {
+        watched.wait_exit().await;
+        SESSIONS.lock().await.remove(&watched.id);
+    });
+
+    let configuration = match payload.configuration {
+        Value::Null => Value::Object(Default::default()),
+        configuration => configuration,
+    };
+    let breakpoints = breakpoints::load(root);
+    if let Err(err) = session
+        .start(&app, &payload.request, configuration, &breakpoints)
+        .await
+    {
+        session.kill();
+        return Err(tauri::Error::Anyhow(err));
+    }
+    Ok(session.info())
+}
+
+#[command]
+/// Running sessions, oldest first.
+pub async fn dap_sessions() -> tauri::Result<Vec<SessionInfo>> {
+    let mut sessions: Vec<SessionInfo> = SESSIONS
+        .lock()
+        .await
+        .values()
+        .map(|session| session.info())
+        .collect();
+    sessions.sort_by_key(|info| info.id.parse::<u64>().unwrap_or_default());
+    Ok(sessions)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DapRequest {
+    /// Session id from `dap_start`
+    session: String,
+    /// DAP command, e.g. `stackTrace`
+    command: String,
+    #[serde(default)]
+    arguments: Value,
+    /// Overrides the default timeout (milliseconds)
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+}
+
+#[command]
+/// Send a request to a session's adapter; resolves to the response `body`
+/// and fails with the adapter's message if it was not successful.
+pub async fn dap_request(payload: DapRequest) -> tauri::Result<Value> {
+    let session = find_session(&payload.session).await?;
+    let limit = payload
+        .timeout_ms
+        .map_or(REQUEST_TIMEOUT, Duration::from_millis);
+    session
+        .request(&payload.command, payload.arguments, limit)
+        .await
+        .map_err(tauri::Error::Anyhow)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DapRequestResponse {
+    /// `session` of the `dap_request` event being answered
+    session: String,
+    /// `seq` of the `dap_request` event being answered
+    request_seq: i64,
+    /// `command` of the `dap_request` event being answered
+    command: String,
+    success: bool,
+    #[serde(default)]
+    body: Value,
+    /// Error message for unsuccessful responses
+    #[serde(default)]
+    message: Option<String>,
+}
+
+#[command]
+/// Answer an adapter → client request previously emitted as `dap_request`.
+pub async fn respond_dap_request(payload: DapRequestResponse) -> tauri::Result<()> {
+    let session = find_session(&payload.session).await?;
+    session
+        .respond(
+            payload.request_seq,
+            &payload.command,
+            payload.success,
+            payload.body,
+            payload.message,
+        )
+        .await
+        .map_err(tauri::Error::Anyhow)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DapStopRequest {
+    session: String,
+    /// `disconnect`'s `terminateDebuggee`; the adapter decides if unset
+    #[serde(default)]
+    terminate_debuggee: Option<bool>,
+}
+
+#[command]
+/// End a session: `disconnect`, then kill the adapter if it lingers.
+pub async fn dap_stop(payload: DapStopRequest) -> tauri::Result<()> {
+    let session = SESSIONS
+        .lock()
+        .await
+        .remove(&payload.session)
+        .ok_or_else(|| tauri::Error::Anyhow(anyhow!("No debug session `{}`", payload.session)))?;
+    session.stop(payload.terminate_debuggee).await;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DapAdaptersRequest {
+    /// Workspace root
+    root: String,
+}
+
+#[command]
+/// Enabled debug adapters for a root, for the adapter picker.
+pub async fn dap_adapters(
+    payload: DapAdaptersRequest,
+) -> tauri::Result<Vec<registry::AdapterConfig>> {
+    Ok(registry::load(Path::new(&payload.root)))
+}
+
+// ----------------------------------------------------------------------------
+// Tauri commands – breakpoints
+// ----------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DapBreakpointsRequest {
+    /// Workspace root
+    root: String,
+}
+
+#[command]
+/// Persisted breakpoints of a root by absolute file path.
+pub async fn dap_breakpoints(
+    payload: DapBreakpointsRequest,
+) -> tauri::Result<BTreeMap<PathBuf, Vec<SourceBreakpoint>>> {
+    Ok(breakpoints::load(Path::new(&payload.root)))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DapSetBreakpointsRequest {
+    /// Workspace root
+    root: String,
+    /// Absolute path of the file
+    path: PathBuf,
+    /// All breakpoints of the file; empty to clear them
+    breakpoints: Vec<SourceBreakpoint>,
+}
+
+#[command]
+/// Replace a file's breakpoints: persisted for the root and sent to every
+/// running session of it. Resolves to each session's `Breakpoint`s (also
+/// emitted as `dap_breakpoints`) by session id.
+pub async fn dap_set_breakpoints<R: Runtime>(
+    app: AppHandle<R>,
+    payload: DapSetBreakpointsRequest,
+) -> tauri::Result<HashMap<String, Value>> {
+    let root = Path::new(&payload.root);
+    breakpoints::save(root, &payload.path, &payload.breakpoints).map_err(tauri::Error::Anyhow)?;
+
+    let mut verified = HashMap::new();
+    for session in root_sessions(&payload.root).await {
+        match session
+            .set_breakpoints(&app, &payload.path, &payload.breakpoints)
+            .await
+        {
+            Ok(breakpoints) => {
+                verified.insert(session.id.clone(), breakpoints);
+            }
+            Err(err) => warn!(
+                "[DAP] Session {} rejected breakpoints for {}: {err:#}",
+                session.id,
+                payload.path.display()
+            ),
+        }
+    }
+    Ok(verified)
+}
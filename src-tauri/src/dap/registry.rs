@@ -0,0 +1,129 @@
+//! Debug-adapter registry.
+//!
+//! Layered like the language-server registry ([`crate::lsp::registry`]),
+//! later layers overriding earlier ones per adapter id and per field:
+//! 1. built-in defaults ([`builtin_adapters`])
+//! 2. user config:      `<config dir>/glass-ide/dap.json`
+//! 3. workspace config: `<root>/.glass/dap.json`
+//!
+//! ```json
+//! {
+//!   "adapters": {
+//!     "debugpy": { "command": ".venv/bin/python" },
+//!     "lldb": { "command": "/opt/homebrew/opt/llvm/bin/lldb-dap" },
+//!     "node": { "command": "js-debug-adapter", "languages": ["javascript"] }
+//!   }
+//! }
+//! ```
+//!
+//! Only adapters that speak DAP over stdio are supported.
+
+use anyhow::{anyhow, Result};
+use dirs_next::config_dir;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::lsp::registry::{merge, read_layer};
+
+/// Resolved configuration of one debug adapter.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AdapterConfig {
+    /// Registry key, e.g. `debugpy` – also the DAP `adapterID`.
+    #[serde(skip_deserializing)]
+    pub id: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Languages the adapter debugs, for the frontend's adapter picker.
+    #[serde(default)]
+    pub languages: Vec<String>,
+    #[serde(default = "enabled_default")]
+    pub enabled: bool,
+}
+
+fn enabled_default() -> bool {
+    true
+}
+
+/// Built-in defaults.
+fn builtin_adapters() -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "debugpy",
+            json!({
+                "command": "python3",
+                "args": ["-m", "debugpy.adapter"],
+                "languages": ["python"],
+            }),
+        ),
+        (
+            "lldb",
+            json!({
+                "command": "lldb-dap",
+                "languages": ["rust", "c", "cpp"],
+            }),
+        ),
+    ]
+}
+
+/// Path of the user-level config file.
+pub fn user_config_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("glass-ide").join("dap.json"))
+}
+
+/// Path of the workspace-level config file for `root`.
+pub fn workspace_config_path(root: &Path) -> PathBuf {
+    root.join(".glass").join("dap.json")
+}
+
+/// Enabled adapters for `root` (built-ins first, then user/workspace
+/// additions by name).
+pub fn load(root: &Path) -> Vec<AdapterConfig> {
+    let mut entries: Vec<(String, Value)> = builtin_adapters()
+        .into_iter()
+        .map(|(id, cfg)| (id.to_string(), cfg))
+        .collect();
+
+    let layers = [
+        user_config_path()
+            .map(|p| read_layer(&p, "adapters"))
+            .unwrap_or_default(),
+        read_layer(&workspace_config_path(root), "adapters"),
+    ];
+    for layer in layers {
+        for (id, overlay) in layer {
+            match entries.iter_mut().find(|(existing, _)| *existing == id) {
+                Some((_, cfg)) => merge(cfg, overlay),
+                None => entries.push((id, overlay)),
+            }
+        }
+    }
+
+    entries
+        .into_iter()
+        .filter_map(
+            |(id, cfg)| match serde_json::from_value::<AdapterConfig>(cfg) {
+                Ok(adapter) => Some(AdapterConfig { id, ..adapter }),
+                Err(err) => {
+                    warn!("[DAP] Ignoring adapter `{id}`: {err}");
+                    None
+                }
+            },
+        )
+        .filter(|adapter| adapter.enabled)
+        .collect()
+}
+
+/// The enabled adapter `id` for `root`.
+pub fn resolve(root: &Path, id: &str) -> Result<AdapterConfig> {
+    load(root)
+        .into_iter()
+        .find(|adapter| adapter.id == id)
+        .ok_or_else(|| anyhow!("No debug adapter `{id}` configured for {}", root.display()))
+}
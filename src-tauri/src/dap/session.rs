@@ -0,0 +1,479 @@
+//! Debug sessions: one adapter process each.
+//!
+//! A [`Session`] owns the adapter's stdio. Requests get a per-session `seq`
+//! and are matched to their response by `request_seq`; events and reverse
+//! requests (`runInTerminal`…) are emitted to the frontend as they arrive:
+//!
+//! - `dap_event` – `{ session, event, body }` for every adapter event
+//!   (`stopped`, `output`, `breakpoint`, `terminated`…)
+//! - `dap_breakpoints` – `{ session, path, breakpoints }` with the adapter's
+//!   view of a file's breakpoints after every `setBreakpoints`
+//! - `dap_request` – `{ session, seq, command, arguments }`, answered via
+//!   `respond_dap_request`
+//! - `dap_session_ended` – `{ session, message }` once the adapter exited
+//!
+//! [`Session::start`] runs the whole start-up sequence, so callers never deal
+//! with `initialize` / `configurationDone`.
+
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use tauri::{AppHandle, Emitter, Runtime};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{ChildStdin, ChildStdout, Command};
+use tokio::sync::{oneshot, watch, Mutex};
+use tokio::time::{timeout, Duration};
+
+use super::breakpoints::{self, SourceBreakpoint};
+use super::registry::AdapterConfig;
+use crate::lsp::framing::{self, FrameReader, FramingError};
+
+/// Default upper bound for a request round-trip.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// `launch` / `attach` may build or wait for the debuggee.
+const LAUNCH_TIMEOUT: Duration = Duration::from_secs(60);
+/// Time for the `disconnect` response and for the adapter to exit afterwards.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Summary of a session, as returned by `dap_start` / `dap_sessions`.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub id: String,
+    pub root: String,
+    pub adapter: String,
+    /// `Capabilities` from the `initialize` response
+    pub capabilities: Value,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct EventPayload {
+    session: String,
+    event: String,
+    body: Value,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct BreakpointsPayload {
+    session: String,
+    path: PathBuf,
+    breakpoints: Value,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RequestPayload {
+    session: String,
+    seq: i64,
+    command: String,
+    arguments: Value,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct EndedPayload {
+    session: String,
+    message: String,
+}
+
+/// One debug adapter process.
+pub struct Session {
+    pub id: String,
+    pub root: String,
+    pub config: AdapterConfig,
+    /// The adapter's stdin.
+    writer: Mutex<ChildStdin>,
+    /// In-flight requests keyed by `seq`.
+    pending: StdMutex<HashMap<i64, oneshot::Sender<Value>>>,
+    next_seq: AtomicI64,
+    capabilities: StdMutex<Value>,
+    /// Set on the adapter's `initialized` event.
+    initialized: watch::Sender<bool>,
+    /// Signals the exit-watch task to kill the process.
+    kill: StdMutex<Option<oneshot::Sender<()>>>,
+    /// Set by the exit-watch task once the process is gone.
+    exited: watch::Sender<bool>,
+}
+
+impl Session {
+    /// Spawn the adapter in `root` plus its reader, stderr and exit-watch
+    /// tasks. The session is not started yet (see [`Session::start`]).
+    pub fn spawn<R: Runtime>(
+        app: &AppHandle<R>,
+        id: String,
+        root: &str,
+        config: AdapterConfig,
+    ) -> Result<Arc<Self>> {
+        let cmd = &config.command;
+        let mut child = Command::new(cmd)
+            .args(&config.args)
+            .envs(&config.env)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to spawn debug adapter `{cmd}`"))?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open stdin for debug adapter"))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open stdout for debug adapter"))?;
+        let stderr = child
+            .stderr
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open stderr for debug adapter"))?;
+
+        let (kill_tx, kill_rx) = oneshot::channel();
+        let session = Arc::new(Session {
+            id,
+            root: root.to_string(),
+            config,
+            writer: Mutex::new(stdin),
+            pending: StdMutex::new(HashMap::new()),
+            next_seq: AtomicI64::new(1),
+            capabilities: StdMutex::new(Value::Null),
+            initialized: watch::Sender::new(false),
+            kill: StdMutex::new(Some(kill_tx)),
+            exited: watch::Sender::new(false),
+        });
+
+        let reader = tokio::spawn(read_loop(
+            app.clone(),
+            session.clone(),
+            FrameReader::new(stdout),
+        ));
+        let adapter = session.config.id.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                info!("[DAP {adapter}] {line}");
+            }
+        });
+
+        let app = app.clone();
+        let watched = session.clone();
+        tokio::spawn(async move {
+            let status = tokio::select! {
+                status = child.wait() => status,
+                _ = kill_rx => {
+                    let _ = child.start_kill();
+                    child.wait().await
+                }
+            };
+            let message = match status {
+                Ok(status) => format!("exited with {status}"),
+                Err(err) => format!("wait failed: {err}"),
+            };
+            let _ = reader.await;
+            info!(
+                "[DAP] Session {} (`{}`) {message}",
+                watched.id, watched.config.id
+            );
+            watched.exited.send_replace(true);
+            let _ = app.emit(
+                "dap_session_ended",
+                EndedPayload {
+                    session: watched.id.clone(),
+                    message,
+                },
+            );
+        });
+        Ok(session)
+    }
+
+    pub fn info(&self) -> SessionInfo {
+        SessionInfo {
+            id: self.id.clone(),
+            root: self.root.clone(),
+            adapter: self.config.id.clone(),
+            capabilities: self.capabilities.lock().unwrap().clone(),
+        }
+    }
+
+    fn capability(&self, name: &str) -> Value {
+        self.capabilities.lock().unwrap()[name].clone()
+    }
+
+    /// Kill the adapter (no-op if it already exited).
+    pub fn kill(&self) {
+        if let Some(tx) = self.kill.lock().unwrap().take() {
+            let _ = tx.send(());
+        }
+    }
+
+    /// Wait until the adapter process is gone.
+    pub async fn wait_exit(&self) {
+        let _ = self.exited.subscribe().wait_for(|e| *e).await;
+    }
+
+    async fn write(&self, msg: &Value) -> Result<()> {
+        let mut writer = self.writer.lock().await;
+        framing::write_frame(&mut *writer, msg).await?;
+        Ok(())
+    }
+
+    /// Send a request and return the response `body`; unsuccessful responses
+    /// become errors carrying the adapter's message.
+    pub async fn request(&self, command: &str, arguments: Value, limit: Duration) -> Result<Value> {
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(seq, tx);
+
+        let request = json!({
+            "seq": seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        });
+        if let Err(err) = self.write(&request).await {
+            self.pending.lock().unwrap().remove(&seq);
+            return Err(err);
+        }
+
+        let response = match timeout(limit, rx).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => return Err(anyhow!("Debug adapter exited")),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&seq);
+                return Err(anyhow!(
+                    "`{command}` timed out after {}ms",
+                    limit.as_millis()
+                ));
+            }
+        };
+        if response["success"].as_bool() != Some(true) {
+            let message = response["body"]["error"]["format"]
+                .as_str()
+                .or_else(|| response["message"].as_str())
+                .unwrap_or("request failed");
+            return Err(anyhow!("`{command}` failed: {message}"));
+        }
+        Ok(response.get("body").cloned().unwrap_or(Value::Null))
+    }
+
+    /// Answer a reverse request emitted as `dap_request`.
+    pub async fn respond(
+        &self,
+        request_seq: i64,
+        command: &str,
+        success: bool,
+        body: Value,
+        message: Option<String>,
+    ) -> Result<()> {
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        let mut response = json!({
+            "seq": seq,
+            "type": "response",
+            "request_seq": request_seq,
+            "command": command,
+            "success": success,
+            "body": body,
+        });
+        if let Some(message) = message {
+            response["message"] = json!(message);
+        }
+        self.write(&response).await
+    }
+
+    /// `initialize`, then `launch` / `attach` with `configuration`, the
+    /// persisted breakpoints once the adapter is `initialized`, and
+    /// `configurationDone`. Adapters such as debugpy answer `launch` only
+    /// after `configurationDone`, so it is awaited last.
+    pub async fn start<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        kind: &str,
+        configuration: Value,
+        breakpoints: &BTreeMap<PathBuf, Vec<SourceBreakpoint>>,
+    ) -> Result<()> {
+        let arguments = json!({
+            "clientID": "glass-ide",
+            "clientName": "Glass IDE",
+            "adapterID": self.config.id,
+            "pathFormat": "path",
+            "linesStartAt1": true,
+            "columnsStartAt1": true,
+            "supportsVariableType": true,
+            "supportsRunInTerminalRequest": true,
+        });
+        let capabilities = self
+            .request("initialize", arguments, REQUEST_TIMEOUT)
+            .await?;
+        *self.capabilities.lock().unwrap() = capabilities;
+
+        let launch = self.request(kind, configuration, LAUNCH_TIMEOUT);
+        tokio::pin!(launch);
+        let mut initialized = self.initialized.subscribe();
+        let launched = tokio::select! {
+            result = &mut launch => Some(result?),
+            _ = initialized.wait_for(|i| *i) => None,
+        };
+        if launched.is_some() {
+            timeout(REQUEST_TIMEOUT, initialized.wait_for(|i| *i))
+                .await
+                .map_err(|_| anyhow!("`{}` never sent `initialized`", self.config.id))?
+                .map_err(|_| anyhow!("Debug adapter exited"))?;
+        }
+
+        for (file, breakpoints) in breakpoints {
+            if let Err(err) = self.set_breakpoints(app, file, breakpoints).await {
+                warn!(
+                    "[DAP] Failed to set breakpoints in {}: {err:#}",
+                    file.display()
+                );
+            }
+        }
+        if let Some(filters) = self.capability("exceptionBreakpointFilters").as_array() {
+            let filters: Vec<&Value> = filters
+                .iter()
+                .filter(|f| f["default"].as_bool() == Some(true))
+                .map(|f| &f["filter"])
+                .collect();
+            let arguments = json!({ "filters": filters });
+            self.request("setExceptionBreakpoints", arguments, REQUEST_TIMEOUT)
+                .await?;
+        }
+        if self
+            .capability("supportsConfigurationDoneRequest")
+            .as_bool()
+            == Some(true)
+        {
+            self.request("configurationDone", json!({}), REQUEST_TIMEOUT)
+                .await?;
+        }
+        if launched.is_none() {
+            launch.await?;
+        }
+        Ok(())
+    }
+
+    /// `setBreakpoints` for `file`, emitting the adapter's answer as
+    /// `dap_breakpoints`; returns its `Breakpoint`s.
+    pub async fn set_breakpoints<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        file: &Path,
+        breakpoints: &[SourceBreakpoint],
+    ) -> Result<Value> {
+        let arguments = breakpoints::arguments(file, breakpoints);
+        let body = self
+            .request("setBreakpoints", arguments, REQUEST_TIMEOUT)
+            .await?;
+        let breakpoints = body.get("breakpoints").cloned().unwrap_or(json!([]));
+        let _ = app.emit(
+            "dap_breakpoints",
+            BreakpointsPayload {
+                session: self.id.clone(),
+                path: file.to_path_buf(),
+                breakpoints: breakpoints.clone(),
+            },
+        );
+        Ok(breakpoints)
+    }
+
+    /// End the session: `disconnect` (terminating a launched debuggee unless
+    /// `terminate_debuggee` says otherwise), then kill the adapter if it is
+    /// still alive after [`SHUTDOWN_TIMEOUT`].
+    pub async fn stop(&self, terminate_debuggee: Option<bool>) {
+        if *self.exited.borrow() {
+            return;
+        }
+        let mut arguments = json!({});
+        if let Some(terminate) = terminate_debuggee {
+            arguments["terminateDebuggee"] = json!(terminate);
+        }
+        if let Err(err) = self
+            .request("disconnect", arguments, SHUTDOWN_TIMEOUT)
+            .await
+        {
+            warn!("[DAP] `{}` ignored disconnect: {err:#}", self.config.id);
+        }
+        if timeout(SHUTDOWN_TIMEOUT, self.wait_exit()).await.is_err() {
+            warn!("[DAP] `{}` did not exit, killing it", self.config.id);
+            self.kill();
+            self.wait_exit().await;
+        }
+    }
+}
+
+/// Per-session reader: routes responses to their waiters and emits events
+/// and reverse requests.
+async fn read_loop<R: Runtime>(
+    app: AppHandle<R>,
+    session: Arc<Session>,
+    mut stdout: FrameReader<ChildStdout>,
+) {
+    loop {
+        let msg = match stdout.next().await {
+            Ok(msg) => msg,
+            Err(FramingError::Closed) => break,
+            Err(err) if err.is_fatal() => {
+                warn!("[DAP] `{}` reader stopped: {err}", session.config.id);
+                break;
+            }
+            Err(err) => {
+                warn!(
+                    "[DAP] `{}` sent a malformed frame: {err}",
+                    session.config.id
+                );
+                continue;
+            }
+        };
+
+        match msg["type"].as_str() {
+            Some("response") => {
+                let Some(seq) = msg["request_seq"].as_i64() else {
+                    continue;
+                };
+                let waiter = session.pending.lock().unwrap().remove(&seq);
+                if let Some(waiter) = waiter {
+                    let _ = waiter.send(msg);
+                }
+            }
+            Some("event") => {
+                let event = msg["event"].as_str().unwrap_or_default().to_string();
+                if event == "initialized" {
+                    session.initialized.send_replace(true);
+                }
+                let _ = app.emit(
+                    "dap_event",
+                    EventPayload {
+                        session: session.id.clone(),
+                        event,
+                        body: msg.get("body").cloned().unwrap_or(Value::Null),
+                    },
+                );
+            }
+            Some("request") => {
+                let _ = app.emit(
+                    "dap_request",
+                    RequestPayload {
+                        session: session.id.clone(),
+                        seq: msg["seq"].as_i64().unwrap_or_default(),
+                        command: msg["command"].as_str().unwrap_or_default().to_string(),
+                        arguments: msg.get("arguments").cloned().unwrap_or(Value::Null),
+                    },
+                );
+            }
+            _ => warn!(
+                "[DAP] `{}` sent an unknown message: {msg}",
+                session.config.id
+            ),
+        }
+    }
+    // Dropping the senders wakes every waiter with an error.
+    session.pending.lock().unwrap().clear();
+}
@@ -0,0 +1,308 @@
+//! Bridge tests against `tools/mock-dap`, a scriptable stdio debug adapter.
+//!
+//! Each test gets its own workspace root with a `.glass/dap.json` that
+//! registers the `mock` adapter running the given script. The adapter appends
+//! every message it receives to `received.jsonl` in the root, and emitted
+//! events are collected from a mock Tauri app.
+
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tauri::test::{mock_app, MockRuntime};
+use tauri::{App, AppHandle};
+
+use super::*;
+use crate::test_support::{self, wait_until, Events};
+
+/// Path of the mock adapter binary, built on first use. `MOCK_DAP` overrides
+/// it for runs without cargo.
+fn mock_dap() -> &'static Path {
+    static PATH: Lazy<PathBuf> = Lazy::new(|| test_support::tool_binary("mock-dap", "MOCK_DAP"));
+    &PATH
+}
+
+struct Fixture {
+    _app: App<MockRuntime>,
+    app: AppHandle<MockRuntime>,
+    root: String,
+    events: Events,
+}
+
+impl Fixture {
+    fn new(name: &str, script: Value) -> Self {
+        let dir = test_support::temp_root("dap", name);
+        std::fs::create_dir_all(dir.join(".glass")).unwrap();
+        let script_path = test_support::write_script(&dir, script);
+        let config = json!({
+            "adapters": { "mock": { "command": mock_dap(), "args": [script_path] } },
+        });
+        std::fs::write(registry::workspace_config_path(&dir), config.to_string()).unwrap();
+
+        let mock = mock_app();
+        let app = mock.handle().clone();
+        let events = Events::listen(
+            &app,
+            &[
+                "dap_event",
+                "dap_breakpoints",
+                "dap_request",
+                "dap_session_ended",
+            ],
+        );
+        Fixture {
+            _app: mock,
+            app,
+            root: dir.to_string_lossy().into_owned(),
+            events,
+        }
+    }
+
+    fn path(&self, file: &str) -> PathBuf {
+        Path::new(&self.root).join(file)
+    }
+
+    async fn start(&self) -> SessionInfo {
+        let payload = DapStartRequest {
+            root: self.root.clone(),
+            adapter: "mock".into(),
+            request: "launch".into(),
+            configuration: json!({ "program": "main" }),
+        };
+        dap_start(self.app.clone(), payload).await.unwrap()
+    }
+
+    async fn request(
+        &self,
+        session: &str,
+        command: &str,
+        arguments: Value,
+    ) -> tauri::Result<Value> {
+        let payload = DapRequest {
+            session: session.into(),
+            command: command.into(),
+            arguments,
+            timeout_ms: None,
+        };
+        dap_request(payload).await
+    }
+
+    async fn set_breakpoints(&self, file: &str, lines: &[u64]) -> HashMap<String, Value> {
+        let payload = DapSetBreakpointsRequest {
+            root: self.root.clone(),
+            path: self.path(file),
+            breakpoints: lines
+                .iter()
+                .map(|&line| SourceBreakpoint {
+                    line,
+                    column: None,
+                    condition: None,
+                    hit_condition: None,
+                    log_message: None,
+                })
+                .collect(),
+        };
+        dap_set_breakpoints(self.app.clone(), payload)
+            .await
+            .unwrap()
+    }
+
+    /// Requests the adapter received, in order.
+    fn received(&self) -> Vec<Value> {
+        test_support::received(Path::new(&self.root))
+    }
+
+    fn commands(&self) -> Vec<String> {
+        self.received()
+            .iter()
+            .filter_map(|msg| msg["command"].as_str().map(str::to_string))
+            .collect()
+    }
+
+    /// Wait until an event `name` matching `pred` was emitted.
+    async fn event(&self, name: &str, pred: impl Fn(&Value) -> bool) -> Value {
+        self.events.wait_for(name, pred).await
+    }
+
+    async fn stop(self, session: &str) {
+        let payload = DapStopRequest {
+            session: session.into(),
+            terminate_debuggee: None,
+        };
+        let _ = dap_stop(payload).await;
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn start_sends_persisted_breakpoints_before_configuration_done() {
+    let fx = Fixture::new(
+        "start",
+        json!({
+            "commands": {
+                // Like debugpy: `launch` is only answered after `configurationDone`.
+                "launch": { "deferUntil": "configurationDone" },
+                "configurationDone": {
+                    "events": [{ "event": "stopped", "body": { "reason": "entry", "threadId": 1 } }],
+                },
+            },
+        }),
+    );
+    // Without a session the breakpoints are only persisted.
+    assert!(fx.set_breakpoints("src/main.rs", &[3, 7]).await.is_empty());
+    let stored = std::fs::read_to_string(breakpoints::file_path(Path::new(&fx.root))).unwrap();
+    let stored: Value = serde_json::from_str(&stored).unwrap();
+    assert_eq!(stored["files"]["src/main.rs"][1]["line"], 7);
+
+    let info = fx.start().await;
+    assert_eq!(info.adapter, "mock");
+    assert_eq!(info.capabilities["supportsConfigurationDoneRequest"], true);
+    assert_eq!(
+        fx.commands(),
+        [
+            "initialize",
+            "launch",
+            "setBreakpoints",
+            "configurationDone"
+        ]
+    );
+    let set = &fx.received()[2];
+    assert_eq!(
+        set["arguments"]["source"]["path"],
+        json!(fx.path("src/main.rs"))
+    );
+    assert_eq!(set["arguments"]["breakpoints"][0]["line"], 3);
+    assert_eq!(fx.received()[1]["arguments"]["program"], "main");
+
+    let event = fx
+        .event("dap_breakpoints", |e| e["session"] == info.id.as_str())
+        .await;
+    assert_eq!(event["breakpoints"][1]["verified"], true);
+    let event = fx.event("dap_event", |e| e["event"] == "stopped").await;
+    assert_eq!(event["session"], info.id.as_str());
+    assert_eq!(event["body"]["threadId"], 1);
+    fx.stop(&info.id).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn requests_are_routed_and_reverse_requests_answered() {
+    let fx = Fixture::new(
+        "requests",
+        json!({
+            "commands": {
+                "evaluate": { "body": { "result": "42", "variablesReference": 0 } },
+                "next": { "error": "cannot step" },
+                "custom/terminal": {
+                    "reverse": [{ "command": "runInTerminal", "arguments": { "args": ["ls"] } }],
+                },
+            },
+        }),
+    );
+    let info = fx.start().await;
+
+    let body = fx
+        .request(&info.id, "evaluate", json!({ "expression": "6 * 7" }))
+        .await
+        .unwrap();
+    assert_eq!(body["result"], "42");
+    let err = fx
+        .request(&info.id, "next", json!({ "threadId": 1 }))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("cannot step"), "{err}");
+    assert!(fx.request("nope", "threads", json!({})).await.is_err());
+
+    fx.request(&info.id, "custom/terminal", json!({}))
+        .await
+        .unwrap();
+    let request = fx
+        .event("dap_request", |e| e["command"] == "runInTerminal")
+        .await;
+    assert_eq!(request["arguments"]["args"][0], "ls");
+    let payload = DapRequestResponse {
+        session: info.id.clone(),
+        request_seq: request["seq"].as_i64().unwrap(),
+        command: "runInTerminal".into(),
+        success: true,
+        body: json!({ "processId": 4242 }),
+        message: None,
+    };
+    respond_dap_request(payload).await.unwrap();
+    let answered = wait_until(|| {
+        fx.received()
+            .into_iter()
+            .find(|msg| msg["type"] == "response")
+    })
+    .await
+    .expect("the adapter got no response");
+    assert_eq!(answered["request_seq"], request["seq"]);
+    assert_eq!(answered["body"]["processId"], 4242);
+    fx.stop(&info.id).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn breakpoint_changes_reach_running_sessions() {
+    let fx = Fixture::new("breakpoints", json!({}));
+    let info = fx.start().await;
+
+    let verified = fx.set_breakpoints("lib.py", &[10]).await;
+    assert_eq!(verified[&info.id][0]["verified"], true);
+    assert_eq!(verified[&info.id][0]["line"], 10);
+    let stored = dap_breakpoints(DapBreakpointsRequest {
+        root: fx.root.clone(),
+    })
+    .await
+    .unwrap();
+    assert_eq!(stored[&fx.path("lib.py")][0].line, 10);
+
+    // Clearing removes the file from the store and reaches the adapter too.
+    let verified = fx.set_breakpoints("lib.py", &[]).await;
+    assert_eq!(verified[&info.id], json!([]));
+    assert!(
+        !std::fs::read_to_string(breakpoints::file_path(Path::new(&fx.root)))
+            .unwrap()
+            .contains("lib.py")
+    );
+    fx.stop(&info.id).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sessions_end_on_stop_and_on_crash() {
+    let fx = Fixture::new(
+        "lifecycle",
+        json!({ "commands": { "custom/crash": { "crash": 3 } } }),
+    );
+    let (a, b) = (fx.start().await, fx.start().await);
+    let running: Vec<String> = dap_sessions()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|s| s.id)
+        .collect();
+    assert!(running.contains(&a.id) && running.contains(&b.id));
+
+    let payload = DapStopRequest {
+        session: a.id.clone(),
+        terminate_debuggee: Some(true),
+    };
+    dap_stop(payload).await.unwrap();
+    let disconnect = fx
+        .received()
+        .into_iter()
+        .find(|msg| msg["command"] == "disconnect")
+        .expect("no disconnect");
+    assert_eq!(disconnect["arguments"]["terminateDebuggee"], true);
+    fx.event("dap_session_ended", |e| e["session"] == a.id.as_str())
+        .await;
+
+    assert!(fx.request(&b.id, "custom/crash", json!({})).await.is_err());
+    let ended = fx
+        .event("dap_session_ended", |e| e["session"] == b.id.as_str())
+        .await;
+    assert!(ended["message"].as_str().unwrap().contains('3'));
+    let gone = wait_until(|| {
+        let sessions = SESSIONS.try_lock().ok()?;
+        (!sessions.contains_key(&b.id)).then_some(())
+    })
+    .await;
+    assert!(gone.is_some(), "crashed session was not removed");
+    fx.stop(&b.id).await;
+}
@@ -0,0 +1,215 @@
+//! Diagnostics store.
+//!
+//! Every server keeps the diagnostics it last published per URI, so the
+//! problems panel can query the whole workspace (`lsp_problems`) and the
+//! explorer can decorate files with counts (`lsp_problem_counts`) without
+//! having listened from start-up. A publish replaces the URI's previous set;
+//! everything a server published is dropped when its process goes away, as
+//! a restarted server publishes again from scratch.
+
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use tower_lsp::lsp_types::Url;
+
+/// `DiagnosticSeverity`; servers may omit it, which counts as an error.
+const ERROR: u64 = 1;
+const WARNING: u64 = 2;
+const INFORMATION: u64 = 3;
+const HINT: u64 = 4;
+
+/// Diagnostics of one server, by URI.
+#[derive(Default)]
+pub struct Diagnostics {
+    files: HashMap<String, Vec<Value>>,
+}
+
+impl Diagnostics {
+    /// Apply `textDocument/publishDiagnostics` params. Returns the URI, or
+    /// `None` for malformed params.
+    pub fn publish(&mut self, params: &Value) -> Option<String> {
+        let uri = params.get("uri")?.as_str()?.to_string();
+        let diagnostics = params.get("diagnostics")?.as_array()?;
+        if diagnostics.is_empty() {
+            self.files.remove(&uri);
+        } else {
+            self.files.insert(uri.clone(), diagnostics.clone());
+        }
+        Some(uri)
+    }
+
+    /// Drop everything; returns the URIs that had diagnostics.
+    pub fn clear(&mut self) -> Vec<String> {
+        self.files.drain().map(|(uri, _)| uri).collect()
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (&String, &Vec<Value>)> {
+        self.files.iter()
+    }
+}
+
+fn severity(diagnostic: &Value) -> u64 {
+    diagnostic
+        .get("severity")
+        .and_then(Value::as_u64)
+        .unwrap_or(ERROR)
+}
+
+/// Path of `uri` relative to `root` (`/`-separated), or absolute if it lies
+/// outside; `None` for non-file URIs.
+fn display_path(root: &str, uri: &str) -> Option<String> {
+    let path = Url::parse(uri).ok()?.to_file_path().ok()?;
+    Some(match path.strip_prefix(root) {
+        Ok(rel) => rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        Err(_) => path.to_string_lossy().into_owned(),
+    })
+}
+
+/// Which problems a query returns.
+pub struct Filter {
+    /// Least severe severity included (1 = errors only … 4 = everything)
+    severity: u64,
+    glob: Option<GlobMatcher>,
+    source: Option<String>,
+}
+
+impl Filter {
+    /// `path` is a glob matched against root-relative paths (`src/**/*.rs`);
+    /// `source` matches either the server id or the diagnostic's `source`.
+    pub fn new(severity: Option<u64>, path: Option<&str>, source: Option<String>) -> Result<Self> {
+        let glob = path
+            .map(|pat| {
+                GlobBuilder::new(pat.trim_start_matches('/'))
+                    .literal_separator(true)
+                    .build()
+                    .with_context(|| format!("Invalid path glob `{pat}`"))
+            })
+            .transpose()?;
+        Ok(Filter {
+            severity: severity.unwrap_or(HINT),
+            glob: glob.map(|g| g.compile_matcher()),
+            source,
+        })
+    }
+
+    fn matches_path(&self, path: Option<&str>) -> bool {
+        match (&self.glob, path) {
+            (None, _) => true,
+            (Some(glob), Some(path)) => glob.is_match(path),
+            (Some(_), None) => false,
+        }
+    }
+
+    fn matches(&self, server: &str, diagnostic: &Value) -> bool {
+        severity(diagnostic) <= self.severity
+            && self.source.as_ref().map_or(true, |source| {
+                source == server || diagnostic.get("source").and_then(Value::as_str) == Some(source)
+            })
+    }
+}
+
+/// A diagnostic with the file and server it belongs to.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Problem {
+    pub root: String,
+    pub server: String,
+    pub uri: String,
+    /// Root-relative path; absolute outside the root, `None` for non-file URIs
+    pub path: Option<String>,
+    pub diagnostic: Value,
+}
+
+/// Diagnostics of one file by severity, summed over all servers.
+#[derive(Serialize, Clone, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileCounts {
+    pub uri: String,
+    pub path: Option<String>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub information: usize,
+    pub hints: usize,
+}
+
+/// Problems matching `filter` in the stores of `(root, server id, store)`,
+/// ordered by path, severity and position.
+pub fn problems<'a>(
+    stores: impl IntoIterator<Item = (&'a str, &'a str, &'a Diagnostics)>,
+    filter: &Filter,
+) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for (root, server, store) in stores {
+        for (uri, diagnostics) in store.files() {
+            let path = display_path(root, uri);
+            if !filter.matches_path(path.as_deref()) {
+                continue;
+            }
+            problems.extend(
+                diagnostics
+                    .iter()
+                    .filter(|d| filter.matches(server, d))
+                    .map(|d| Problem {
+                        root: root.to_string(),
+                        server: server.to_string(),
+                        uri: uri.clone(),
+                        path: path.clone(),
+                        diagnostic: d.clone(),
+                    }),
+            );
+        }
+    }
+    let position = |p: &Problem| {
+        let start = &p.diagnostic["range"]["start"];
+        (start["line"].as_u64(), start["character"].as_u64())
+    };
+    problems.sort_by(|a, b| {
+        (&a.path, &a.uri, severity(&a.diagnostic), position(a)).cmp(&(
+            &b.path,
+            &b.uri,
+            severity(&b.diagnostic),
+            position(b),
+        ))
+    });
+    problems
+}
+
+/// Per-file counts of the problems matching `filter`, ordered by path.
+pub fn counts<'a>(
+    stores: impl IntoIterator<Item = (&'a str, &'a str, &'a Diagnostics)>,
+    filter: &Filter,
+) -> Vec<FileCounts> {
+    let mut files: BTreeMap<(Option<String>, String), FileCounts> = BTreeMap::new();
+    for problem in problems(stores, filter) {
+        let counts = files
+            .entry((problem.path.clone(), problem.uri.clone()))
+            .or_insert_with(|| FileCounts {
+                uri: problem.uri,
+                path: problem.path,
+                ..FileCounts::default()
+            });
+        match severity(&problem.diagnostic) {
+            WARNING => counts.warnings += 1,
+            INFORMATION => counts.information += 1,
+            HINT => counts.hints += 1,
+            _ => counts.errors += 1,
+        }
+    }
+    files.into_values().collect()
+}
+
+/// Payload of the `lsp_diagnostics` event: `PublishDiagnosticsParams` plus
+/// the server that published them (an empty list when they were dropped).
+#[derive(Serialize, Clone)]
+pub struct DiagnosticsEvent<'a> {
+    pub root: &'a str,
+    pub server: &'a str,
+    #[serde(flatten)]
+    pub params: Value,
+}
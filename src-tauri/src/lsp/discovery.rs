@@ -0,0 +1,451 @@
+//! Language-server discovery and health checks.
+//!
+//! Servers are spawned from whatever [`resolve_command`] finds, so the health
+//! report (`lsp_health`) and the actual start-up always agree. A `command` is
+//! looked up in this order:
+//! 1. as a path, if it contains a separator (relative paths against the root)
+//! 2. `node_modules/.bin` in the root and its ancestors
+//! 3. `PATH`
+//! 4. toolchain directories missing from the PATH of GUI apps:
+//!    `~/.cargo/bin` (rustup proxies) and `$GOPATH/bin` / `~/go/bin`
+//!
+//! The health check additionally runs each binary with its `versionArgs`
+//! and resolves rustup proxies with `rustup which`, because rustup installs a
+//! proxy for components like rust-analyzer even when the component itself is
+//! missing. It also scans the root for source files whose language no
+//! installed server handles, so the UI can point at what to install.
+
+use anyhow::{anyhow, Result};
+use globset::GlobSet;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+use walkdir::{DirEntry, WalkDir};
+
+use super::registry::{self, ServerConfig};
+use super::server::ServerState;
+use crate::commands::fs::should_ignore;
+use crate::commands::search_query::build_globset;
+
+/// Upper bound for a `--version` probe.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+/// Files looked at when scanning a root for languages.
+const MAX_SCANNED_FILES: usize = 50_000;
+
+/// Source languages worth a language server, by file extension.
+const KNOWN_LANGUAGES: &[(&str, &[&str])] = &[
+    ("rust", &["rs"]),
+    ("typescript", &["ts", "mts", "cts"]),
+    ("typescriptreact", &["tsx"]),
+    ("javascript", &["js", "mjs", "cjs"]),
+    ("javascriptreact", &["jsx"]),
+    ("python", &["py", "pyi"]),
+    ("go", &["go"]),
+    ("java", &["java"]),
+    ("kotlin", &["kt", "kts"]),
+    ("c", &["c", "h"]),
+    ("cpp", &["cc", "cpp", "cxx", "hh", "hpp", "hxx"]),
+    ("csharp", &["cs"]),
+    ("swift", &["swift"]),
+    ("ruby", &["rb"]),
+    ("php", &["php"]),
+    ("lua", &["lua"]),
+    ("zig", &["zig"]),
+    ("dart", &["dart"]),
+    ("elixir", &["ex", "exs"]),
+    ("haskell", &["hs"]),
+    ("ocaml", &["ml", "mli"]),
+    ("scala", &["scala"]),
+    ("shellscript", &["sh", "bash"]),
+    ("html", &["html", "htm"]),
+    ("css", &["css"]),
+    ("scss", &["scss"]),
+    ("vue", &["vue"]),
+    ("svelte", &["svelte"]),
+];
+
+/// Where a server binary was found.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Source {
+    /// `command` is a path
+    Config,
+    /// `node_modules/.bin`
+    Workspace,
+    Path,
+    /// `~/.cargo/bin`, Go bin directory
+    Toolchain,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Status {
+    Installed,
+    Missing,
+    /// Found, but the version probe failed (e.g. a rustup proxy for a
+    /// component that is not installed).
+    Broken,
+}
+
+/// Health of one configured server.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerHealth {
+    pub id: String,
+    pub command: String,
+    pub languages: Vec<String>,
+    pub status: Status,
+    pub path: Option<String>,
+    pub source: Option<Source>,
+    /// First line of the version output
+    pub version: Option<String>,
+    pub error: Option<String>,
+    /// State of the server if it was started for this root
+    pub state: Option<ServerState>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum MissingReason {
+    /// No configured server handles the language.
+    NotConfigured,
+    /// Configured servers are missing or broken.
+    NotInstalled,
+}
+
+/// A language found in the root that no working server handles.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MissingLanguage {
+    pub language: String,
+    /// Number of files of this language in the root
+    pub files: usize,
+    pub reason: MissingReason,
+    /// Configured servers that would handle it once installed
+    pub servers: Vec<String>,
+}
+
+/// Health report for one workspace root.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RootHealth {
+    pub root: String,
+    pub servers: Vec<ServerHealth>,
+    pub missing_languages: Vec<MissingLanguage>,
+}
+
+/// Resolve `config.command` for `root`, see the module docs for the order.
+pub fn resolve_command(config: &ServerConfig, root: &Path) -> Result<(PathBuf, Source)> {
+    let command = &config.command;
+    find_program(command, root).ok_or_else(|| {
+        if is_path(command) {
+            anyhow!(
+                "Language server `{}` does not exist",
+                root.join(command).display()
+            )
+        } else {
+            anyhow!(
+                "Language server `{command}` not found in node_modules/.bin, PATH, \
+                 ~/.cargo/bin or the Go bin directory – install it or set `command` in lsp.json"
+            )
+        }
+    })
+}
+
+fn is_path(command: &str) -> bool {
+    command.contains('/') || command.contains(std::path::MAIN_SEPARATOR)
+}
+
+/// Locate `command` for `root`: paths are taken relative to the root, bare
+/// names are looked up like language servers (also used for formatters).
+pub fn find_program(command: &str, root: &Path) -> Option<(PathBuf, Source)> {
+    if is_path(command) {
+        return executable(&root.join(command)).map(|path| (path, Source::Config));
+    }
+
+    let workspace = root
+        .ancestors()
+        .map(|dir| dir.join("node_modules").join(".bin"));
+    let path = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let mut dirs = workspace
+        .map(|dir| (dir, Source::Workspace))
+        .chain(path.into_iter().map(|dir| (dir, Source::Path)))
+        .chain(
+            toolchain_dirs()
+                .into_iter()
+                .map(|dir| (dir, Source::Toolchain)),
+        );
+    dirs.find_map(|(dir, source)| executable(&dir.join(command)).map(|path| (path, source)))
+}
+
+/// Toolchain bin directories, which GUI apps often lack in their `PATH`.
+fn toolchain_dirs() -> Vec<PathBuf> {
+    let home = dirs_next::home_dir();
+    let cargo = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|h| h.join(".cargo")));
+    let go = std::env::var_os("GOBIN").map(PathBuf::from).or_else(|| {
+        std::env::var_os("GOPATH")
+            .and_then(|p| std::env::split_paths(&p).next())
+            .or_else(|| home.as_ref().map(|h| h.join("go")))
+            .map(|p| p.join("bin"))
+    });
+    cargo.map(|c| c.join("bin")).into_iter().chain(go).collect()
+}
+
+/// `path` itself or, on Windows, with an executable extension – if it is an
+/// executable file.
+fn executable(path: &Path) -> Option<PathBuf> {
+    let candidates: Vec<PathBuf> = if cfg!(windows) {
+        ["exe", "cmd", "bat"]
+            .iter()
+            .map(|ext| {
+                let mut name = OsString::from(path.as_os_str());
+                name.push(".");
+                name.push(ext);
+                PathBuf::from(name)
+            })
+            .chain(std::iter::once(path.to_path_buf()))
+            .collect()
+    } else {
+        vec![path.to_path_buf()]
+    };
+    candidates.into_iter().find(|p| is_executable(p))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// The `rustup` binary next to `path` if `path` is one of its proxies
+/// (proxies are hard links to, or copies of, rustup itself).
+fn rustup_for_proxy(path: &Path) -> Option<PathBuf> {
+    let rustup = path.with_file_name(format!("rustup{}", std::env::consts::EXE_SUFFIX));
+    (rustup != path && same_file(path, &rustup)).then_some(rustup)
+}
+
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (a.metadata(), b.metadata()) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.metadata(), b.metadata()) {
+        (Ok(a), Ok(b)) => a.len() == b.len(),
+        _ => false,
+    }
+}
+
+/// Run `program args…` and return the first output line, or why it failed.
+async fn first_line(program: &Path, args: &[String], cwd: &Path) -> Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(cwd)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = timeout(VERSION_TIMEOUT, output)
+        .await
+        .map_err(|_| anyhow!("`{}` did not answer in time", program.display()))??;
+    let text = |bytes: &[u8]| {
+        String::from_utf8_lossy(bytes)
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty())
+            .map(str::to_string)
+    };
+    let line = text(&output.stdout).or_else(|| text(&output.stderr));
+    if output.status.success() {
+        Ok(line.unwrap_or_default())
+    } else {
+        Err(anyhow!(
+            "{}",
+            line.unwrap_or_else(|| format!("exited with {}", output.status))
+        ))
+    }
+}
+
+/// Probe one server: locate the binary and ask it for its version.
+pub async fn check_server(config: &ServerConfig, root: &Path) -> ServerHealth {
+    let mut health = ServerHealth {
+        id: config.id.clone(),
+        command: config.command.clone(),
+        languages: config.languages.clone(),
+        status: Status::Missing,
+        path: None,
+        source: None,
+        version: None,
+        error: None,
+        state: None,
+    };
+    if let Some(recording) = &config.replay {
+        health.status = Status::Installed;
+        health.path = Some(root.join(recording).to_string_lossy().into_owned());
+        health.version = Some("replay".to_string());
+        return health;
+    }
+    let (path, source) = match resolve_command(config, root) {
+        Ok(found) => found,
+        Err(err) => {
+            health.error = Some(err.to_string());
+            return health;
+        }
+    };
+    health.path = Some(path.to_string_lossy().into_owned());
+    health.source = Some(source);
+
+    if let Some(rustup) = rustup_for_proxy(&path) {
+        let which = vec!["which".to_string(), config.command.clone()];
+        if let Err(err) = first_line(&rustup, &which, root).await {
+            health.status = Status::Broken;
+            health.error = Some(format!(
+                "rustup proxy without the component ({err:#}) – try `rustup component add {}`",
+                config.command
+            ));
+            return health;
+        }
+    }
+    if config.version_args.is_empty() {
+        health.status = Status::Installed;
+        return health;
+    }
+    match first_line(&path, &config.version_args, root).await {
+        Ok(version) => {
+            health.status = Status::Installed;
+            health.version = Some(version).filter(|v| !v.is_empty());
+        }
+        Err(err) => {
+            health.status = Status::Broken;
+            health.error = Some(format!("{err:#}"));
+        }
+    }
+    health
+}
+
+fn is_hidden(entry: &DirEntry) -> bool {
+    entry
+        .file_name()
+        .to_str()
+        .map(|s| s.starts_with('.'))
+        .unwrap_or(false)
+}
+
+/// Known-language file counts in `root`, skipping hidden and ignored dirs.
+fn scan_languages(root: &Path) -> BTreeMap<&'static str, Vec<PathBuf>> {
+    let mut found: BTreeMap<&'static str, Vec<PathBuf>> = BTreeMap::new();
+    let files = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || (!is_hidden(e) && !should_ignore(e.path())))
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .take(MAX_SCANNED_FILES);
+    for entry in files {
+        let ext = entry
+            .path()
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        if let Some((language, _)) = KNOWN_LANGUAGES.iter().find(|(_, exts)| exts.contains(&ext)) {
+            let rel = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            found.entry(language).or_default().push(rel.to_path_buf());
+        }
+    }
+    found
+}
+
+/// Languages in `root` without a working server. A file counts as handled
+/// if an installed server matches its language id or its path.
+fn missing_languages(
+    root: &Path,
+    servers: &[(ServerConfig, ServerHealth)],
+) -> Vec<MissingLanguage> {
+    // One glob set per server, not one per file and server.
+    let globs: Vec<Option<GlobSet>> = servers
+        .iter()
+        .map(|(config, _)| build_globset(&config.file_patterns, false))
+        .collect();
+    scan_languages(root)
+        .into_iter()
+        .filter_map(|(language, files)| {
+            let candidates: Vec<&(ServerConfig, ServerHealth)> = servers
+                .iter()
+                .zip(&globs)
+                .filter(|((config, _), globs)| {
+                    config.languages.iter().any(|l| l == language)
+                        || globs
+                            .as_ref()
+                            .is_some_and(|set| files.iter().any(|f| set.is_match(f)))
+                })
+                .map(|(server, _)| server)
+                .collect();
+            if candidates
+                .iter()
+                .any(|(_, health)| health.status == Status::Installed)
+            {
+                return None;
+            }
+            Some(MissingLanguage {
+                language: language.to_string(),
+                files: files.len(),
+                reason: if candidates.is_empty() {
+                    MissingReason::NotConfigured
+                } else {
+                    MissingReason::NotInstalled
+                },
+                servers: candidates.iter().map(|(c, _)| c.id.clone()).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Probe every configured server of `root` and scan it for languages
+/// without one. `state` reports servers already started for the root.
+pub async fn check_root(root: &Path, state: impl Fn(&str) -> Option<ServerState>) -> RootHealth {
+    let probes: Vec<_> = registry::load(root)
+        .into_iter()
+        .map(|config| {
+            let root = root.to_path_buf();
+            tokio::spawn(async move {
+                let health = check_server(&config, &root).await;
+                (config, health)
+            })
+        })
+        .collect();
+    let mut servers = Vec::new();
+    for probe in probes {
+        if let Ok((config, mut health)) = probe.await {
+            health.state = state(&config.id);
+            servers.push((config, health));
+        }
+    }
+    let scan_root = root.to_path_buf();
+    let scan_servers = servers.clone();
+    let missing_languages =
+        tokio::task::spawn_blocking(move || missing_languages(&scan_root, &scan_servers))
+            .await
+            .unwrap_or_default();
+    RootHealth {
+        root: root.to_string_lossy().into_owned(),
+        servers: servers.into_iter().map(|(_, health)| health).collect(),
+        missing_languages,
+    }
+}
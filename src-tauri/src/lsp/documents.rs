@@ -0,0 +1,205 @@
+//! Open-document tracking for the LSP bridge.
+//!
+//! Two levels of state:
+//! • [`OPEN_DOCUMENTS`] – the editor's buffers, owned by the backend's
+//!   `lsp_*_document` commands and fanned out to every server handling them.
+//! • [`Documents`] – per server, mirrors the `textDocument/didOpen` /
+//!   `didChange` / `didClose` notifications actually sent so the exact state
+//!   can be replayed (`didOpen`) after the server restarts.
+//!
+//! Incremental changes are applied with UTF-16 positions, as mandated by the
+//! protocol.
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::commands::editorconfig;
+
+/// An editor buffer synchronised with language servers.
+pub struct OpenDocument {
+    /// Folder whose servers the document was opened on.
+    pub root: String,
+    pub language_id: String,
+    pub version: i64,
+    pub text: String,
+    /// Ids of the servers the document is open on.
+    pub servers: Vec<String>,
+}
+
+/// Editor buffers keyed by URI.
+pub static OPEN_DOCUMENTS: Lazy<Mutex<HashMap<String, OpenDocument>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Buffer text of an open document – may be newer than the file on disk.
+pub fn open_text(uri: &str) -> Option<String> {
+    OPEN_DOCUMENTS
+        .lock()
+        .unwrap()
+        .get(uri)
+        .map(|doc| doc.text.clone())
+}
+
+/// Text of document `uri` at `path`: the synchronised editor buffer if open,
+/// else the file on disk, decoded like the editor reads it.
+pub fn document_text(uri: &str, path: &Path) -> Result<String> {
+    match open_text(uri) {
+        Some(text) => Ok(text),
+        None => editorconfig::read_text(path),
+    }
+}
+
+/// Text of a document as last sent to the server.
+pub struct TrackedDocument {
+    pub uri: String,
+    pub language_id: String,
+    pub version: i64,
+    pub text: String,
+}
+
+/// Documents currently open on one server, keyed by URI.
+#[derive(Default)]
+pub struct Documents {
+    docs: HashMap<String, TrackedDocument>,
+}
+
+impl Documents {
+    /// Update the tracked state from an outgoing notification. Returns `false`
+    /// for methods that do not affect document state.
+    pub fn track(&mut self, method: &str, params: &Value) -> bool {
+        let Some(uri) = params.pointer("/textDocument/uri").and_then(Value::as_str) else {
+            return false;
+        };
+        let version = params
+            .pointer("/textDocument/version")
+            .and_then(Value::as_i64);
+
+        match method {
+            "textDocument/didOpen" => {
+                let doc = TrackedDocument {
+                    uri: uri.to_string(),
+                    language_id: params
+                        .pointer("/textDocument/languageId")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    version: version.unwrap_or(0),
+                    text: params
+                        .pointer("/textDocument/text")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                };
+                self.docs.insert(uri.to_string(), doc);
+            }
+            "textDocument/didChange" => {
+                let Some(doc) = self.docs.get_mut(uri) else {
+                    return false;
+                };
+                let changes = params.get("contentChanges").and_then(Value::as_array);
+                for change in changes.into_iter().flatten() {
+                    apply_change(&mut doc.text, change);
+                }
+                if let Some(version) = version {
+                    doc.version = version;
+                }
+            }
+            "textDocument/didClose" => {
+                self.docs.remove(uri);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// `didOpen` notifications restoring every tracked document.
+    pub fn did_open_notifications(&self) -> Vec<Value> {
+        self.docs
+            .values()
+            .map(|doc| {
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/didOpen",
+                    "params": {
+                        "textDocument": {
+                            "uri": doc.uri,
+                            "languageId": doc.language_id,
+                            "version": doc.version,
+                            "text": doc.text,
+                        }
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+/// Byte offset of an LSP position (UTF-16 `character`) in `text`, clamped to
+/// the end of the line / document.
+pub fn offset_at(text: &str, line: usize, character: usize) -> usize {
+    let mut line_start = 0;
+    for _ in 0..line {
+        match text[line_start..].find('\n') {
+            Some(pos) => line_start += pos + 1,
+            None => return text.len(),
+        }
+    }
+    let line_end = text[line_start..]
+        .find('\n')
+        .map_or(text.len(), |pos| line_start + pos);
+
+    let mut units = 0;
+    for (i, ch) in text[line_start..line_end].char_indices() {
+        if units >= character {
+            return line_start + i;
+        }
+        units += ch.len_utf16();
+    }
+    line_end
+}
+
+/// LSP position (line, UTF-16 `character`) of byte `offset` in `text` – the
+/// inverse of [`offset_at`].
+pub fn position_at(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+    let line = before.matches('\n').count();
+    (line, before[line_start..].encode_utf16().count())
+}
+
+/// Byte range of an LSP `Range` in `text`; an end before the start is
+/// clamped to the start.
+pub fn byte_range(text: &str, range: &Value) -> (usize, usize) {
+    let pos = |key: &str| {
+        let line = range
+            .pointer(&format!("/{key}/line"))
+            .and_then(Value::as_u64);
+        let character = range
+            .pointer(&format!("/{key}/character"))
+            .and_then(Value::as_u64);
+        offset_at(
+            text,
+            line.unwrap_or(0) as usize,
+            character.unwrap_or(0) as usize,
+        )
+    };
+    let start = pos("start");
+    (start, pos("end").max(start))
+}
+
+/// Apply one `TextDocumentContentChangeEvent` – ranged or full replacement.
+pub fn apply_change(text: &mut String, change: &Value) {
+    let new_text = change
+        .get("text")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let Some(range) = change.get("range") else {
+        *text = new_text.to_string();
+        return;
+    };
+    let (start, end) = byte_range(text, range);
+    text.replace_range(start..end, new_text);
+}
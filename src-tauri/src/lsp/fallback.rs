@@ -0,0 +1,209 @@
+//! Built-in, index-backed answers for a few LSP requests.
+//!
+//! Used by `invoke_lsp` when no language server can be started for a root
+//! (binary missing, server crashed…), so basic navigation keeps working
+//! offline on any machine. Everything is backed by the tree-sitter symbol
+//! index in `commands::symbol_indexer`:
+//! • `workspace/symbol`            – fuzzy search over the index of every
+//!   workspace root
+//! • `textDocument/documentSymbol` – fresh parse of the document (open
+//!   buffer if synchronised, else the file on disk)
+//! • `textDocument/definition`     – identifier under the cursor, resolved by
+//!   exact name (same file first, then nearest path)
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::Url;
+
+use super::documents;
+use crate::commands::symbol_indexer::{self, Symbol, SymbolKind};
+use crate::commands::workspace;
+
+const WORKSPACE_SYMBOL_LIMIT: usize = 200;
+const DEFINITION_LIMIT: usize = 10;
+
+/// Whether `method` can be answered without a language server.
+pub fn handles(method: &str) -> bool {
+    matches!(
+        method,
+        "workspace/symbol" | "textDocument/documentSymbol" | "textDocument/definition"
+    )
+}
+
+/// Answer `request` from the symbol index, returning a complete JSON-RPC
+/// response object (same shape a real server would send). `workspace` is the
+/// directory or `.glass-workspace` file the request was sent for, `root` the
+/// root resolved from it for the request's document.
+pub fn respond(workspace: &Path, root: &Path, request: &Value) -> Result<Value> {
+    let method = request.get("method").and_then(Value::as_str).unwrap_or("");
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    let result = match method {
+        "workspace/symbol" => workspace_symbol(workspace, root, &params),
+        "textDocument/documentSymbol" => document_symbol(&params)?,
+        "textDocument/definition" => definition(root, &params)?,
+        _ => return Err(anyhow!("No built-in fallback for `{method}`")),
+    };
+    Ok(json!({
+        "jsonrpc": "2.0",
+        "id": request.get("id").cloned().unwrap_or(Value::Null),
+        "result": result,
+    }))
+}
+
+fn symbol_information(uri: &Url, symbol: &Symbol) -> Value {
+    json!({
+        "name": symbol.name,
+        "kind": symbol.kind.lsp_kind(),
+        "containerName": symbol.container,
+        "location": { "uri": uri, "range": symbol.range },
+    })
+}
+
+/// Path and text of the request's document (see [`documents::document_text`]).
+fn document(params: &Value) -> Result<(PathBuf, String)> {
+    let uri = params
+        .pointer("/textDocument/uri")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("Missing textDocument.uri"))?;
+    let path = Url::parse(uri)?
+        .to_file_path()
+        .map_err(|_| anyhow!("Not a file URI: {uri}"))?;
+    let text = documents::document_text(uri, &path)?;
+    Ok((path, text))
+}
+
+/// Search every root of `workspace` (just `root` if it cannot be loaded).
+fn workspace_symbol(workspace: &Path, root: &Path, params: &Value) -> Value {
+    let query = params.get("query").and_then(Value::as_str).unwrap_or("");
+    let mut roots: Vec<PathBuf> = workspace::resolve_roots(workspace)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|r| r.path)
+        .collect();
+    if roots.is_empty() {
+        roots.push(root.to_path_buf());
+    }
+    let symbols = symbol_indexer::search_roots(&roots, query, WORKSPACE_SYMBOL_LIMIT);
+    Value::Array(
+        symbols
+            .iter()
+            .filter_map(|(root, rel, s)| {
+                let uri = Url::from_file_path(root.join(rel)).ok()?;
+                Some(symbol_information(&uri, s))
+            })
+            .collect(),
+    )
+}
+
+fn document_symbol(params: &Value) -> Result<Value> {
+    let (path, text) = document(params)?;
+    let uri = Url::from_file_path(&path).map_err(|_| anyhow!("Invalid document path"))?;
+    let symbols = symbol_indexer::extract_symbols(&path, &text).unwrap_or_default();
+    Ok(Value::Array(
+        symbols
+            .iter()
+            .map(|s| symbol_information(&uri, s))
+            .collect(),
+    ))
+}
+
+/// Identifier under an LSP position (UTF-16 `character`) in `text`.
+fn word_at(text: &str, line: usize, character: usize) -> Option<String> {
+    let line_text = text.lines().nth(line)?;
+    // Convert the UTF-16 column into a char index.
+    let mut units = 0;
+    let mut col = line_text.chars().count();
+    for (i, ch) in line_text.chars().enumerate() {
+        if units >= character {
+            col = i;
+            break;
+        }
+        units += ch.len_utf16();
+    }
+
+    let chars: Vec<char> = line_text.chars().collect();
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let mut start = col.min(chars.len());
+    while start > 0 && is_ident(chars[start - 1]) {
+        start -= 1;
+    }
+    let mut end = col.min(chars.len());
+    while end < chars.len() && is_ident(chars[end]) {
+        end += 1;
+    }
+    (start < end).then(|| chars[start..end].iter().collect())
+}
+
+/// Lower is better: definitions of types and callables beat fields/variables.
+fn kind_rank(kind: SymbolKind) -> u8 {
+    match kind {
+        SymbolKind::Class
+        | SymbolKind::Struct
+        | SymbolKind::Interface
+        | SymbolKind::Enum
+        | SymbolKind::TypeAlias
+        | SymbolKind::Function
+        | SymbolKind::Method
+        | SymbolKind::Macro
+        | SymbolKind::Module => 0,
+        SymbolKind::Constant | SymbolKind::EnumMember => 1,
+        SymbolKind::Field | SymbolKind::Variable => 2,
+    }
+}
+
+/// Number of leading path components shared by two relative paths.
+fn shared_prefix(a: &Path, b: &Path) -> usize {
+    a.components()
+        .zip(b.components())
+        .take_while(|(x, y)| x == y)
+        .count()
+}
+
+fn definition(root: &Path, params: &Value) -> Result<Value> {
+    let (path, text) = document(params)?;
+    let line = params
+        .pointer("/position/line")
+        .and_then(Value::as_u64)
+        .unwrap_or(0) as usize;
+    let character = params
+        .pointer("/position/character")
+        .and_then(Value::as_u64)
+        .unwrap_or(0) as usize;
+    let Some(word) = word_at(&text, line, character) else {
+        return Ok(Value::Null);
+    };
+
+    // Same-file definitions win – parse the current text so they are never stale.
+    let uri = Url::from_file_path(&path).map_err(|_| anyhow!("Invalid document path"))?;
+    let local: Vec<Value> = symbol_indexer::extract_symbols(&path, &text)
+        .unwrap_or_default()
+        .iter()
+        .filter(|s| s.name == word)
+        .map(|s| json!({ "uri": uri, "range": s.selection_range }))
+        .collect();
+    if !local.is_empty() {
+        return Ok(Value::Array(local));
+    }
+
+    let rel = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+    let mut candidates = symbol_indexer::lookup_exact(root, &word);
+    candidates.sort_by(|(pa, sa), (pb, sb)| {
+        kind_rank(sa.kind)
+            .cmp(&kind_rank(sb.kind))
+            .then_with(|| {
+                shared_prefix(&rel, Path::new(pb)).cmp(&shared_prefix(&rel, Path::new(pa)))
+            })
+            .then_with(|| pa.cmp(pb))
+    });
+    Ok(Value::Array(
+        candidates
+            .iter()
+            .take(DEFINITION_LIMIT)
+            .filter_map(|(p, s)| {
+                let uri = Url::from_file_path(root.join(p)).ok()?;
+                Some(json!({ "uri": uri, "range": s.selection_range }))
+            })
+            .collect(),
+    ))
+}
@@ -0,0 +1,504 @@
+//! Document formatting (`lsp_format_document`).
+//!
+//! A document is formatted by the first of its language servers with a
+//! `documentFormattingProvider`. Without one – or with `lspFormatting`
+//! turned off for the language – an external formatter runs on the buffer
+//! text instead: it reads the text on stdin and writes the result to stdout,
+//! in the root directory. The result is returned as `TextEdit`s against the
+//! text that was formatted, so the editor can apply it like any other edit.
+//!
+//! Formatters and per-language settings are layered like the server
+//! registry ([`super::registry`]):
+//! 1. built-in defaults ([`builtin_formatters`])
+//! 2. user config:      `<config dir>/glass-ide/format.json`
+//! 3. workspace config: `<root>/.glass/format.json`
+//!
+//! ```json
+//! {
+//!   "formatters": {
+//!     "prettier": { "args": ["--stdin-filepath", "${file}", "--single-quote"] },
+//!     "gofmt": { "command": "gofmt", "languages": ["go"], "filePatterns": ["*.go"] }
+//!   },
+//!   "languages": {
+//!     "python": { "formatter": "black", "lspFormatting": false },
+//!     "markdown": { "enabled": false },
+//!     "typescript": { "tabSize": 4 }
+//!   }
+//! }
+//! ```
+//!
+//! Arguments may use `${file}` (absolute path of the document), `${tabSize}`
+//! and `${insertSpaces}`. Indentation from `.editorconfig` takes precedence
+//! over the language settings, and its `end_of_line`,
+//! `trim_trailing_whitespace` and `insert_final_newline` are applied to
+//! whatever the formatter returned (see [`EditorConfig::normalize`]).
+
+use anyhow::{anyhow, Context, Result};
+use dirs_next::config_dir;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+
+use super::documents::{position_at, OPEN_DOCUMENTS};
+use super::registry::{matches_patterns, merge, read_layer};
+use super::{discovery, find_server, workspace_edit};
+use crate::commands::editorconfig::{self, EditorConfig, IndentStyle};
+
+/// Upper bound for one run of an external formatter.
+const FORMAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Resolved configuration of one external formatter.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatterConfig {
+    /// Registry key, e.g. `prettier`.
+    #[serde(skip)]
+    pub id: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// LSP language ids the formatter handles.
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// Globs for files the formatter handles when the language is unknown;
+    /// patterns without `/` match the file name at any depth.
+    #[serde(default)]
+    pub file_patterns: Vec<String>,
+    #[serde(default = "enabled_default")]
+    pub enabled: bool,
+}
+
+/// Formatting settings of one language.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguageSettings {
+    /// External formatter id; default: the first one handling the language.
+    #[serde(default)]
+    pub formatter: Option<String>,
+    /// Prefer the language server's `textDocument/formatting`.
+    #[serde(default = "enabled_default")]
+    pub lsp_formatting: bool,
+    #[serde(default = "enabled_default")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub tab_size: Option<u32>,
+    #[serde(default)]
+    pub insert_spaces: Option<bool>,
+}
+
+impl Default for LanguageSettings {
+    fn default() -> Self {
+        LanguageSettings {
+            formatter: None,
+            lsp_formatting: true,
+            enabled: true,
+            tab_size: None,
+            insert_spaces: None,
+        }
+    }
+}
+
+fn enabled_default() -> bool {
+    true
+}
+
+/// Built-in defaults, in priority order.
+fn builtin_formatters() -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "rustfmt",
+            json!({
+                "command": "rustfmt",
+                "args": ["--edition", "2021"],
+                "languages": ["rust"],
+                "filePatterns": ["*.rs"],
+            }),
+        ),
+        (
+            "prettier",
+            json!({
+                "command": "prettier",
+                "args": ["--stdin-filepath", "${file}"],
+                "languages": [
+                    "typescript", "typescriptreact", "javascript", "javascriptreact",
+                    "json", "jsonc", "css", "scss", "less", "html", "vue", "markdown", "yaml",
+                ],
+                "filePatterns": [
+                    "*.ts", "*.tsx", "*.mts", "*.cts", "*.js", "*.jsx", "*.mjs", "*.cjs",
+                    "*.json", "*.css", "*.scss", "*.less", "*.html", "*.vue", "*.md",
+                    "*.yaml", "*.yml",
+                ],
+            }),
+        ),
+        (
+            "black",
+            json!({
+                "command": "black",
+                "args": ["--quiet", "--stdin-filename", "${file}", "-"],
+                "languages": ["python"],
+                "filePatterns": ["*.py", "*.pyi"],
+            }),
+        ),
+    ]
+}
+
+/// Path of the user-level config file.
+pub fn user_config_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("glass-ide").join("format.json"))
+}
+
+/// Path of the workspace-level config file for `root`.
+pub fn workspace_config_path(root: &Path) -> PathBuf {
+    root.join(".glass").join("format.json")
+}
+
+/// Formatting configuration of a root.
+pub struct Config {
+    /// Enabled formatters in priority order.
+    pub formatters: Vec<FormatterConfig>,
+    pub languages: HashMap<String, LanguageSettings>,
+}
+
+/// Formatting configuration for `root`.
+pub fn load(root: &Path) -> Config {
+    let layer = |section: &str| {
+        [
+            user_config_path()
+                .map(|p| read_layer(&p, section))
+                .unwrap_or_default(),
+            read_layer(&workspace_config_path(root), section),
+        ]
+    };
+
+    let mut entries: Vec<(String, Value)> = builtin_formatters()
+        .into_iter()
+        .map(|(id, cfg)| (id.to_string(), cfg))
+        .collect();
+    for layer in layer("formatters") {
+        for (id, overlay) in layer {
+            match entries.iter_mut().find(|(existing, _)| *existing == id) {
+                Some((_, cfg)) => merge(cfg, overlay),
+                None => entries.push((id, overlay)),
+            }
+        }
+    }
+    let formatters = entries
+        .into_iter()
+        .filter_map(
+            |(id, cfg)| match serde_json::from_value::<FormatterConfig>(cfg) {
+                Ok(formatter) => Some(FormatterConfig { id, ..formatter }),
+                Err(err) => {
+                    warn!("[LSP] Ignoring formatter `{id}`: {err}");
+                    None
+                }
+            },
+        )
+        .filter(|formatter| formatter.enabled)
+        .collect();
+
+    let mut languages = Map::new();
+    for layer in layer("languages") {
+        for (language, overlay) in layer {
+            merge(languages.entry(language).or_insert(Value::Null), overlay);
+        }
+    }
+    let languages = languages
+        .into_iter()
+        .filter_map(|(language, cfg)| match serde_json::from_value(cfg) {
+            Ok(settings) => Some((language, settings)),
+            Err(err) => {
+                warn!("[LSP] Ignoring formatting settings for `{language}`: {err}");
+                None
+            }
+        })
+        .collect();
+    Config {
+        formatters,
+        languages,
+    }
+}
+
+impl Config {
+    /// Settings for `language`, defaults if it has none.
+    pub fn language(&self, language: Option<&str>) -> LanguageSettings {
+        language
+            .and_then(|l| self.languages.get(l))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// External formatter for a document: the language's `formatter`, else
+    /// the first one handling the language id or matching `rel`.
+    pub fn formatter(
+        &self,
+        settings: &LanguageSettings,
+        language: Option<&str>,
+        rel: &Path,
+    ) -> Option<&FormatterConfig> {
+        if let Some(id) = &settings.formatter {
+            let formatter = self.formatters.iter().find(|f| f.id == *id);
+            if formatter.is_none() {
+                warn!("[LSP] Formatter `{id}` is not configured or disabled");
+            }
+            return formatter;
+        }
+        self.formatters.iter().find(|f| {
+            language.is_some_and(|l| f.languages.iter().any(|x| x == l))
+                || matches_patterns(&f.file_patterns, rel)
+        })
+    }
+}
+
+/// Effective formatting options of a document.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Options {
+    pub tab_size: u32,
+    pub insert_spaces: bool,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+}
+
+impl Options {
+    /// `.editorconfig` first, then the language settings, then 4 spaces.
+    pub fn new(settings: &LanguageSettings, config: &EditorConfig) -> Self {
+        Options {
+            tab_size: config.indent_size.or(settings.tab_size).unwrap_or(4),
+            insert_spaces: config
+                .indent_style
+                .map(|style| style == IndentStyle::Space)
+                .or(settings.insert_spaces)
+                .unwrap_or(true),
+            trim_trailing_whitespace: config.trim_trailing_whitespace,
+            insert_final_newline: config.insert_final_newline,
+        }
+    }
+
+    /// LSP `FormattingOptions`.
+    pub fn to_lsp(self) -> Value {
+        let mut options = json!({
+            "tabSize": self.tab_size,
+            "insertSpaces": self.insert_spaces,
+        });
+        if let Some(trim) = self.trim_trailing_whitespace {
+            options["trimTrailingWhitespace"] = json!(trim);
+        }
+        match self.insert_final_newline {
+            Some(true) => options["insertFinalNewline"] = json!(true),
+            Some(false) => options["trimFinalNewlines"] = json!(true),
+            None => {}
+        }
+        options
+    }
+}
+
+/// Result of formatting a document.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Formatted {
+    /// `TextEdit`s against the formatted text; empty if nothing changed.
+    pub edits: Vec<Value>,
+    /// Id of the language server or external formatter that ran; `None` if
+    /// only `.editorconfig` rules were applied.
+    pub formatter: Option<String>,
+}
+
+/// Format the document at `path` (in `root`, URI `uri`) whose buffer holds
+/// `text`. Language servers are only asked if `text` is what they have open.
+pub async fn format(
+    root: &str,
+    uri: &str,
+    path: &Path,
+    language: Option<&str>,
+    text: &str,
+) -> Result<Formatted> {
+    let config = load(Path::new(root));
+    let settings = config.language(language);
+    if !settings.enabled {
+        return Ok(Formatted {
+            edits: Vec::new(),
+            formatter: None,
+        });
+    }
+    let editorconfig = editorconfig::resolve(path);
+    let options = Options::new(&settings, &editorconfig);
+
+    if settings.lsp_formatting {
+        match format_with_server(uri, text, options).await {
+            Ok(Some((server, edits))) => {
+                let formatted = workspace_edit::apply_text_edits(text, &edits)?;
+                let normalized = editorconfig.normalize(&formatted);
+                // Keep the server's edits (and with them the cursor) unless
+                // `.editorconfig` changed something on top.
+                let edits = if normalized == formatted {
+                    edits
+                } else {
+                    diff_edits(text, &normalized)
+                };
+                return Ok(Formatted {
+                    edits,
+                    formatter: Some(server),
+                });
+            }
+            Ok(None) => {}
+            Err(err) => warn!("[LSP] Formatting {uri} on the server failed: {err:#}"),
+        }
+    }
+
+    let rel = path.strip_prefix(root).unwrap_or(path);
+    let (formatted, formatter) = match config.formatter(&settings, language, rel) {
+        Some(formatter) => (
+            run_external(formatter, Path::new(root), path, text, options).await?,
+            Some(formatter.id.clone()),
+        ),
+        None => (text.to_string(), None),
+    };
+    let formatted = editorconfig.normalize(&formatted);
+    Ok(Formatted {
+        edits: diff_edits(text, &formatted),
+        formatter,
+    })
+}
+
+/// Ask the first server of the open document `uri` that can format it.
+/// `None` if the document is not open with `text` or no server can format.
+async fn format_with_server(
+    uri: &str,
+    text: &str,
+    options: Options,
+) -> Result<Option<(String, Vec<Value>)>> {
+    let (root, servers) = {
+        let open = OPEN_DOCUMENTS.lock().unwrap();
+        match open.get(uri) {
+            Some(doc) if doc.text == text => (doc.root.clone(), doc.servers.clone()),
+            _ => return Ok(None),
+        }
+    };
+    for id in servers {
+        let Ok(server) = find_server(&root, &id).await else {
+            continue;
+        };
+        let capable = server
+            .init_result()
+            .pointer("/capabilities/documentFormattingProvider")
+            .is_some_and(|p| p.as_bool().unwrap_or(p.is_object()));
+        if !capable {
+            continue;
+        }
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": "glass/format",
+            "method": "textDocument/formatting",
+            "params": { "textDocument": { "uri": uri }, "options": options.to_lsp() },
+        });
+        let limit = server.config.request_timeout("textDocument/formatting");
+        let response = server.request(request, limit).await?;
+        if let Some(error) = response.get("error") {
+            return Err(anyhow!("`{id}` answered with {error}"));
+        }
+        let edits = match response.get("result") {
+            Some(Value::Array(edits)) => edits.clone(),
+            _ => Vec::new(),
+        };
+        return Ok(Some((id, edits)));
+    }
+    Ok(None)
+}
+
+/// Run `formatter` on `text` and return its output.
+async fn run_external(
+    formatter: &FormatterConfig,
+    root: &Path,
+    path: &Path,
+    text: &str,
+    options: Options,
+) -> Result<String> {
+    let (program, _) = discovery::find_program(&formatter.command, root).ok_or_else(|| {
+        anyhow!(
+            "Formatter `{}` not found – install it or set `command` in format.json",
+            formatter.command
+        )
+    })?;
+    let file = path.to_string_lossy();
+    let args = formatter.args.iter().map(|arg| {
+        arg.replace("${file}", &file)
+            .replace("${tabSize}", &options.tab_size.to_string())
+            .replace("${insertSpaces}", &options.insert_spaces.to_string())
+    });
+    let mut child = Command::new(&program)
+        .args(args)
+        .envs(&formatter.env)
+        .current_dir(root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to run formatter `{}`", formatter.id))?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = text.to_string();
+    // Written concurrently so a formatter streaming its output cannot block
+    // on a full stdout pipe while we are still writing.
+    let writer = tokio::spawn(async move {
+        let _ = stdin.write_all(input.as_bytes()).await;
+    });
+    let output = timeout(FORMAT_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| {
+            anyhow!(
+                "Formatter `{}` timed out after {}s",
+                formatter.id,
+                FORMAT_TIMEOUT.as_secs()
+            )
+        })??;
+    let _ = writer.await;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(
+            "Formatter `{}` failed ({}): {}",
+            formatter.id,
+            output.status,
+            stderr.trim()
+        ));
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|_| anyhow!("Formatter `{}` returned invalid UTF-8", formatter.id))
+}
+
+/// Edits turning `old` into `new`: one `TextEdit` replacing the lines
+/// between the common leading and trailing lines.
+pub fn diff_edits(old: &str, new: &str) -> Vec<Value> {
+    if old == new {
+        return Vec::new();
+    }
+    let a: Vec<&str> = old.split_inclusive('\n').collect();
+    let b: Vec<&str> = new.split_inclusive('\n').collect();
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take(a.len().min(b.len()) - prefix)
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    let start: usize = a[..prefix].iter().map(|line| line.len()).sum();
+    let end = old.len() - a[a.len() - suffix..].iter().map(|l| l.len()).sum::<usize>();
+    let position = |offset| {
+        let (line, character) = position_at(old, offset);
+        json!({ "line": line, "character": character })
+    };
+    vec![json!({
+        "range": { "start": position(start), "end": position(end) },
+        "newText": b[prefix..b.len() - suffix].concat(),
+    })]
+}
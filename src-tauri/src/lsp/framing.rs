@@ -0,0 +1,404 @@
+//! LSP base-protocol framing.
+//!
+//! Every message is a header block (`Content-Length`, optional
+//! `Content-Type`) terminated by an empty line, followed by a UTF-8 JSON body.
+//! [`LspCodec`] decodes frames incrementally from a byte buffer, so partial
+//! reads simply wait for more input. Malformed input yields a typed
+//! [`FramingError`] and the codec resynchronises on the next
+//! `Content-Length` header – a single bad frame never takes the server down.
+//!
+//! The Debug Adapter Protocol uses the same base protocol, so debug adapters
+//! (see [`crate::dap`]) are read and written with this module too.
+
+use bytes::{Buf, BytesMut};
+use serde_json::Value;
+use std::fmt;
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Header blocks larger than this are treated as garbage.
+const MAX_HEADER_BYTES: usize = 8 * 1024;
+/// Upper bound for a single message body.
+const MAX_CONTENT_LENGTH: usize = 256 * 1024 * 1024;
+const HEADER_SLOTS: usize = 8;
+const RESYNC_MARKER: &[u8] = b"Content-Length";
+
+/// Errors reading a frame. Only [`FramingError::is_fatal`] errors end the
+/// stream; the others describe one skipped frame.
+#[derive(Debug)]
+pub enum FramingError {
+    /// The stream ended.
+    Closed,
+    Io(io::Error),
+    /// Header block that is not a list of `Name: value` lines.
+    InvalidHeader(String),
+    MissingContentLength,
+    InvalidContentLength(String),
+    /// `Content-Type` with a charset other than UTF-8.
+    UnsupportedCharset(String),
+    /// Body is not valid JSON.
+    InvalidJson(serde_json::Error),
+}
+
+impl FramingError {
+    /// Whether the stream is unusable after this error.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, FramingError::Closed | FramingError::Io(_))
+    }
+}
+
+impl fmt::Display for FramingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FramingError::Closed => write!(f, "stream closed"),
+            FramingError::Io(err) => write!(f, "I/O error: {err}"),
+            FramingError::InvalidHeader(msg) => write!(f, "invalid header: {msg}"),
+            FramingError::MissingContentLength => write!(f, "missing Content-Length header"),
+            FramingError::InvalidContentLength(value) => {
+                write!(f, "invalid Content-Length: {value}")
+            }
+            FramingError::UnsupportedCharset(charset) => {
+                write!(f, "unsupported charset: {charset}")
+            }
+            FramingError::InvalidJson(err) => write!(f, "invalid JSON body: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for FramingError {}
+
+impl From<io::Error> for FramingError {
+    fn from(err: io::Error) -> Self {
+        FramingError::Io(err)
+    }
+}
+
+/// Body expected after a parsed header block.
+struct PendingBody {
+    len: usize,
+    /// Reported once the body has been skipped.
+    error: Option<FramingError>,
+}
+
+/// Incremental decoder for the LSP base protocol.
+#[derive(Default)]
+pub struct LspCodec {
+    body: Option<PendingBody>,
+}
+
+impl LspCodec {
+    /// Decode the next message from `src`, consuming its bytes. Returns
+    /// `Ok(None)` if `src` does not hold a complete frame yet.
+    pub fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Value>, FramingError> {
+        if self.body.is_none() {
+            match self.decode_headers(src) {
+                Ok(Some(body)) => self.body = Some(body),
+                Ok(None) => return Ok(None),
+                Err(err) => {
+                    resync(src);
+                    return Err(err);
+                }
+            }
+        }
+
+        let len = self.body.as_ref().map_or(0, |b| b.len);
+        if src.len() < len {
+            src.reserve(len - src.len());
+            return Ok(None);
+        }
+        let body = src.split_to(len);
+        match self.body.take().and_then(|b| b.error) {
+            Some(err) => Err(err),
+            None => serde_json::from_slice(&body)
+                .map(Some)
+                .map_err(FramingError::InvalidJson),
+        }
+    }
+
+    /// Parse and consume a complete header block.
+    fn decode_headers(&mut self, src: &mut BytesMut) -> Result<Option<PendingBody>, FramingError> {
+        let mut slots = [httparse::EMPTY_HEADER; HEADER_SLOTS];
+        let (header_len, headers) = match httparse::parse_headers(src, &mut slots) {
+            Ok(httparse::Status::Complete(parsed)) => parsed,
+            Ok(httparse::Status::Partial) if src.len() > MAX_HEADER_BYTES => {
+                return Err(FramingError::InvalidHeader(format!(
+                    "no end of headers within {MAX_HEADER_BYTES} bytes"
+                )));
+            }
+            Ok(httparse::Status::Partial) => return Ok(None),
+            Err(err) => return Err(FramingError::InvalidHeader(err.to_string())),
+        };
+
+        let mut len = None;
+        let mut error = None;
+        for header in headers {
+            let value = String::from_utf8_lossy(header.value);
+            if header.name.eq_ignore_ascii_case("Content-Length") {
+                len = Some(
+                    value
+                        .trim()
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|len| *len <= MAX_CONTENT_LENGTH)
+                        .ok_or_else(|| FramingError::InvalidContentLength(value.to_string()))?,
+                );
+            } else if header.name.eq_ignore_ascii_case("Content-Type") {
+                if let Some(charset) = charset(&value) {
+                    // `utf8` is accepted for backwards compatibility (spec).
+                    if !matches!(charset.to_ascii_lowercase().as_str(), "utf-8" | "utf8") {
+                        error = Some(FramingError::UnsupportedCharset(charset.to_string()));
+                    }
+                }
+            }
+        }
+        let len = len.ok_or(FramingError::MissingContentLength)?;
+        src.advance(header_len);
+        Ok(Some(PendingBody { len, error }))
+    }
+}
+
+/// `charset` parameter of a `Content-Type` value.
+fn charset(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// Drop bytes up to the next `Content-Length` header after the current
+/// position. Keeps a possible partial marker at the end of the buffer.
+fn resync(src: &mut BytesMut) {
+    let next = src
+        .windows(RESYNC_MARKER.len())
+        .skip(1)
+        .position(|w| w.eq_ignore_ascii_case(RESYNC_MARKER))
+        .map(|pos| pos + 1);
+    let skip = next.unwrap_or_else(|| {
+        src.len()
+            .saturating_sub(RESYNC_MARKER.len() - 1)
+            .max(1)
+            .min(src.len())
+    });
+    src.advance(skip);
+}
+
+/// Encode `msg` as a complete frame.
+pub fn encode(msg: &Value) -> Vec<u8> {
+    let body = msg.to_string();
+    let mut frame = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+    frame.extend_from_slice(body.as_bytes());
+    frame
+}
+
+/// Write `msg` as one frame and flush.
+pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, msg: &Value) -> io::Result<()> {
+    writer.write_all(&encode(msg)).await?;
+    writer.flush().await
+}
+
+/// Reads frames from a byte stream.
+pub struct FrameReader<R> {
+    reader: R,
+    buf: BytesMut,
+    codec: LspCodec,
+}
+
+impl<R: AsyncRead + Unpin> FrameReader<R> {
+    pub fn new(reader: R) -> Self {
+        FrameReader {
+            reader,
+            buf: BytesMut::with_capacity(8 * 1024),
+            codec: LspCodec::default(),
+        }
+    }
+
+    /// Next message, or the error for the frame that could not be decoded.
+    pub async fn next(&mut self) -> Result<Value, FramingError> {
+        loop {
+            if let Some(msg) = self.codec.decode(&mut self.buf)? {
+                return Ok(msg);
+            }
+            if self.reader.read_buf(&mut self.buf).await? == 0 {
+                return Err(FramingError::Closed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CASES: usize = 500;
+
+    /// Decode everything in `input`, fed in chunks of random size.
+    fn decode_chunked(rng: &mut fastrand::Rng, input: &[u8]) -> Vec<Result<Value, String>> {
+        let mut codec = LspCodec::default();
+        let mut buf = BytesMut::new();
+        let mut out = Vec::new();
+        let mut rest = input;
+        loop {
+            loop {
+                let before = buf.len();
+                match codec.decode(&mut buf) {
+                    Ok(Some(msg)) => out.push(Ok(msg)),
+                    Ok(None) => break,
+                    Err(err) => {
+                        assert!(!err.is_fatal());
+                        assert!(buf.len() < before, "error without progress");
+                        out.push(Err(err.to_string()));
+                    }
+                }
+            }
+            if rest.is_empty() {
+                return out;
+            }
+            let n = rng.usize(1..=rest.len().min(64));
+            buf.extend_from_slice(&rest[..n]);
+            rest = &rest[n..];
+        }
+    }
+
+    fn random_string(rng: &mut fastrand::Rng) -> String {
+        let len = rng.usize(0..12);
+        (0..len)
+            .map(|_| match rng.u8(0..4) {
+                0 => rng.alphanumeric(),
+                1 => ['é', '😀', '\n', '"', '\\', '\r'][rng.usize(0..6)],
+                _ => rng.lowercase(),
+            })
+            .collect()
+    }
+
+    fn random_value(rng: &mut fastrand::Rng, depth: u32) -> Value {
+        match rng.u8(0..if depth == 0 { 4 } else { 6 }) {
+            0 => Value::Null,
+            1 => json!(rng.bool()),
+            2 => json!(rng.i64(..)),
+            3 => json!(random_string(rng)),
+            4 => Value::Array(
+                (0..rng.usize(0..4))
+                    .map(|_| random_value(rng, depth - 1))
+                    .collect(),
+            ),
+            _ => Value::Object(
+                (0..rng.usize(0..4))
+                    .map(|_| (random_string(rng), random_value(rng, depth - 1)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Bytes that can never spell a `Content-Length` header.
+    fn garbage(rng: &mut fastrand::Rng) -> Vec<u8> {
+        const ALPHABET: &[u8] = b"abxyz:019 \t\r\n{}\"\xff\x00-";
+        (0..rng.usize(1..200))
+            .map(|_| ALPHABET[rng.usize(0..ALPHABET.len())])
+            .collect()
+    }
+
+    #[test]
+    fn roundtrip_in_random_chunks() {
+        let mut rng = fastrand::Rng::with_seed(0x15b);
+        for _ in 0..CASES {
+            let messages: Vec<Value> = (0..rng.usize(1..6))
+                .map(|_| random_value(&mut rng, 3))
+                .collect();
+            let input: Vec<u8> = messages.iter().flat_map(encode).collect();
+            let decoded = decode_chunked(&mut rng, &input);
+            let expected: Vec<Result<Value, String>> = messages.into_iter().map(Ok).collect();
+            assert_eq!(decoded, expected);
+        }
+    }
+
+    #[test]
+    fn recovers_after_garbage() {
+        let mut rng = fastrand::Rng::with_seed(0x6a7);
+        for _ in 0..CASES {
+            let msg = random_value(&mut rng, 2);
+            let mut input = garbage(&mut rng);
+            input.extend(encode(&msg));
+            let decoded = decode_chunked(&mut rng, &input);
+            assert_eq!(decoded.last(), Some(&Ok(msg)), "input: {input:?}");
+        }
+    }
+
+    #[test]
+    fn never_panics_on_mutated_frames() {
+        let mut rng = fastrand::Rng::with_seed(0x3c1);
+        for _ in 0..CASES {
+            let mut input: Vec<u8> = (0..3)
+                .flat_map(|_| encode(&random_value(&mut rng, 2)))
+                .collect();
+            for _ in 0..rng.usize(1..8) {
+                let i = rng.usize(0..input.len());
+                match rng.u8(0..3) {
+                    0 => input[i] = rng.u8(..),
+                    1 => {
+                        input.remove(i);
+                    }
+                    _ => input.insert(i, rng.u8(..)),
+                }
+            }
+            decode_chunked(&mut rng, &input);
+        }
+    }
+
+    #[test]
+    fn content_type_charset() {
+        let body = br#"{"a":1}"#;
+        let frame = |content_type: &str| {
+            let mut frame = format!(
+                "Content-Length: {}\r\nContent-Type: {content_type}\r\n\r\n",
+                body.len()
+            )
+            .into_bytes();
+            frame.extend_from_slice(body);
+            frame
+        };
+        let mut rng = fastrand::Rng::with_seed(1);
+        for ok in [
+            "application/vscode-jsonrpc; charset=utf-8",
+            "application/vscode-jsonrpc; charset=utf8",
+            "application/vscode-jsonrpc",
+        ] {
+            assert_eq!(decode_chunked(&mut rng, &frame(ok)), [Ok(json!({"a": 1}))]);
+        }
+
+        let mut input = frame("application/vscode-jsonrpc; charset=latin1");
+        input.extend(encode(&json!(2)));
+        let decoded = decode_chunked(&mut rng, &input);
+        assert_eq!(decoded[0], Err("unsupported charset: latin1".to_string()));
+        assert_eq!(decoded[1], Ok(json!(2)));
+    }
+
+    #[test]
+    fn malformed_frames_are_skipped() {
+        let mut rng = fastrand::Rng::with_seed(2);
+        let cases: [&[u8]; 4] = [
+            b"Content-Length: 3\r\n\r\n{x}",
+            b"Content-Length: nope\r\n\r\n",
+            b"Content-Length: 99999999999999\r\n\r\n",
+            b"Content-Type: text/plain\r\n\r\n",
+        ];
+        for case in cases {
+            let mut input = case.to_vec();
+            input.extend(encode(&json!({"ok": true})));
+            let decoded = decode_chunked(&mut rng, &input);
+            assert!(decoded[0].is_err(), "{}", String::from_utf8_lossy(case));
+            assert_eq!(decoded.last(), Some(&Ok(json!({"ok": true}))));
+        }
+    }
+
+    #[tokio::test]
+    async fn reader_reports_closed_stream() {
+        let mut input = encode(&json!(1));
+        input.extend_from_slice(b"Content-Length: 10\r\n\r\n{");
+        let mut reader = FrameReader::new(&input[..]);
+        assert_eq!(reader.next().await.unwrap(), json!(1));
+        assert!(matches!(reader.next().await, Err(FramingError::Closed)));
+    }
+}
@@ -1,27 +1,105 @@
 //! Language-server bridge for Glass-IDE (MVP)
 //!
-//! Spawns / reuses one language-server process per workspace root.
-//! Currently supports TypeScript (`typescript-language-server`) and Rust (`rust-analyzer`).
-//! It exposes a single Tauri command `invoke_lsp` that forwards a JSON-RPC request
-//! to the appropriate language-server and awaits the matching response.
+//! Spawns / reuses language-server processes per workspace root – one per
+//! configured server, so a root can run e.g. rust-analyzer and the TypeScript
+//! server side by side. For multi-root workspaces requests are routed to the
+//! root containing the document, then to a server by document language (see
+//! [`registry`]).
+//! It exposes a Tauri command `invoke_lsp` that forwards a JSON-RPC request
+//! to the appropriate language-server and awaits the matching response. The
+//! `initialize` handshake is performed by the backend when a server starts;
+//! the resulting capabilities are available via `lsp_capabilities`.
 //!
-//! Limitations (to be improved incrementally):
-//! • Only handles **request → response** flow (no server-initiated notifications yet).
-//! • No restart / crash recovery.
-//! • Very naive Content-Length framing.
+//! Each server process has a dedicated reader task that routes responses by id to the
+//! waiting request (oneshot channel), so any number of requests can be in
+//! flight at once – a slow hover no longer blocks completion. Timeouts are
+//! per method and configurable per server (see
+//! [`registry::ServerConfig::request_timeout`]); abandoned or timed-out
+//! requests are cancelled on the server with `$/cancelRequest`
+//! (`cancel_lsp_request`).
+//!
+//! Server-initiated traffic is forwarded as events tagged with `root` and
+//! `server`: notifications as `lsp_notification` (plus `lsp_diagnostics`),
+//! and requests the backend cannot answer itself as `lsp_request`, to be
+//! answered via `respond_lsp_request`. Work-done progress is emitted as typed
+//! `lsp_progress` events; `lsp_status` returns a status snapshot of every
+//! running server for the status bar.
+//!
+//! Published diagnostics are also kept per server (see [`diagnostics`]):
+//! `lsp_problems` queries them workspace-wide, filtered by severity, path
+//! glob and source, and `lsp_problem_counts` sums them per file.
+//!
+//! When no server can be started (binary missing, crashed…) `workspace/symbol`,
+//! `textDocument/documentSymbol` and `textDocument/definition` are answered from
+//! the built-in symbol index instead (see [`fallback`]).
+//!
+//! Open documents are owned by the backend: the editor reports buffer changes
+//! via `lsp_open_document` / `lsp_change_document` / `lsp_save_document` /
+//! `lsp_close_document` and the backend keeps every server handling the file
+//! in sync, honouring each server's `textDocumentSync` kind. Documents a
+//! workspace edit renames or deletes follow it (`lsp_document_moved`).
+//!
+//! Server binaries are looked up in the workspace, `PATH` and toolchain
+//! directories (see [`discovery`]); `lsp_health` reports which configured
+//! servers are installed and which workspace languages lack one.
+//!
+//! Crashed servers are restarted with backoff and get their handshake and
+//! open documents replayed (see [`server`]); state changes are emitted as
+//! `lsp_server_state`. Servers are shut down gracefully on `stop_lsp`, when
+//! their root leaves the workspace and on app exit. Server stderr goes to the
+//! app log and can be fetched with `lsp_server_output`.
+//!
+//! `lsp_format_document` formats a document with its language server or,
+//! failing that, an external formatter such as rustfmt, prettier or black,
+//! honouring per-language settings and `.editorconfig` (see [`formatting`]).
+//!
+//! `lsp_semantic_tokens` returns one token stream per document: the server's
+//! semantic tokens overlaid on a tree-sitter highlight fallback, cached and
+//! sent as deltas (see [`semantic_tokens`]).
+//!
+//! `WorkspaceEdit`s (from `workspace/applyEdit` or `apply_workspace_edit`)
+//! are applied by the backend as one undoable operation (see
+//! [`workspace_edit`]).
+//!
+//! Messages are framed by [`framing`], which skips malformed frames instead
+//! of dropping the server.
+//!
+//! For debugging, all traffic can be recorded to a file (`lsp_set_recording`,
+//! viewed with `lsp_recording`, see [`recorder`]); a recording can stand in
+//! for a real server (see [`replay`]).
 
-use anyhow::{anyhow, Context, Result};
+mod diagnostics;
+mod discovery;
+mod documents;
+mod fallback;
+mod formatting;
+pub mod framing;
+mod progress;
+mod recorder;
+pub mod registry;
+mod replay;
+mod semantic_tokens;
+mod server;
+#[cfg(test)]
+mod tests;
+pub mod workspace_edit;
+
+use anyhow::{anyhow, Result};
+use log::warn;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::process::Stdio;
-use tauri::Emitter;
-use tauri::{command, AppHandle};
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{ChildStdin, ChildStdout, Command};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{command, AppHandle, Emitter, Runtime};
 use tokio::sync::Mutex;
-use tokio::time::{timeout, Duration};
+use tower_lsp::lsp_types::Url;
+
+use self::registry::ServerConfig;
+use self::server::{LspServer, ServerState, SyncKind, OUTPUT_LINES};
+use crate::commands::workspace;
 
 // ----------------------------------------------------------------------------
 // Types
@@ -30,10 +108,17 @@ use tokio::time::{timeout, Duration};
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LspInvokeRequest {
-    /// Absolute workspace root – used to map to server instance
+    /// Absolute workspace root or `.glass-workspace` file – used to map to server instance
     root: String,
     /// Raw JSON-RPC request (object)
     request: Value,
+    /// LSP language id used to pick the server when the request carries no
+    /// `textDocument` (e.g. `workspace/symbol`). Optional.
+    #[serde(default)]
+    language: Option<String>,
+    /// Overrides the server's timeout for this request (milliseconds).
+    #[serde(default)]
+    timeout_ms: Option<u64>,
 }
 
 #[derive(Serialize)]
@@ -46,106 +131,119 @@ pub struct LspInvokeResponse {
 // Server management
 // ----------------------------------------------------------------------------
 
-struct LspProcess {
-    stdin: ChildStdin,
-    stdout: BufReader<ChildStdout>,
-}
+/// Supervised servers keyed by (root, server id).
+type ServerKey = (String, String);
 
-static SERVERS: Lazy<Mutex<HashMap<String, LspProcess>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static SERVERS: Lazy<Mutex<HashMap<ServerKey, Arc<LspServer>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
 
-/// Return mutable reference to LspProcess for `root`, spawning if needed.
-async fn ensure_server(root: &str) -> Result<()> {
-    let mut map = SERVERS.lock().await;
-    if map.contains_key(root) {
-        return Ok(());
-    }
+/// Return the server running `config` for `root`, spawning and initializing
+/// it if needed. The map lock is only held while looking up / spawning, never
+/// across the handshake or a request – concurrent callers wait in
+/// [`LspServer::connection`] instead.
+async fn ensure_server<R: Runtime>(
+    app: &AppHandle<R>,
+    root: &str,
+    config: &ServerConfig,
+) -> Result<Arc<LspServer>> {
+    let key = (root.to_string(), config.id.clone());
+    let server = {
+        let mut map = SERVERS.lock().await;
+        if let Some(server) = map.get(&key) {
+            return Ok(server.clone());
+        }
+        let server = LspServer::spawn_new(app, root, config)?;
+        map.insert(key, server.clone());
+        server
+    };
+    server.initialize(app).await?;
+    Ok(server)
+}
 
-    let root_path = root;
-    if !std::path::Path::new(root_path).exists() {
-        return Err(anyhow!("Workspace root does not exist"));
+/// Remove the servers matching `filter` from [`SERVERS`] and stop them
+/// concurrently.
+async fn stop_servers<R: Runtime>(app: &AppHandle<R>, filter: impl Fn(&ServerKey) -> bool) {
+    let stopped: Vec<Arc<LspServer>> = {
+        let mut map = SERVERS.lock().await;
+        let keys: Vec<ServerKey> = map.keys().filter(|k| filter(k)).cloned().collect();
+        keys.iter().filter_map(|k| map.remove(k)).collect()
+    };
+    let tasks: Vec<_> = stopped
+        .into_iter()
+        .map(|server| {
+            let app = app.clone();
+            tokio::spawn(async move { server.stop(&app).await })
+        })
+        .collect();
+    for task in tasks {
+        let _ = task.await;
     }
+}
 
-    // Pick language server binary heuristically: look for Cargo.toml vs. tsconfig.json
-    let is_rust = std::path::Path::new(root_path).join("Cargo.toml").exists();
-    let (cmd, args): (&str, &[&str]) = if is_rust {
-        ("rust-analyzer", &[])
-    } else {
-        // default to TypeScript server
-        ("typescript-language-server", &["--stdio"])
-    };
+/// Stop every server – called on app exit.
+pub async fn shutdown_all<R: Runtime>(app: &AppHandle<R>) {
+    stop_servers(app, |_| true).await;
+}
 
-    let mut child = Command::new(cmd)
-        .args(args)
-        .current_dir(root_path)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null())
-        .spawn()
-        .with_context(|| format!("Failed to spawn language server `{cmd}`"))?;
-
-    let stdin = child
-        .stdin
-        .take()
-        .ok_or_else(|| anyhow!("Failed to open stdin for language server"))?;
-    let stdout = child
-        .stdout
-        .take()
-        .ok_or_else(|| anyhow!("Failed to open stdout for language server"))?;
-
-    let root_owned = root.to_string();
-    map.insert(
-        root_owned.clone(),
-        LspProcess {
-            stdin,
-            stdout: BufReader::new(stdout),
-        },
-    );
-    tokio::spawn(async move {
-        // Wait for child to finish – then drop from map.
-        let _ = child.wait().await;
-        let mut map = SERVERS.lock().await;
-        map.remove(&root_owned);
-    });
-    Ok(())
+/// Stop the servers of roots that are not part of `roots` any more – called
+/// when the workspace changes.
+pub async fn retain_roots<R: Runtime>(app: &AppHandle<R>, roots: &[workspace::WorkspaceRoot]) {
+    let keep: Vec<String> = roots
+        .iter()
+        .map(|r| r.path.to_string_lossy().into_owned())
+        .collect();
+    stop_servers(app, |(root, _)| !keep.contains(root)).await;
+}
+
+async fn find_server(root: &str, server: &str) -> Result<Arc<LspServer>> {
+    SERVERS
+        .lock()
+        .await
+        .get(&(root.to_string(), server.to_string()))
+        .cloned()
+        .ok_or_else(|| anyhow!("No `{server}` server running for {root}"))
 }
 
 // ----------------------------------------------------------------------------
 // Helper functions
 // ----------------------------------------------------------------------------
 
-/// Write JSON-RPC frame with proper Content-Length header.
-async fn write_rpc(stdin: &mut ChildStdin, msg: &Value) -> Result<()> {
-    let payload = serde_json::to_vec(msg)?;
-    let header = format!("Content-Length: {}\r\n\r\n", payload.len());
-    stdin.write_all(header.as_bytes()).await?;
-    stdin.write_all(&payload).await?;
-    stdin.flush().await?;
-    Ok(())
+/// Local path of a `file://` URI.
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    Url::parse(uri).ok()?.to_file_path().ok()
 }
 
-/// Read next JSON-RPC object from stdout (blocking until one arrives).
-async fn read_rpc(stdout: &mut BufReader<ChildStdout>) -> Result<Value> {
-    // Read headers until blank line
-    let mut content_length: Option<usize> = None;
-    loop {
-        let mut header = String::new();
-        let n = stdout.read_line(&mut header).await?;
-        if n == 0 {
-            return Err(anyhow!("LSP stdout closed"));
-        }
-        let h = header.trim();
-        if h.is_empty() {
-            break; // end of headers
-        }
-        if let Some(rem) = h.strip_prefix("Content-Length:") {
-            content_length = Some(rem.trim().parse::<usize>()?);
-        }
-    }
-    let len = content_length.ok_or_else(|| anyhow!("Missing Content-Length"))?;
-    let mut buf = vec![0u8; len];
-    stdout.read_exact(&mut buf).await?;
-    let val: Value = serde_json::from_slice(&buf)?;
-    Ok(val)
+/// Local path of the request's `textDocument.uri`, if any.
+fn document_path(request: &Value) -> Option<PathBuf> {
+    request
+        .pointer("/params/textDocument/uri")
+        .and_then(Value::as_str)
+        .and_then(uri_to_path)
+}
+
+/// Resolve the folder whose server should handle a request. For multi-root
+/// workspaces this is the root containing `document`, falling back to the
+/// first root.
+fn server_root(root: &str, document: Option<&Path>) -> Result<String> {
+    let roots = workspace::resolve_roots(Path::new(root))?;
+    let chosen = document
+        .and_then(|p| workspace::root_for_path(&roots, p))
+        .or_else(|| roots.first())
+        .ok_or_else(|| anyhow!("Workspace root does not exist"))?;
+    Ok(chosen.path.to_string_lossy().into_owned())
+}
+
+/// Route to the root and server for `document` / `language` (see
+/// [`registry::resolve`]), starting the server if needed.
+async fn route<R: Runtime>(
+    app: &AppHandle<R>,
+    root: &str,
+    document: Option<&Path>,
+    language: Option<&str>,
+) -> Result<Arc<LspServer>> {
+    let root = server_root(root, document)?;
+    let config = registry::resolve(Path::new(&root), document, language)?;
+    ensure_server(app, &root, &config).await
 }
 
 // ----------------------------------------------------------------------------
@@ -153,51 +251,819 @@ async fn read_rpc(stdout: &mut BufReader<ChildStdout>) -> Result<Value> {
 // ----------------------------------------------------------------------------
 
 #[command]
-pub async fn invoke_lsp(
-    app: AppHandle,
+pub async fn invoke_lsp<R: Runtime>(
+    app: AppHandle<R>,
     payload: LspInvokeRequest,
 ) -> tauri::Result<LspInvokeResponse> {
-    let root = payload.root.clone();
-    ensure_server(&root).await.map_err(tauri::Error::Anyhow)?;
+    let document = document_path(&payload.request);
+    let root = server_root(&payload.root, document.as_deref()).map_err(tauri::Error::Anyhow)?;
+    let method = payload
+        .request
+        .get("method")
+        .and_then(Value::as_str)
+        .unwrap_or("");
+    // Explicit language first, then `textDocument.languageId` (didOpen).
+    let language = payload.language.as_deref().or_else(|| {
+        payload
+            .request
+            .pointer("/params/textDocument/languageId")
+            .and_then(Value::as_str)
+    });
+
+    let result = match route(&app, &root, document.as_deref(), language).await {
+        Ok(server) => {
+            let limit = payload
+                .timeout_ms
+                .map(Duration::from_millis)
+                .unwrap_or_else(|| server.config.request_timeout(method));
+            server.request(payload.request.clone(), limit).await
+        }
+        Err(err) => Err(err),
+    };
+    match result {
+        Ok(response) => Ok(LspInvokeResponse { response }),
+        Err(err) if fallback::handles(method) => {
+            warn!("[LSP] {err:#} – answering `{method}` from the symbol index");
+            let response =
+                fallback::respond(Path::new(&payload.root), Path::new(&root), &payload.request)
+                    .map_err(tauri::Error::Anyhow)?;
+            Ok(LspInvokeResponse { response })
+        }
+        Err(err) => Err(tauri::Error::Anyhow(err)),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspCancelRequest {
+    /// `root` the request was sent with
+    root: String,
+    /// JSON-RPC `id` the request was sent with
+    id: Value,
+}
+
+#[command]
+/// Abandon a pending `invoke_lsp` call: the server receives `$/cancelRequest`
+/// and the call resolves with a `RequestCancelled` (-32800) error response.
+/// Returns `false` if no such request is in flight.
+pub async fn cancel_lsp_request(payload: LspCancelRequest) -> tauri::Result<bool> {
+    let roots: Vec<String> = workspace::resolve_roots(Path::new(&payload.root))
+        .map_err(tauri::Error::Anyhow)?
+        .iter()
+        .map(|r| r.path.to_string_lossy().into_owned())
+        .collect();
+    let servers: Vec<Arc<LspServer>> = SERVERS
+        .lock()
+        .await
+        .iter()
+        .filter(|((root, _), _)| roots.contains(root))
+        .map(|(_, server)| server.clone())
+        .collect();
+
+    let mut cancelled = false;
+    for server in servers {
+        cancelled |= server.cancel(&payload.id).await;
+    }
+    Ok(cancelled)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspRequestResponse {
+    /// `root` of the `lsp_request` event being answered
+    root: String,
+    /// `server` of the `lsp_request` event being answered
+    server: String,
+    /// `id` of the `lsp_request` event being answered
+    id: Value,
+    #[serde(default)]
+    result: Value,
+    /// JSON-RPC error object (`{ code, message }`); takes precedence over `result`
+    error: Option<Value>,
+}
+
+#[command]
+/// Answer a server → client request previously emitted as `lsp_request`.
+pub async fn respond_lsp_request(payload: LspRequestResponse) -> tauri::Result<()> {
+    let server = find_server(&payload.root, &payload.server)
+        .await
+        .map_err(tauri::Error::Anyhow)?;
+    let response = match payload.error {
+        Some(error) => json!({ "jsonrpc": "2.0", "id": payload.id, "error": error }),
+        None => json!({ "jsonrpc": "2.0", "id": payload.id, "result": payload.result }),
+    };
+    let conn = server.connection().await.map_err(tauri::Error::Anyhow)?;
+    conn.write(&response).await.map_err(tauri::Error::Anyhow)
+}
 
-    // Acquire map again to get mutable refs
-    let mut map = SERVERS.lock().await;
-    let proc = map
-        .get_mut(&root)
-        .ok_or_else(|| tauri::Error::Anyhow(anyhow!("Server disappeared")))?;
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspServerRef {
+    /// `root` as reported in `lsp_server_state` events
+    root: String,
+    /// `server` id as reported in `lsp_server_state` events
+    server: String,
+}
 
-    // Send request
-    write_rpc(&mut proc.stdin, &payload.request)
+#[command]
+/// Restart a server by hand – also revives servers that hit the crash-loop cap.
+pub async fn restart_lsp<R: Runtime>(
+    app: AppHandle<R>,
+    payload: LspServerRef,
+) -> tauri::Result<()> {
+    let server = find_server(&payload.root, &payload.server)
         .await
         .map_err(tauri::Error::Anyhow)?;
+    server.restart(&app).await.map_err(tauri::Error::Anyhow)
+}
 
-    let request_id = payload.request.get("id").cloned();
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspStopRequest {
+    /// Workspace root or `.glass-workspace` file
+    root: String,
+    /// Server id to stop; all servers of the workspace if omitted
+    server: Option<String>,
+}
 
-    // Read messages until we find matching response; forward diagnostics events on the fly.
-    let resp = loop {
-        let msg = timeout(Duration::from_secs(5), read_rpc(&mut proc.stdout))
-            .await
-            .map_err(|_| tauri::Error::Anyhow(anyhow!("LSP timeout")))?
-            .map_err(tauri::Error::Anyhow)?;
+#[command]
+/// Gracefully shut down language servers (`shutdown` / `exit`, then kill).
+/// Stopped servers are started again on the next request that needs them.
+pub async fn stop_lsp<R: Runtime>(app: AppHandle<R>, payload: LspStopRequest) -> tauri::Result<()> {
+    let mut roots: Vec<String> = workspace::resolve_roots(Path::new(&payload.root))
+        .map_err(tauri::Error::Anyhow)?
+        .iter()
+        .map(|r| r.path.to_string_lossy().into_owned())
+        .collect();
+    // Also accept a server root exactly as reported in events.
+    roots.push(payload.root.clone());
+    stop_servers(&app, |(root, id)| {
+        roots.contains(root) && payload.server.as_ref().map_or(true, |s| s == id)
+    })
+    .await;
+    Ok(())
+}
 
-        // Publish diagnostics notification
-        if msg.get("method") == Some(&Value::String("textDocument/publishDiagnostics".into())) {
-            if let Some(params) = msg.get("params") {
-                let _ = app.emit("lsp_diagnostics", params.clone());
-            }
-            continue; // keep waiting
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspOutputRequest {
+    /// `root` as reported in `lsp_server_state` events
+    root: String,
+    /// `server` id as reported in `lsp_server_state` events
+    server: String,
+    /// Number of most recent lines (default: everything buffered)
+    lines: Option<usize>,
+}
+
+#[command]
+/// Recent stderr output of a server, oldest line first – kept across restarts.
+pub async fn lsp_server_output(payload: LspOutputRequest) -> tauri::Result<Vec<String>> {
+    let server = find_server(&payload.root, &payload.server)
+        .await
+        .map_err(tauri::Error::Anyhow)?;
+    Ok(server.output(payload.lines.unwrap_or(OUTPUT_LINES)))
+}
+
+/// Roots whose servers a `root` filter selects: the workspace's roots plus
+/// the given path itself; `None` selects every server.
+fn root_filter(root: Option<&str>) -> tauri::Result<Option<Vec<String>>> {
+    let Some(root) = root else {
+        return Ok(None);
+    };
+    let mut roots: Vec<String> = workspace::resolve_roots(Path::new(root))
+        .map_err(tauri::Error::Anyhow)?
+        .iter()
+        .map(|r| r.path.to_string_lossy().into_owned())
+        .collect();
+    roots.push(root.to_string());
+    Ok(Some(roots))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspStatusRequest {
+    /// Only servers of this workspace root or `.glass-workspace` file
+    #[serde(default)]
+    root: Option<String>,
+}
+
+#[command]
+/// Status of the running servers – state, reported health and progress tasks
+/// – ordered by root and server id.
+pub async fn lsp_status(payload: LspStatusRequest) -> tauri::Result<Vec<server::ServerStatus>> {
+    let roots = root_filter(payload.root.as_deref())?;
+    let mut status: Vec<server::ServerStatus> = SERVERS
+        .lock()
+        .await
+        .iter()
+        .filter(|((root, _), _)| roots.as_ref().map_or(true, |roots| roots.contains(root)))
+        .map(|(_, server)| server.status())
+        .collect();
+    status.sort_by(|a, b| (&a.root, &a.server).cmp(&(&b.root, &b.server)));
+    Ok(status)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspProblemsRequest {
+    /// Only servers of this workspace root or `.glass-workspace` file
+    #[serde(default)]
+    root: Option<String>,
+    /// Least severe `DiagnosticSeverity` to include (1 = errors only)
+    #[serde(default)]
+    severity: Option<u64>,
+    /// Glob over root-relative paths, e.g. `src/**/*.rs`
+    #[serde(default)]
+    path: Option<String>,
+    /// Server id or diagnostic `source`
+    #[serde(default)]
+    source: Option<String>,
+    /// Return at most this many problems
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+/// Run `f` over the diagnostics stores of the servers `payload` selects.
+async fn with_diagnostics<T>(
+    payload: &LspProblemsRequest,
+    f: impl FnOnce(&[(&str, &str, &diagnostics::Diagnostics)], &diagnostics::Filter) -> T,
+) -> tauri::Result<T> {
+    let roots = root_filter(payload.root.as_deref())?;
+    let filter = diagnostics::Filter::new(
+        payload.severity,
+        payload.path.as_deref(),
+        payload.source.clone(),
+    )
+    .map_err(tauri::Error::Anyhow)?;
+    let servers = SERVERS.lock().await;
+    let guards: Vec<_> = servers
+        .iter()
+        .filter(|((root, _), _)| roots.as_ref().map_or(true, |roots| roots.contains(root)))
+        .map(|(_, server)| (server, server.diagnostics()))
+        .collect();
+    let stores: Vec<_> = guards
+        .iter()
+        .map(|(server, store)| (server.root.as_str(), server.config.id.as_str(), &**store))
+        .collect();
+    Ok(f(&stores, &filter))
+}
+
+#[command]
+/// Workspace-wide problems from every server's published diagnostics,
+/// ordered by path, severity and position.
+pub async fn lsp_problems(payload: LspProblemsRequest) -> tauri::Result<Vec<diagnostics::Problem>> {
+    let limit = payload.limit.unwrap_or(usize::MAX);
+    let mut problems = with_diagnostics(&payload, |stores, filter| {
+        diagnostics::problems(stores.iter().copied(), filter)
+    })
+    .await?;
+    problems.truncate(limit);
+    Ok(problems)
+}
+
+#[command]
+/// Problem counts per file by severity, for explorer decorations; takes the
+/// same filters as `lsp_problems` (`limit` is ignored).
+pub async fn lsp_problem_counts(
+    payload: LspProblemsRequest,
+) -> tauri::Result<Vec<diagnostics::FileCounts>> {
+    with_diagnostics(&payload, |stores, filter| {
+        diagnostics::counts(stores.iter().copied(), filter)
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspHealthRequest {
+    /// Workspace root or `.glass-workspace` file
+    root: String,
+}
+
+#[command]
+/// Probe the configured servers of every workspace root (binary lookup,
+/// version) and list the languages in each root that lack a working server.
+pub async fn lsp_health(payload: LspHealthRequest) -> tauri::Result<Vec<discovery::RootHealth>> {
+    let roots = workspace::resolve_roots(Path::new(&payload.root)).map_err(tauri::Error::Anyhow)?;
+    let states: HashMap<ServerKey, ServerState> = SERVERS
+        .lock()
+        .await
+        .iter()
+        .map(|(key, server)| (key.clone(), server.state()))
+        .collect();
+    let mut report = Vec::new();
+    for root in roots {
+        let key_root = root.path.to_string_lossy().into_owned();
+        let state = |id: &str| states.get(&(key_root.clone(), id.to_string())).copied();
+        report.push(discovery::check_root(&root.path, state).await);
+    }
+    Ok(report)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspCapabilitiesRequest {
+    /// Workspace root or `.glass-workspace` file
+    root: String,
+    /// Document whose server should be queried
+    uri: Option<String>,
+    /// LSP language id, used when no `uri` is given
+    language: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspCapabilities {
+    root: String,
+    server: String,
+    /// `ServerCapabilities` from the server's `InitializeResult`
+    capabilities: Value,
+    server_info: Option<Value>,
+}
+
+#[command]
+/// Capabilities of the server handling `uri` / `language` – starts and
+/// initializes the server if it is not running yet, so the frontend can call
+/// this before enabling features.
+pub async fn lsp_capabilities<R: Runtime>(
+    app: AppHandle<R>,
+    payload: LspCapabilitiesRequest,
+) -> tauri::Result<LspCapabilities> {
+    let document = payload.uri.as_deref().and_then(uri_to_path);
+    let server = route(
+        &app,
+        &payload.root,
+        document.as_deref(),
+        payload.language.as_deref(),
+    )
+    .await
+    .map_err(tauri::Error::Anyhow)?;
+    server.connection().await.map_err(tauri::Error::Anyhow)?;
+    let result = server.init_result();
+    Ok(LspCapabilities {
+        root: server.root.clone(),
+        server: server.config.id.clone(),
+        capabilities: result.get("capabilities").cloned().unwrap_or(Value::Null),
+        server_info: result.get("serverInfo").cloned(),
+    })
+}
+
+// ----------------------------------------------------------------------------
+// Tauri commands – document synchronisation
+// ----------------------------------------------------------------------------
+
+/// Serialises sync commands so notifications reach servers in editor order.
+static SYNC_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspOpenDocument {
+    /// Workspace root or `.glass-workspace` file
+    root: String,
+    /// `file://` URI of the document
+    uri: String,
+    language_id: String,
+    text: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspChangeDocument {
+    uri: String,
+    /// `TextDocumentContentChangeEvent`s (UTF-16 ranges), in order
+    changes: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspDocumentRef {
+    uri: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspDocumentState {
+    uri: String,
+    version: i64,
+    /// Ids of the servers the document is open on
+    servers: Vec<String>,
+}
+
+/// Send `method` for `uri` to every server in `servers`, building the params
+/// per server. Servers that are down are skipped with a warning – the
+/// tracked state is replayed when they come back.
+async fn notify_servers(
+    root: &str,
+    servers: &[String],
+    method: &str,
+    params: impl Fn(&LspServer) -> Option<Value>,
+) {
+    for id in servers {
+        let result = match find_server(root, id).await {
+            Ok(server) => match params(&server) {
+                Some(params) => server.notify(method, params).await,
+                None => Ok(()),
+            },
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            warn!("[LSP] `{method}` not sent to `{id}`: {err:#}");
+        }
+    }
+}
+
+#[command]
+/// Open a document on every server handling it (see
+/// [`registry::resolve_all`]), starting servers as needed. Re-opening an open
+/// document replaces its text, or re-opens it if the language id changed.
+pub async fn lsp_open_document<R: Runtime>(
+    app: AppHandle<R>,
+    payload: LspOpenDocument,
+) -> tauri::Result<LspDocumentState> {
+    let path = uri_to_path(&payload.uri)
+        .ok_or_else(|| tauri::Error::Anyhow(anyhow!("Not a file URI: {}", payload.uri)))?;
+    let root = server_root(&payload.root, Some(&path)).map_err(tauri::Error::Anyhow)?;
+    let configs =
+        registry::resolve_all(Path::new(&root), &path, Some(payload.language_id.as_str()));
+
+    // Starting a server can take until the initialize timeout, so it happens
+    // before taking the sync lock.
+    let mut started = Vec::new();
+    for config in &configs {
+        match ensure_server(&app, &root, config).await {
+            Ok(server) => started.push(server),
+            Err(err) => warn!(
+                "[LSP] Not opening {} on `{}`: {err:#}",
+                payload.uri, config.id
+            ),
+        }
+    }
+
+    let _sync = SYNC_LOCK.lock().await;
+    let existing = documents::OPEN_DOCUMENTS
+        .lock()
+        .unwrap()
+        .get(&payload.uri)
+        .map(|doc| doc.language_id == payload.language_id);
+    match existing {
+        // Opened meanwhile or before: a full-text change instead of a second
+        // `didOpen`.
+        Some(true) => {
+            let changes = vec![json!({ "text": payload.text })];
+            return change_document(LspChangeDocument {
+                uri: payload.uri,
+                changes,
+            })
+            .await;
+        }
+        // Language mode changed – the set of servers may differ, start over.
+        Some(false) => close_document(&payload.uri).await,
+        None => {}
+    }
+
+    let params = json!({
+        "textDocument": {
+            "uri": payload.uri,
+            "languageId": payload.language_id,
+            "version": 0,
+            "text": payload.text,
+        }
+    });
+    let mut servers = Vec::new();
+    for server in started {
+        if !server.sync_options().open_close {
+            continue;
+        }
+        // A failed send is still tracked and replayed once the server is back.
+        if let Err(err) = server.notify("textDocument/didOpen", params.clone()).await {
+            warn!(
+                "[LSP] `textDocument/didOpen` not sent to `{}`: {err:#}",
+                server.config.id
+            );
+        }
+        servers.push(server.config.id.clone());
+    }
+
+    documents::OPEN_DOCUMENTS.lock().unwrap().insert(
+        payload.uri.clone(),
+        documents::OpenDocument {
+            root,
+            language_id: payload.language_id,
+            version: 0,
+            text: payload.text,
+            servers: servers.clone(),
+        },
+    );
+    Ok(LspDocumentState {
+        uri: payload.uri,
+        version: 0,
+        servers,
+    })
+}
+
+#[command]
+/// Apply editor changes to an open document and forward them to its servers
+/// – ranged for incremental-sync servers, as full text for full-sync ones.
+pub async fn lsp_change_document(payload: LspChangeDocument) -> tauri::Result<LspDocumentState> {
+    let _sync = SYNC_LOCK.lock().await;
+    change_document(payload).await
+}
+
+/// [`lsp_change_document`] – the caller holds `SYNC_LOCK`.
+async fn change_document(payload: LspChangeDocument) -> tauri::Result<LspDocumentState> {
+    let (root, version, text, servers) = {
+        let mut open = documents::OPEN_DOCUMENTS.lock().unwrap();
+        let doc = open
+            .get_mut(&payload.uri)
+            .ok_or_else(|| tauri::Error::Anyhow(anyhow!("Document not open: {}", payload.uri)))?;
+        for change in &payload.changes {
+            documents::apply_change(&mut doc.text, change);
         }
+        doc.version += 1;
+        (
+            doc.root.clone(),
+            doc.version,
+            doc.text.clone(),
+            doc.servers.clone(),
+        )
+    };
+
+    let document = json!({ "uri": payload.uri, "version": version });
+    notify_servers(&root, &servers, "textDocument/didChange", |server| {
+        let changes = match server.sync_options().change {
+            SyncKind::Incremental => json!(payload.changes),
+            SyncKind::Full => json!([{ "text": text }]),
+            SyncKind::None => return None,
+        };
+        Some(json!({ "textDocument": document, "contentChanges": changes }))
+    })
+    .await;
+    Ok(LspDocumentState {
+        uri: payload.uri,
+        version,
+        servers,
+    })
+}
 
-        // Response with id?
-        if let Some(id_val) = &request_id {
-            if msg.get("id") == Some(id_val) {
-                break msg;
+#[command]
+/// Notify the servers of an open document that it was saved.
+pub async fn lsp_save_document(payload: LspDocumentRef) -> tauri::Result<()> {
+    let _sync = SYNC_LOCK.lock().await;
+    let (root, text, servers) = {
+        let open = documents::OPEN_DOCUMENTS.lock().unwrap();
+        let doc = open
+            .get(&payload.uri)
+            .ok_or_else(|| tauri::Error::Anyhow(anyhow!("Document not open: {}", payload.uri)))?;
+        (doc.root.clone(), doc.text.clone(), doc.servers.clone())
+    };
+
+    notify_servers(&root, &servers, "textDocument/didSave", |server| {
+        let include_text = server.sync_options().save?;
+        let mut params = json!({ "textDocument": { "uri": payload.uri } });
+        if include_text {
+            params["text"] = json!(text);
+        }
+        Some(params)
+    })
+    .await;
+    Ok(())
+}
+
+#[command]
+/// Close a document on all its servers and stop tracking it.
+pub async fn lsp_close_document(payload: LspDocumentRef) -> tauri::Result<()> {
+    let _sync = SYNC_LOCK.lock().await;
+    close_document(&payload.uri).await;
+    Ok(())
+}
+
+/// [`lsp_close_document`] – the caller holds `SYNC_LOCK`.
+async fn close_document(uri: &str) {
+    let Some(doc) = documents::OPEN_DOCUMENTS.lock().unwrap().remove(uri) else {
+        return;
+    };
+    semantic_tokens::forget(uri);
+
+    let params = json!({ "textDocument": { "uri": uri } });
+    notify_servers(&doc.root, &doc.servers, "textDocument/didClose", |_| {
+        Some(params.clone())
+    })
+    .await;
+}
+
+/// Follow open documents that a workspace edit renamed or deleted: close
+/// them on their servers, re-open renamed ones under the new URI with their
+/// buffer text, and emit `lsp_document_moved` so the editor retargets or
+/// closes the tab.
+async fn move_documents<R: Runtime>(app: &AppHandle<R>, moved: Vec<workspace_edit::MovedDocument>) {
+    for doc in moved {
+        let open = {
+            let _sync = SYNC_LOCK.lock().await;
+            let open = documents::OPEN_DOCUMENTS
+                .lock()
+                .unwrap()
+                .get(&doc.old_uri)
+                .map(|d| (d.root.clone(), d.language_id.clone(), d.text.clone()));
+            close_document(&doc.old_uri).await;
+            open
+        };
+        let Some((root, language_id, text)) = open else {
+            continue; // closed meanwhile
+        };
+        if let Some(new_uri) = &doc.new_uri {
+            let payload = LspOpenDocument {
+                root,
+                uri: new_uri.clone(),
+                language_id,
+                text: doc.text.clone().unwrap_or(text),
+            };
+            if let Err(err) = lsp_open_document(app.clone(), payload).await {
+                warn!("[LSP] Failed to re-open {new_uri}: {err:#}");
             }
-        } else {
-            // If original request lacked id, return first non-diag message
-            break msg;
         }
+        let _ = app.emit("lsp_document_moved", doc);
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Tauri commands – formatting
+// ----------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspFormatRequest {
+    /// Workspace root or `.glass-workspace` file
+    root: String,
+    /// `file://` URI of the document
+    uri: String,
+    /// LSP language id; defaults to the open document's
+    #[serde(default)]
+    language_id: Option<String>,
+    /// Text to format; defaults to the open buffer, then the file on disk
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[command]
+/// Format a document – see [`formatting`]. The edits apply to the text that
+/// was formatted and are not written anywhere, so format-on-save applies
+/// them to the buffer before saving.
+pub async fn lsp_format_document(
+    payload: LspFormatRequest,
+) -> tauri::Result<formatting::Formatted> {
+    let path = uri_to_path(&payload.uri)
+        .ok_or_else(|| tauri::Error::Anyhow(anyhow!("Not a file URI: {}", payload.uri)))?;
+    let root = server_root(&payload.root, Some(&path)).map_err(tauri::Error::Anyhow)?;
+    let text = match payload.text {
+        Some(text) => text,
+        None => documents::document_text(&payload.uri, &path).map_err(tauri::Error::Anyhow)?,
     };
+    let language = payload.language_id.or_else(|| {
+        documents::OPEN_DOCUMENTS
+            .lock()
+            .unwrap()
+            .get(&payload.uri)
+            .map(|doc| doc.language_id.clone())
+    });
+    formatting::format(&root, &payload.uri, &path, language.as_deref(), &text)
+        .await
+        .map_err(tauri::Error::Anyhow)
+}
+
+// ----------------------------------------------------------------------------
+// Tauri commands – semantic tokens
+// ----------------------------------------------------------------------------
 
-    Ok(LspInvokeResponse { response: resp })
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspSemanticTokensRequest {
+    /// `file://` URI of an open document
+    uri: String,
+    /// `resultId` of the tokens the editor holds; answered with a delta
+    #[serde(default)]
+    previous_result_id: Option<String>,
+}
+
+#[command]
+/// Semantic tokens of an open document in the legend of
+/// `lsp_semantic_tokens_legend` – see [`semantic_tokens`].
+pub async fn lsp_semantic_tokens(
+    payload: LspSemanticTokensRequest,
+) -> tauri::Result<semantic_tokens::SemanticTokens> {
+    semantic_tokens::tokens(&payload.uri, payload.previous_result_id.as_deref())
+        .await
+        .map_err(tauri::Error::Anyhow)
+}
+
+#[command]
+/// Legend of the tokens returned by `lsp_semantic_tokens`.
+pub fn lsp_semantic_tokens_legend() -> semantic_tokens::Legend {
+    semantic_tokens::LEGEND
+}
+
+// ----------------------------------------------------------------------------
+// Tauri commands – workspace edits
+// ----------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyWorkspaceEditRequest {
+    /// Workspace root or `.glass-workspace` file; edits outside it are rejected
+    root: String,
+    /// LSP `WorkspaceEdit`, e.g. from a rename or code action
+    edit: Value,
+    /// Name of the undo operation, e.g. "Rename `foo` to `bar`"
+    label: Option<String>,
+}
+
+#[command]
+/// Apply a `WorkspaceEdit` on the backend – see [`workspace_edit`].
+pub async fn apply_workspace_edit<R: Runtime>(
+    app: AppHandle<R>,
+    payload: ApplyWorkspaceEditRequest,
+) -> tauri::Result<workspace_edit::WorkspaceEditResult> {
+    let roots: Vec<PathBuf> = workspace::resolve_roots(Path::new(&payload.root))
+        .map_err(tauri::Error::Anyhow)?
+        .into_iter()
+        .map(|r| r.path)
+        .collect();
+    Ok(workspace_edit::apply(
+        &app,
+        &roots,
+        &payload.edit,
+        payload.label.as_deref(),
+    ))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoWorkspaceEditRequest {
+    /// `undoId` of an applied edit; the most recent one if omitted
+    id: Option<u64>,
+}
+
+#[command]
+/// Revert a workspace edit – files on disk and forwarded open-document edits.
+/// Returns its label.
+pub async fn undo_workspace_edit<R: Runtime>(
+    app: AppHandle<R>,
+    payload: UndoWorkspaceEditRequest,
+) -> tauri::Result<String> {
+    workspace_edit::undo(&app, payload.id).map_err(tauri::Error::Anyhow)
+}
+
+// ----------------------------------------------------------------------------
+// Tauri commands – traffic recording
+// ----------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspSetRecordingRequest {
+    enabled: bool,
+    /// Recording file (default: `<cache dir>/glass-ide/lsp-trace.jsonl`);
+    /// truncated when recording starts.
+    #[serde(default)]
+    path: Option<String>,
+}
+
+#[command]
+/// Start or stop recording all LSP traffic. Returns the recording file.
+pub async fn lsp_set_recording(payload: LspSetRecordingRequest) -> tauri::Result<Option<String>> {
+    let path = if payload.enabled {
+        Some(recorder::start(payload.path.map(PathBuf::from)).map_err(tauri::Error::Anyhow)?)
+    } else {
+        recorder::stop();
+        recorder::path()
+    };
+    Ok(path.map(|p| p.to_string_lossy().into_owned()))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspRecordingRequest {
+    /// Only messages of this root, as reported in events
+    #[serde(default)]
+    root: Option<String>,
+    /// Only messages of this server id
+    #[serde(default)]
+    server: Option<String>,
+    /// Number of most recent messages (default: all)
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[command]
+/// Messages of the current (or last) recording, oldest first.
+pub async fn lsp_recording(payload: LspRecordingRequest) -> tauri::Result<Vec<recorder::Entry>> {
+    let path = recorder::path()
+        .ok_or_else(|| tauri::Error::Anyhow(anyhow!("LSP traffic has not been recorded")))?;
+    let mut entries = recorder::read(&path).map_err(tauri::Error::Anyhow)?;
+    entries.retain(|e| {
+        payload.root.as_ref().map_or(true, |r| *r == e.root)
+            && payload.server.as_ref().map_or(true, |s| *s == e.server)
+    });
+    if let Some(limit) = payload.limit {
+        entries.drain(..entries.len().saturating_sub(limit));
+    }
+    Ok(entries)
 }
@@ -0,0 +1,159 @@
+//! Work-done progress (`window/workDoneProgress/create` + `$/progress`).
+//!
+//! Each server keeps the tasks it created (pending) or reports as running – rust-analyzer's
+//! indexing, `cargo check`… – so a status bar can query them (`lsp_status`)
+//! instead of listening from start-up. Every update is also emitted as an
+//! `lsp_progress` event. Tasks of a process that exits are ended for it.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum ProgressKind {
+    /// `window/workDoneProgress/create`: the task is pending until `begin`.
+    Create,
+    Begin,
+    Report,
+    End,
+}
+
+/// A running work-done progress task.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressTask {
+    /// `ProgressToken` (string or number) chosen by the server
+    pub token: Value,
+    pub title: String,
+    pub message: Option<String>,
+    /// 0–100, if the server reports it
+    pub percentage: Option<u32>,
+    pub cancellable: bool,
+    /// Created by the server but not begun yet (no title so far)
+    pub pending: bool,
+    /// Milliseconds since the Unix epoch
+    pub started: u64,
+}
+
+/// Payload of the `lsp_progress` event.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressEvent {
+    pub root: String,
+    pub server: String,
+    pub kind: ProgressKind,
+    #[serde(flatten)]
+    pub task: ProgressTask,
+}
+
+/// Running tasks of one server, in start order.
+#[derive(Default)]
+pub struct Progress {
+    tasks: Vec<ProgressTask>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+impl Progress {
+    /// Register the token of a `window/workDoneProgress/create` request as a
+    /// pending task. `None` if a task with that token is already running.
+    pub fn create(&mut self, params: &Value) -> Option<ProgressTask> {
+        let token = params.get("token")?;
+        if self.tasks.iter().any(|t| t.token == *token) {
+            return None;
+        }
+        let task = ProgressTask {
+            token: token.clone(),
+            title: String::new(),
+            message: None,
+            percentage: None,
+            cancellable: false,
+            pending: true,
+            started: now_ms(),
+        };
+        self.tasks.push(task.clone());
+        Some(task)
+    }
+
+    /// Apply a `$/progress` notification. Returns the kind and the task as
+    /// updated (or as finished, for `end`); `None` for values that are not
+    /// work-done progress or for reports on tokens that never began.
+    pub fn update(&mut self, params: &Value) -> Option<(ProgressKind, ProgressTask)> {
+        let token = params.get("token")?;
+        let value = params.get("value")?;
+        let message = value
+            .get("message")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let percentage = value
+            .get("percentage")
+            .and_then(Value::as_u64)
+            .map(|p| p.min(100) as u32);
+        let cancellable = value.get("cancellable").and_then(Value::as_bool);
+        let index = self.tasks.iter().position(|t| t.token == *token);
+
+        match value.get("kind").and_then(Value::as_str)? {
+            "begin" => {
+                let task = ProgressTask {
+                    token: token.clone(),
+                    title: value
+                        .get("title")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    message,
+                    percentage,
+                    cancellable: cancellable.unwrap_or(false),
+                    pending: false,
+                    started: now_ms(),
+                };
+                // A created token begins its pending task; a token may be
+                // reused once its task ended, and a repeated `begin` restarts it.
+                match index {
+                    Some(index) => self.tasks[index] = task.clone(),
+                    None => self.tasks.push(task.clone()),
+                }
+                Some((ProgressKind::Begin, task))
+            }
+            "report" => {
+                let task = &mut self.tasks[index?];
+                if task.pending {
+                    return None;
+                }
+                if message.is_some() {
+                    task.message = message;
+                }
+                if percentage.is_some() {
+                    task.percentage = percentage;
+                }
+                if let Some(cancellable) = cancellable {
+                    task.cancellable = cancellable;
+                }
+                Some((ProgressKind::Report, task.clone()))
+            }
+            "end" => {
+                let mut task = self.tasks.remove(index?);
+                if message.is_some() {
+                    task.message = message;
+                }
+                Some((ProgressKind::End, task))
+            }
+            _ => None,
+        }
+    }
+
+    /// Running tasks, oldest first.
+    pub fn tasks(&self) -> Vec<ProgressTask> {
+        self.tasks.clone()
+    }
+
+    /// Drop every task, e.g. because the server exited.
+    pub fn end_all(&mut self) -> Vec<ProgressTask> {
+        std::mem::take(&mut self.tasks)
+    }
+}
@@ -0,0 +1,147 @@
+//! Opt-in recorder for language-server traffic.
+//!
+//! While recording is enabled (`lsp_set_recording`) every JSON-RPC message
+//! exchanged with any server is appended to a JSON-lines file, one [`Entry`]
+//! per line:
+//!
+//! ```json
+//! {"time":1718000000123,"direction":"send","root":"/proj","server":"rust-analyzer","message":{…}}
+//! ```
+//!
+//! Messages are recorded as they go over the wire, i.e. with the ids the
+//! backend assigned, including the handshake and the backend's own answers to
+//! server requests. Recordings can be read back with `lsp_recording` and
+//! replayed as a fake server (see [`super::replay`]).
+
+use anyhow::{anyhow, Context, Result};
+use dirs_next::cache_dir;
+use log::warn;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Direction of a recorded message, seen from the IDE.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Direction {
+    /// IDE → server
+    Send,
+    /// Server → IDE
+    Receive,
+}
+
+/// One recorded message.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    /// Milliseconds since the Unix epoch.
+    pub time: u64,
+    pub direction: Direction,
+    pub root: String,
+    pub server: String,
+    pub message: Value,
+}
+
+struct Recording {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+/// Checked before touching [`RECORDING`], so disabled recording costs one load.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Current recording, or the last one once stopped (kept for `lsp_recording`).
+static RECORDING: Lazy<Mutex<Option<Recording>>> = Lazy::new(|| Mutex::new(None));
+
+/// Default recording file.
+pub fn default_path() -> Option<PathBuf> {
+    Some(cache_dir()?.join("glass-ide").join("lsp-trace.jsonl"))
+}
+
+/// Start recording to `path` (default: [`default_path`]), truncating it.
+pub fn start(path: Option<PathBuf>) -> Result<PathBuf> {
+    let path = path
+        .or_else(default_path)
+        .ok_or_else(|| anyhow!("No cache directory for the LSP recording"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let file = File::create(&path)
+        .with_context(|| format!("Failed to create LSP recording {}", path.display()))?;
+    *RECORDING.lock().unwrap() = Some(Recording {
+        path: path.clone(),
+        file: Some(file),
+    });
+    ENABLED.store(true, Ordering::Release);
+    Ok(path)
+}
+
+/// Stop recording; the file stays available to [`path`].
+pub fn stop() {
+    ENABLED.store(false, Ordering::Release);
+    if let Some(recording) = RECORDING.lock().unwrap().as_mut() {
+        recording.file = None;
+    }
+}
+
+/// File of the current or last recording.
+pub fn path() -> Option<PathBuf> {
+    RECORDING.lock().unwrap().as_ref().map(|r| r.path.clone())
+}
+
+/// Append `message` to the recording, if one is running.
+pub fn record(direction: Direction, root: &str, server: &str, message: &Value) {
+    if !ENABLED.load(Ordering::Acquire) {
+        return;
+    }
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64);
+    let entry = Entry {
+        time,
+        direction,
+        root: root.to_string(),
+        server: server.to_string(),
+        message: message.clone(),
+    };
+    let Ok(mut line) = serde_json::to_vec(&entry) else {
+        return;
+    };
+    line.push(b'\n');
+
+    let mut recording = RECORDING.lock().unwrap();
+    let Some(file) = recording.as_mut().and_then(|r| r.file.as_mut()) else {
+        return;
+    };
+    if let Err(err) = file.write_all(&line) {
+        warn!("[LSP] Recording stopped: {err}");
+        ENABLED.store(false, Ordering::Release);
+        if let Some(recording) = recording.as_mut() {
+            recording.file = None;
+        }
+    }
+}
+
+/// Read a recording, skipping lines that are not valid entries.
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open LSP recording {}", path.display()))?;
+    let mut entries = Vec::new();
+    for (n, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(err) => warn!("[LSP] {}:{}: invalid entry: {err}", path.display(), n + 1),
+        }
+    }
+    Ok(entries)
+}
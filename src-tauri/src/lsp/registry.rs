@@ -0,0 +1,317 @@
+//! Language-server registry.
+//!
+//! Maps languages / file globs to the server that should handle them, so a
+//! single root can run several servers at once (Rust, TS, Python, Go…).
+//! Configuration is layered, later layers overriding earlier ones per server
+//! id and per field:
+//! 1. built-in defaults ([`builtin_servers`])
+//! 2. user config:      `<config dir>/glass-ide/lsp.json`
+//! 3. workspace config: `<root>/.glass/lsp.json`
+//!
+//! ```json
+//! {
+//!   "servers": {
+//!     "python": { "command": "pylsp", "args": [] },
+//!     "typescript": { "enabled": false },
+//!     "zls": { "command": "zls", "languages": ["zig"], "filePatterns": ["*.zig"] },
+//!     "rust-analyzer": { "timeouts": { "textDocument/references": 120000 } },
+//!     "go": { "replay": ".glass/gopls-trace.jsonl" }
+//!   }
+//! }
+//! ```
+//!
+//! Config files are tiny and re-read on every lookup, so edits apply to the
+//! next server that is started without any reload step.
+
+use anyhow::{anyhow, Result};
+use dirs_next::config_dir;
+use log::warn;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::server::REQUEST_TIMEOUT;
+use crate::commands::search_query::build_globset;
+
+/// Built-in per-method request timeouts in milliseconds; everything else gets
+/// [`REQUEST_TIMEOUT`]. Interactive requests fail fast, whole-project queries
+/// get time to index.
+const DEFAULT_TIMEOUTS: &[(&str, u64)] = &[
+    ("textDocument/completion", 2_000),
+    ("completionItem/resolve", 2_000),
+    ("textDocument/hover", 2_000),
+    ("textDocument/signatureHelp", 2_000),
+    ("textDocument/documentHighlight", 2_000),
+    ("textDocument/references", 60_000),
+    ("textDocument/implementation", 30_000),
+    ("textDocument/rename", 30_000),
+    ("textDocument/formatting", 10_000),
+    ("textDocument/rangeFormatting", 10_000),
+    ("workspace/symbol", 10_000),
+    ("workspace/executeCommand", 30_000),
+];
+
+/// Resolved configuration of one language server.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerConfig {
+    /// Registry key, e.g. `rust-analyzer` – also used to tag events.
+    #[serde(skip)]
+    pub id: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// LSP language ids (`textDocument.languageId`) handled by this server.
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// Globs for documents handled by this server; patterns without `/`
+    /// match the file name at any depth.
+    #[serde(default)]
+    pub file_patterns: Vec<String>,
+    /// Files whose presence in the root selects this server for requests
+    /// that carry no document (e.g. `workspace/symbol`).
+    #[serde(default)]
+    pub root_markers: Vec<String>,
+    #[serde(default)]
+    pub initialization_options: Option<Value>,
+    /// Answers `workspace/configuration`, looked up by dotted `section`.
+    #[serde(default)]
+    pub settings: Value,
+    /// Request timeouts in milliseconds by method, overriding the built-in
+    /// ones; the key `default` applies to methods without a specific timeout.
+    #[serde(default)]
+    pub timeouts: HashMap<String, u64>,
+    /// Recording (see `lsp_set_recording`) to replay instead of running
+    /// `command`; relative paths are resolved against the root.
+    #[serde(default)]
+    pub replay: Option<PathBuf>,
+    /// Arguments that make `command` print its version, for the health check
+    /// (`lsp_health`); empty to skip the probe.
+    #[serde(default = "version_args_default")]
+    pub version_args: Vec<String>,
+    #[serde(default = "enabled_default")]
+    pub enabled: bool,
+}
+
+fn enabled_default() -> bool {
+    true
+}
+
+fn version_args_default() -> Vec<String> {
+    vec!["--version".to_string()]
+}
+
+/// Built-in defaults, in routing priority order.
+fn builtin_servers() -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "rust-analyzer",
+            json!({
+                "command": "rust-analyzer",
+                "languages": ["rust"],
+                "filePatterns": ["*.rs"],
+                "rootMarkers": ["Cargo.toml"],
+            }),
+        ),
+        (
+            "typescript",
+            json!({
+                "command": "typescript-language-server",
+                "args": ["--stdio"],
+                "languages": ["typescript", "typescriptreact", "javascript", "javascriptreact"],
+                "filePatterns": ["*.ts", "*.tsx", "*.mts", "*.cts", "*.js", "*.jsx", "*.mjs", "*.cjs"],
+                "rootMarkers": ["tsconfig.json", "jsconfig.json", "package.json"],
+            }),
+        ),
+        (
+            "python",
+            json!({
+                "command": "pyright-langserver",
+                "args": ["--stdio"],
+                "languages": ["python"],
+                "filePatterns": ["*.py", "*.pyi"],
+                "rootMarkers": ["pyproject.toml", "pyrightconfig.json", "setup.py", "requirements.txt"],
+                // The language server has no version flag.
+                "versionArgs": [],
+            }),
+        ),
+        (
+            "go",
+            json!({
+                "command": "gopls",
+                "languages": ["go"],
+                "filePatterns": ["*.go"],
+                "rootMarkers": ["go.mod"],
+                "versionArgs": ["version"],
+            }),
+        ),
+    ]
+}
+
+/// Path of the user-level config file.
+pub fn user_config_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("glass-ide").join("lsp.json"))
+}
+
+/// Path of the workspace-level config file for `root`.
+pub fn workspace_config_path(root: &Path) -> PathBuf {
+    root.join(".glass").join("lsp.json")
+}
+
+/// Read the `section` object (e.g. `servers`) of a config file (empty if
+/// missing / invalid). Shared with the debug-adapter registry.
+pub fn read_layer(path: &Path, section: &str) -> Map<String, Value> {
+    let Ok(bytes) = fs::read(path) else {
+        return Map::new();
+    };
+    match serde_json::from_slice::<Value>(&bytes) {
+        Ok(Value::Object(mut file)) => match file.remove(section) {
+            Some(Value::Object(servers)) => servers,
+            _ => Map::new(),
+        },
+        Ok(_) => Map::new(),
+        Err(err) => {
+            warn!("Ignoring invalid config {}: {err}", path.display());
+            Map::new()
+        }
+    }
+}
+
+/// Recursively merge `overlay` into `base`; objects merge, everything else replaces.
+pub fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Enabled servers for `root`, in routing priority order (built-ins first,
+/// then user/workspace additions by name).
+pub fn load(root: &Path) -> Vec<ServerConfig> {
+    let mut entries: Vec<(String, Value)> = builtin_servers()
+        .into_iter()
+        .map(|(id, cfg)| (id.to_string(), cfg))
+        .collect();
+
+    let layers = [
+        user_config_path()
+            .map(|p| read_layer(&p, "servers"))
+            .unwrap_or_default(),
+        read_layer(&workspace_config_path(root), "servers"),
+    ];
+    for layer in layers {
+        for (id, overlay) in layer {
+            match entries.iter_mut().find(|(existing, _)| *existing == id) {
+                Some((_, cfg)) => merge(cfg, overlay),
+                None => entries.push((id, overlay)),
+            }
+        }
+    }
+
+    entries
+        .into_iter()
+        .filter_map(
+            |(id, cfg)| match serde_json::from_value::<ServerConfig>(cfg) {
+                Ok(server) => Some(ServerConfig { id, ..server }),
+                Err(err) => {
+                    warn!("[LSP] Ignoring server `{id}`: {err}");
+                    None
+                }
+            },
+        )
+        .filter(|server| server.enabled)
+        .collect()
+}
+
+impl ServerConfig {
+    /// Whether `path` (relative to the root) matches one of `file_patterns`.
+    pub fn matches_file(&self, rel: &Path) -> bool {
+        matches_patterns(&self.file_patterns, rel)
+    }
+
+    /// How long to wait for the response to a `method` request.
+    pub fn request_timeout(&self, method: &str) -> Duration {
+        let builtin = DEFAULT_TIMEOUTS
+            .iter()
+            .find(|(m, _)| *m == method)
+            .map(|(_, ms)| *ms);
+        self.timeouts
+            .get(method)
+            .copied()
+            .or(builtin)
+            .or_else(|| self.timeouts.get("default").copied())
+            .map_or(REQUEST_TIMEOUT, Duration::from_millis)
+    }
+
+    /// Value for a `workspace/configuration` item `section` (dotted path).
+    pub fn setting(&self, section: Option<&str>) -> Value {
+        let Some(section) = section.filter(|s| !s.is_empty()) else {
+            return self.settings.clone();
+        };
+        section
+            .split('.')
+            .try_fold(&self.settings, |value, key| value.get(key))
+            .cloned()
+            .unwrap_or(Value::Null)
+    }
+}
+
+/// Whether `rel` (relative to the root) matches one of `patterns`; patterns
+/// without `/` match the file name at any depth.
+pub fn matches_patterns(patterns: &[String], rel: &Path) -> bool {
+    build_globset(patterns, false).is_some_and(|set| set.is_match(rel))
+}
+
+/// Pick the server for a request in `root`: by explicit language id first,
+/// then by document path, then by root markers.
+pub fn resolve(
+    root: &Path,
+    document: Option<&Path>,
+    language: Option<&str>,
+) -> Result<ServerConfig> {
+    let servers = load(root);
+
+    if let Some(language) = language {
+        if let Some(server) = servers
+            .iter()
+            .find(|s| s.languages.iter().any(|l| l == language))
+        {
+            return Ok(server.clone());
+        }
+    }
+    if let Some(document) = document {
+        let rel = document.strip_prefix(root).unwrap_or(document);
+        if let Some(server) = servers.iter().find(|s| s.matches_file(rel)) {
+            return Ok(server.clone());
+        }
+        return Err(anyhow!(
+            "No language server configured for {}",
+            document.display()
+        ));
+    }
+    servers
+        .into_iter()
+        .find(|s| s.root_markers.iter().any(|m| root.join(m).exists()))
+        .ok_or_else(|| anyhow!("No language server configured for {}", root.display()))
+}
+
+/// Every server that handles `document`: by language id or file pattern, in
+/// priority order. Lets e.g. a linter server run next to the language server.
+pub fn resolve_all(root: &Path, document: &Path, language: Option<&str>) -> Vec<ServerConfig> {
+    let rel = document.strip_prefix(root).unwrap_or(document);
+    load(root)
+        .into_iter()
+        .filter(|s| {
+            language.is_some_and(|l| s.languages.iter().any(|x| x == l)) || s.matches_file(rel)
+        })
+        .collect()
+}
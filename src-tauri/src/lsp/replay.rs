@@ -0,0 +1,370 @@
+//! Fake language server driven by a [`recorder`](super::recorder) recording.
+//!
+//! A server configured with `"replay": "<recording.jsonl>"` is not spawned;
+//! the bridge talks to an in-process [`Script`] over an in-memory pipe
+//! instead. That exercises the whole bridge (handshake, routing, sync,
+//! events) without a real language server, and reproduces a user's session
+//! from their trace.
+//!
+//! Live messages are matched to recorded ones by method: each consumes the
+//! first unused recorded message of the same method, preferring one with
+//! identical params. A request is answered with its recorded response under
+//! the live id; once all recorded requests of a method are used the last one
+//! is answered again. Server-initiated messages recorded after a client
+//! message (diagnostics after `didOpen`, progress, `workspace/configuration`…)
+//! are sent when the matching live message arrives. Timing is not reproduced.
+
+use anyhow::Result;
+use log::warn;
+use serde_json::{json, Value};
+use std::path::Path;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::framing::{self, FrameReader, FramingError};
+use super::recorder::{self, Direction, Entry};
+
+/// JSON-RPC error code for methods the recording has no answer for.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// A recorded client message and what the server sent back.
+struct Step {
+    method: String,
+    params: Value,
+    /// Recorded id, for requests.
+    id: Option<Value>,
+    /// Server messages in recorded order; includes this request's response.
+    replies: Vec<Value>,
+    answered: bool,
+    used: bool,
+}
+
+/// A recording prepared for replay.
+#[derive(Default)]
+pub struct Script {
+    /// Server messages recorded before the first client message.
+    preamble: Vec<Value>,
+    steps: Vec<Step>,
+}
+
+impl Script {
+    /// Load the messages of `server` from a recording; a recording that
+    /// contains no messages of `server` is used as a whole.
+    pub fn load(path: &Path, server: &str) -> Result<Self> {
+        let entries = recorder::read(path)?;
+        let own = entries.iter().any(|e| e.server == server);
+        Ok(Self::from_entries(
+            entries.into_iter().filter(|e| !own || e.server == server),
+        ))
+    }
+
+    /// Build a script from recorded entries of a single server.
+    pub fn from_entries(entries: impl IntoIterator<Item = Entry>) -> Self {
+        let mut script = Script::default();
+        for entry in entries {
+            let msg = entry.message;
+            let method = msg
+                .get("method")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            match (entry.direction, method) {
+                (Direction::Send, Some(method)) => script.steps.push(Step {
+                    method,
+                    params: msg.get("params").cloned().unwrap_or(Value::Null),
+                    id: msg.get("id").cloned(),
+                    replies: Vec::new(),
+                    answered: false,
+                    used: false,
+                }),
+                // The IDE's answers to server requests are generated live.
+                (Direction::Send, None) => {}
+                (Direction::Receive, Some(_)) => match script.steps.last_mut() {
+                    Some(step) => step.replies.push(msg),
+                    None => script.preamble.push(msg),
+                },
+                (Direction::Receive, None) => {
+                    let id = msg.get("id");
+                    // Ids restart with every server incarnation – the latest
+                    // unanswered request with this id is the one.
+                    let step = script
+                        .steps
+                        .iter_mut()
+                        .rev()
+                        .find(|s| !s.answered && s.id.is_some() && s.id.as_ref() == id);
+                    if let Some(step) = step {
+                        step.answered = true;
+                        step.replies.push(msg);
+                    }
+                }
+            }
+        }
+        script
+    }
+
+    /// Recorded replies to a live message, with the response (if any)
+    /// re-addressed to `live_id`.
+    fn replies(&mut self, method: &str, params: &Value, live_id: Option<&Value>) -> Vec<Value> {
+        let is_request = live_id.is_some();
+        let same_kind = |s: &Step| s.method == method && s.id.is_some() == is_request;
+        let unused = |s: &Step| !s.used && same_kind(s);
+        let index = self
+            .steps
+            .iter()
+            .position(|s| unused(s) && s.params == *params)
+            .or_else(|| self.steps.iter().position(unused));
+        let index = match index {
+            Some(index) => {
+                self.steps[index].used = true;
+                Some(index)
+            }
+            None if is_request => self.steps.iter().rposition(same_kind),
+            None => None,
+        };
+        let Some(step) = index.map(|i| &self.steps[i]) else {
+            return Vec::new();
+        };
+
+        step.replies
+            .iter()
+            .map(|reply| {
+                let mut reply = reply.clone();
+                if let (None, Some(id)) = (reply.get("method"), live_id) {
+                    reply["id"] = id.clone();
+                }
+                reply
+            })
+            .collect()
+    }
+
+    /// Act as the server on `reader` / `writer` until `exit` or EOF.
+    pub async fn serve<R, W>(mut self, reader: R, mut writer: W)
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut reader = FrameReader::new(reader);
+        let preamble = std::mem::take(&mut self.preamble);
+        for msg in preamble {
+            if framing::write_frame(&mut writer, &msg).await.is_err() {
+                return;
+            }
+        }
+        loop {
+            let msg = match reader.next().await {
+                Ok(msg) => msg,
+                Err(FramingError::Closed) => return,
+                Err(err) if err.is_fatal() => {
+                    warn!("[LSP replay] Reader stopped: {err}");
+                    return;
+                }
+                Err(err) => {
+                    warn!("[LSP replay] Malformed frame: {err}");
+                    continue;
+                }
+            };
+            // Responses to replayed server requests need no answer.
+            let Some(method) = msg.get("method").and_then(Value::as_str) else {
+                continue;
+            };
+            let id = msg.get("id");
+            let params = msg.get("params").unwrap_or(&Value::Null);
+
+            let mut replies = self.replies(method, params, id);
+            if let Some(id) = id {
+                let answered = replies
+                    .iter()
+                    .any(|r| r.get("method").is_none() && r.get("id") == Some(id));
+                if !answered {
+                    replies.push(unrecorded_response(method, id));
+                }
+            }
+            for reply in replies {
+                if framing::write_frame(&mut writer, &reply).await.is_err() {
+                    return;
+                }
+            }
+            if method == "exit" {
+                return;
+            }
+        }
+    }
+}
+
+/// Answer for a request the recording has no response for. `shutdown` always
+/// succeeds so replayed servers stop cleanly.
+fn unrecorded_response(method: &str, id: &Value) -> Value {
+    if method == "shutdown" {
+        return json!({ "jsonrpc": "2.0", "id": id, "result": null });
+    }
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {
+            "code": METHOD_NOT_FOUND,
+            "message": format!("No recorded response for `{method}`"),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{duplex, split, DuplexStream, ReadHalf, WriteHalf};
+
+    fn entry(direction: Direction, message: Value) -> Entry {
+        Entry {
+            time: 0,
+            direction,
+            root: "/proj".into(),
+            server: "fake".into(),
+            message,
+        }
+    }
+
+    fn send(message: Value) -> Entry {
+        entry(Direction::Send, message)
+    }
+
+    fn receive(message: Value) -> Entry {
+        entry(Direction::Receive, message)
+    }
+
+    fn hover(id: i64, line: u64) -> Value {
+        json!({
+            "jsonrpc": "2.0", "id": id, "method": "textDocument/hover",
+            "params": { "textDocument": { "uri": "file:///proj/a.rs" }, "position": { "line": line, "character": 0 } },
+        })
+    }
+
+    fn recording() -> Vec<Entry> {
+        vec![
+            send(json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} })),
+            receive(
+                json!({ "jsonrpc": "2.0", "method": "window/logMessage", "params": { "message": "hi" } }),
+            ),
+            receive(
+                json!({ "jsonrpc": "2.0", "id": 1, "result": { "capabilities": { "hoverProvider": true } } }),
+            ),
+            send(json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} })),
+            send(json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {} })),
+            receive(
+                json!({ "jsonrpc": "2.0", "method": "textDocument/publishDiagnostics", "params": { "diagnostics": [] } }),
+            ),
+            // Concurrent hovers, answered out of order.
+            send(hover(2, 1)),
+            send(hover(3, 7)),
+            receive(json!({ "jsonrpc": "2.0", "id": 3, "result": { "contents": "line 7" } })),
+            receive(json!({ "jsonrpc": "2.0", "id": 2, "result": { "contents": "line 1" } })),
+        ]
+    }
+
+    /// Client side of a replay running in the background.
+    struct Client {
+        reader: FrameReader<ReadHalf<DuplexStream>>,
+        writer: WriteHalf<DuplexStream>,
+    }
+
+    impl Client {
+        fn start(entries: Vec<Entry>) -> Self {
+            let (client, server) = duplex(64 * 1024);
+            let (server_reader, server_writer) = split(server);
+            tokio::spawn(Script::from_entries(entries).serve(server_reader, server_writer));
+            let (reader, writer) = split(client);
+            Client {
+                reader: FrameReader::new(reader),
+                writer,
+            }
+        }
+
+        async fn send(&mut self, msg: Value) {
+            framing::write_frame(&mut self.writer, &msg).await.unwrap();
+        }
+
+        async fn next(&mut self) -> Value {
+            self.reader.next().await.unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn answers_under_live_ids_with_recorded_notifications() {
+        let mut client = Client::start(recording());
+        client
+            .send(
+                json!({ "jsonrpc": "2.0", "id": 41, "method": "initialize", "params": { "x": 1 } }),
+            )
+            .await;
+        assert_eq!(client.next().await["method"], "window/logMessage");
+        let init = client.next().await;
+        assert_eq!(init["id"], 41);
+        assert_eq!(init["result"]["capabilities"]["hoverProvider"], true);
+
+        client
+            .send(json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {} }))
+            .await;
+        assert_eq!(
+            client.next().await["method"],
+            "textDocument/publishDiagnostics"
+        );
+    }
+
+    #[tokio::test]
+    async fn prefers_requests_with_equal_params() {
+        let mut client = Client::start(recording());
+        client.send(hover(10, 7)).await;
+        let response = client.next().await;
+        assert_eq!(response["id"], 10);
+        assert_eq!(response["result"]["contents"], "line 7");
+
+        client.send(hover(11, 7)).await;
+        assert_eq!(client.next().await["result"]["contents"], "line 1");
+        // Everything used – the last recorded hover is repeated.
+        client.send(hover(12, 3)).await;
+        let response = client.next().await;
+        assert_eq!(response["id"], 12);
+        assert_eq!(response["result"]["contents"], "line 7");
+    }
+
+    #[tokio::test]
+    async fn unrecorded_requests_fail_and_exit_ends_the_replay() {
+        let mut client = Client::start(recording());
+        client
+            .send(
+                json!({ "jsonrpc": "2.0", "id": 5, "method": "textDocument/rename", "params": {} }),
+            )
+            .await;
+        let response = client.next().await;
+        assert_eq!(response["id"], 5);
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        client
+            .send(json!({ "jsonrpc": "2.0", "id": 6, "method": "shutdown" }))
+            .await;
+        assert_eq!(
+            client.next().await,
+            json!({ "jsonrpc": "2.0", "id": 6, "result": null })
+        );
+        client
+            .send(json!({ "jsonrpc": "2.0", "method": "exit" }))
+            .await;
+        assert!(matches!(
+            client.reader.next().await,
+            Err(FramingError::Closed)
+        ));
+    }
+
+    #[test]
+    fn responses_attach_to_the_latest_request_with_their_id() {
+        // A restart makes ids start over.
+        let mut entries = recording();
+        entries.push(send(hover(2, 9)));
+        entries.push(receive(
+            json!({ "jsonrpc": "2.0", "id": 2, "result": { "contents": "line 9" } }),
+        ));
+        let mut script = Script::from_entries(entries);
+        let params = hover(0, 9)["params"].clone();
+        let replies = script.replies("textDocument/hover", &params, Some(&json!(20)));
+        assert_eq!(
+            replies,
+            vec![json!({ "jsonrpc": "2.0", "id": 20, "result": { "contents": "line 9" } })]
+        );
+    }
+}
@@ -0,0 +1,710 @@
+//! Semantic tokens for open documents (`lsp_semantic_tokens`).
+//!
+//! Every result is one merged token stream in a fixed legend
+//! ([`TOKEN_TYPES`] / [`TOKEN_MODIFIERS`], `lsp_semantic_tokens_legend`):
+//! the tokens of the document's language server, remapped from the server's
+//! legend, overlaid on a tree-sitter fallback built from the grammar's
+//! highlight query. Fallback tokens are dropped where they overlap a server
+//! token, so the fallback only fills gaps – and covers the whole file while
+//! the server is starting or has no semantic tokens.
+//!
+//! Results are cached per document. The server is asked with
+//! `textDocument/semanticTokens/full/delta` when it supports it, and the
+//! editor gets a `SemanticTokensDelta` against the result it last received,
+//! so a keystroke in a large file transfers a few numbers instead of the
+//! whole token array. The fallback is only recomputed when the buffer
+//! version changed.
+
+use anyhow::{anyhow, Result};
+use log::warn;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{Parser, Query, QueryCursor};
+
+use super::documents::OPEN_DOCUMENTS;
+use super::find_server;
+use super::server::LspServer;
+use crate::commands::symbol_indexer::Lang;
+
+/// Token types of the merged stream – the predefined LSP types.
+pub const TOKEN_TYPES: &[&str] = &[
+    "namespace",
+    "type",
+    "class",
+    "enum",
+    "interface",
+    "struct",
+    "typeParameter",
+    "parameter",
+    "variable",
+    "property",
+    "enumMember",
+    "event",
+    "function",
+    "method",
+    "macro",
+    "keyword",
+    "modifier",
+    "comment",
+    "string",
+    "number",
+    "regexp",
+    "operator",
+    "decorator",
+];
+
+/// Token modifiers of the merged stream – the predefined LSP modifiers.
+pub const TOKEN_MODIFIERS: &[&str] = &[
+    "declaration",
+    "definition",
+    "readonly",
+    "static",
+    "deprecated",
+    "abstract",
+    "async",
+    "modification",
+    "documentation",
+    "defaultLibrary",
+];
+
+/// `SemanticTokensLegend` of the merged stream.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Legend {
+    pub token_types: &'static [&'static str],
+    pub token_modifiers: &'static [&'static str],
+}
+
+pub const LEGEND: Legend = Legend {
+    token_types: TOKEN_TYPES,
+    token_modifiers: TOKEN_MODIFIERS,
+};
+
+/// One token with an absolute position (UTF-16 columns).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Token {
+    line: u32,
+    start: u32,
+    length: u32,
+    kind: u32,
+    modifiers: u32,
+}
+
+/// A `SemanticTokensEdit` on the flat token array.
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TokensEdit {
+    pub start: usize,
+    pub delete_count: usize,
+    pub data: Vec<u32>,
+}
+
+/// `SemanticTokens` (`data`) or `SemanticTokensDelta` (`edits`) for the editor.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticTokens {
+    pub result_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Vec<u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edits: Option<Vec<TokensEdit>>,
+    /// Server whose tokens are included; `None` for the fallback alone.
+    pub server: Option<String>,
+}
+
+/// Last server result for a document, the base of `full/delta` requests.
+#[derive(Clone)]
+struct ServerResult {
+    server: String,
+    result_id: Option<String>,
+    data: Vec<u32>,
+}
+
+struct Entry {
+    /// Buffer version `fallback` was computed for.
+    version: i64,
+    fallback: Vec<Token>,
+    server: Option<ServerResult>,
+    /// Last merged stream sent to the editor.
+    result_id: String,
+    data: Vec<u32>,
+}
+
+/// Cached results by document URI.
+static CACHE: Lazy<Mutex<HashMap<String, Entry>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_RESULT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Drop the cached results of a closed document.
+pub fn forget(uri: &str) {
+    CACHE.lock().unwrap().remove(uri);
+}
+
+/// Tokens for the open document `uri`; a delta if `previous_result_id` is the
+/// result the editor got last.
+pub async fn tokens(uri: &str, previous_result_id: Option<&str>) -> Result<SemanticTokens> {
+    let (root, language, version, text, servers) = {
+        let open = OPEN_DOCUMENTS.lock().unwrap();
+        let doc = open
+            .get(uri)
+            .ok_or_else(|| anyhow!("Document not open: {uri}"))?;
+        (
+            doc.root.clone(),
+            doc.language_id.clone(),
+            doc.version,
+            doc.text.clone(),
+            doc.servers.clone(),
+        )
+    };
+    let (cached_fallback, cached_server, previous) = {
+        let cache = CACHE.lock().unwrap();
+        match cache.get(uri) {
+            Some(entry) => (
+                (entry.version == version).then(|| entry.fallback.clone()),
+                entry.server.clone(),
+                (previous_result_id == Some(entry.result_id.as_str())).then(|| entry.data.clone()),
+            ),
+            None => (None, None, None),
+        }
+    };
+
+    let fallback = match cached_fallback {
+        Some(fallback) => fallback,
+        None => tokio::task::spawn_blocking(move || fallback_tokens(&language, &text)).await?,
+    };
+
+    let mut server_result = None;
+    let mut server_tokens = Vec::new();
+    for id in &servers {
+        let Ok(server) = find_server(&root, id).await else {
+            continue;
+        };
+        let Some(provider) = provider(&server) else {
+            continue;
+        };
+        let base = cached_server.as_ref().filter(|r| r.server == *id);
+        match request_tokens(&server, uri, &provider, base).await {
+            Ok(result) => {
+                server_tokens = decode_server(&result.data, &provider);
+                server_result = Some(result);
+            }
+            Err(err) => warn!("[LSP] Semantic tokens from `{id}` failed: {err:#}"),
+        }
+        break;
+    }
+
+    let data = encode(&overlay(&server_tokens, &fallback));
+    let result_id = NEXT_RESULT_ID.fetch_add(1, Ordering::Relaxed).to_string();
+    let server = server_result.as_ref().map(|r| r.server.clone());
+    let response = match previous {
+        Some(previous) => SemanticTokens {
+            result_id: result_id.clone(),
+            data: None,
+            edits: Some(vec![diff(&previous, &data)]),
+            server,
+        },
+        None => SemanticTokens {
+            result_id: result_id.clone(),
+            data: Some(data.clone()),
+            edits: None,
+            server,
+        },
+    };
+    CACHE.lock().unwrap().insert(
+        uri.to_string(),
+        Entry {
+            version,
+            fallback,
+            server: server_result,
+            result_id,
+            data,
+        },
+    );
+    Ok(response)
+}
+
+// ----------------------------------------------------------------------------
+// Language server tokens
+// ----------------------------------------------------------------------------
+
+/// What a server's `semanticTokensProvider` offers, with its legend mapped
+/// onto the merged one.
+struct Provider {
+    delta: bool,
+    /// Merged type index by server type index; `None` for unknown types.
+    types: Vec<Option<u32>>,
+    /// Merged modifier bit by server modifier bit.
+    modifiers: Vec<Option<u32>>,
+}
+
+fn provider(server: &LspServer) -> Option<Provider> {
+    let init = server.init_result();
+    let provider = init.pointer("/capabilities/semanticTokensProvider")?;
+    let full = provider.get("full")?;
+    if full == &Value::Bool(false) {
+        return None;
+    }
+    let map = |key: &str, merged: &[&str]| -> Vec<Option<u32>> {
+        provider
+            .pointer(&format!("/legend/{key}"))
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .map(|name| {
+                let name = name.as_str()?;
+                merged.iter().position(|m| *m == name).map(|i| i as u32)
+            })
+            .collect()
+    };
+    Some(Provider {
+        delta: full.get("delta").and_then(Value::as_bool).unwrap_or(false),
+        types: map("tokenTypes", TOKEN_TYPES),
+        modifiers: map("tokenModifiers", TOKEN_MODIFIERS),
+    })
+}
+
+/// Ask `server` for the tokens of `uri`: a delta against `base` if possible,
+/// the full array otherwise (also when the delta request fails).
+async fn request_tokens(
+    server: &LspServer,
+    uri: &str,
+    provider: &Provider,
+    base: Option<&ServerResult>,
+) -> Result<ServerResult> {
+    let base = base.filter(|b| provider.delta && b.result_id.is_some());
+    if let Some(base) = base {
+        let params = json!({ "textDocument": { "uri": uri }, "previousResultId": base.result_id });
+        let result = send(server, "textDocument/semanticTokens/full/delta", params).await;
+        match result.and_then(|result| apply_server_result(server, base, result)) {
+            Ok(result) => return Ok(result),
+            Err(err) => warn!(
+                "[LSP] Semantic tokens delta from `{}` failed, requesting all: {err:#}",
+                server.config.id
+            ),
+        }
+    }
+    let params = json!({ "textDocument": { "uri": uri } });
+    let result = send(server, "textDocument/semanticTokens/full", params).await?;
+    let empty = ServerResult {
+        server: server.config.id.clone(),
+        result_id: None,
+        data: Vec::new(),
+    };
+    apply_server_result(server, &empty, result)
+}
+
+async fn send(server: &LspServer, method: &str, params: Value) -> Result<Value> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": "glass/semanticTokens",
+        "method": method,
+        "params": params,
+    });
+    let response = server
+        .request(request, server.config.request_timeout(method))
+        .await?;
+    if let Some(error) = response.get("error") {
+        return Err(anyhow!("`{method}` failed: {error}"));
+    }
+    Ok(response.get("result").cloned().unwrap_or(Value::Null))
+}
+
+/// New server state from a `SemanticTokens` or `SemanticTokensDelta` result.
+fn apply_server_result(
+    server: &LspServer,
+    base: &ServerResult,
+    result: Value,
+) -> Result<ServerResult> {
+    let result_id = result
+        .get("resultId")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let numbers = |value: &Value| -> Vec<u32> {
+        value
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|n| n.as_u64().map(|n| n as u32))
+            .collect()
+    };
+    let data = if let Some(data) = result.get("data") {
+        numbers(data)
+    } else if let Some(edits) = result.get("edits").and_then(Value::as_array) {
+        let mut edits: Vec<TokensEdit> = edits
+            .iter()
+            .map(|edit| TokensEdit {
+                start: edit.get("start").and_then(Value::as_u64).unwrap_or(0) as usize,
+                delete_count: edit.get("deleteCount").and_then(Value::as_u64).unwrap_or(0) as usize,
+                data: edit.get("data").map(numbers).unwrap_or_default(),
+            })
+            .collect();
+        edits.sort_by_key(|edit| edit.start);
+        apply_edits(&base.data, &edits)?
+    } else {
+        // `null`: no tokens.
+        Vec::new()
+    };
+    Ok(ServerResult {
+        server: server.config.id.clone(),
+        result_id,
+        data,
+    })
+}
+
+/// Apply sorted, non-overlapping edits to a token array.
+fn apply_edits(data: &[u32], edits: &[TokensEdit]) -> Result<Vec<u32>> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut cursor = 0;
+    for edit in edits {
+        let end = edit.start + edit.delete_count;
+        if edit.start < cursor || end > data.len() {
+            return Err(anyhow!("Semantic tokens edit out of range"));
+        }
+        out.extend_from_slice(&data[cursor..edit.start]);
+        out.extend_from_slice(&edit.data);
+        cursor = end;
+    }
+    out.extend_from_slice(&data[cursor..]);
+    Ok(out)
+}
+
+/// Absolute tokens from a server's relative array, in the merged legend.
+/// Tokens of types the merged legend lacks are dropped.
+fn decode_server(data: &[u32], provider: &Provider) -> Vec<Token> {
+    let mut tokens = Vec::with_capacity(data.len() / 5);
+    let (mut line, mut start) = (0, 0);
+    for chunk in data.chunks_exact(5) {
+        let [delta_line, delta_start, length, kind, modifiers] = chunk else {
+            continue;
+        };
+        line += delta_line;
+        start = if *delta_line == 0 {
+            start + delta_start
+        } else {
+            *delta_start
+        };
+        let Some(Some(kind)) = provider.types.get(*kind as usize) else {
+            continue;
+        };
+        let modifiers = (0..32)
+            .filter(|bit| modifiers & (1 << bit) != 0)
+            .filter_map(|bit| provider.modifiers.get(bit).copied().flatten())
+            .fold(0, |acc, bit| acc | (1 << bit));
+        tokens.push(Token {
+            line,
+            start,
+            length: *length,
+            kind: *kind,
+            modifiers,
+        });
+    }
+    tokens
+}
+
+// ----------------------------------------------------------------------------
+// Tree-sitter fallback
+// ----------------------------------------------------------------------------
+
+/// Highlight query of `lang`, compiled on first use.
+fn highlights(lang: Lang) -> Option<&'static Query> {
+    static QUERIES: Lazy<Vec<(Lang, Option<Query>)>> = Lazy::new(|| {
+        // The TypeScript query only holds the additions to JavaScript's, so
+        // it goes after the JavaScript (and for TSX the JSX) query, as in
+        // the grammar's own `tree-sitter.json`.
+        let javascript = tree_sitter_javascript::HIGHLIGHT_QUERY;
+        let jsx = tree_sitter_javascript::JSX_HIGHLIGHT_QUERY;
+        let typescript = tree_sitter_typescript::HIGHLIGHTS_QUERY;
+        [
+            (Lang::Rust, tree_sitter_rust::HIGHLIGHTS_QUERY.to_string()),
+            (Lang::TypeScript, [javascript, typescript].join("\n")),
+            (Lang::Tsx, [javascript, jsx, typescript].join("\n")),
+            (
+                Lang::Python,
+                tree_sitter_python::HIGHLIGHTS_QUERY.to_string(),
+            ),
+            (Lang::Go, tree_sitter_go::HIGHLIGHTS_QUERY.to_string()),
+        ]
+        .into_iter()
+        .map(|(lang, source)| {
+            let query = Query::new(&lang.grammar(), &source)
+                .map_err(|err| warn!("[LSP] Invalid highlight query for {lang:?}: {err}"))
+                .ok();
+            (lang, query)
+        })
+        .collect()
+    });
+    QUERIES
+        .iter()
+        .find(|(l, _)| *l == lang)
+        .and_then(|(_, query)| query.as_ref())
+}
+
+/// Merged type and modifiers for a highlight capture name.
+fn capture_kind(name: &str) -> Option<(u32, u32)> {
+    let (kind, modifiers): (&str, &[&str]) = match name {
+        "keyword" => ("keyword", &[]),
+        "comment.documentation" => ("comment", &["documentation"]),
+        "comment" => ("comment", &[]),
+        "string" => ("string", &[]),
+        "number" => ("number", &[]),
+        "operator" => ("operator", &[]),
+        "attribute" => ("decorator", &[]),
+        "function.macro" => ("macro", &[]),
+        "function.method" => ("method", &[]),
+        "function.builtin" => ("function", &["defaultLibrary"]),
+        "function" => ("function", &[]),
+        "type.builtin" => ("type", &["defaultLibrary"]),
+        "type" => ("type", &[]),
+        "constructor" => ("class", &[]),
+        "constant.builtin" => ("variable", &["readonly", "defaultLibrary"]),
+        "constant" => ("variable", &["readonly"]),
+        "variable.builtin" => ("variable", &["defaultLibrary"]),
+        "variable.parameter" => ("parameter", &[]),
+        "variable" => ("variable", &[]),
+        "property" => ("property", &[]),
+        _ => return None,
+    };
+    let index = |names: &[&str], name: &str| names.iter().position(|n| *n == name);
+    let kind = index(TOKEN_TYPES, kind)? as u32;
+    let modifiers = modifiers
+        .iter()
+        .filter_map(|m| index(TOKEN_MODIFIERS, m))
+        .fold(0, |acc, bit| acc | (1 << bit));
+    Some((kind, modifiers))
+}
+
+/// Tokens from the grammar's highlight query, split per line and without
+/// overlaps (the first capture of a node wins, like in tree-sitter-highlight).
+fn fallback_tokens(language: &str, text: &str) -> Vec<Token> {
+    let Some(lang) = Lang::from_language_id(language) else {
+        return Vec::new();
+    };
+    let Some(query) = highlights(lang) else {
+        return Vec::new();
+    };
+    let mut parser = Parser::new();
+    if parser.set_language(&lang.grammar()).is_err() {
+        return Vec::new();
+    }
+    let Some(tree) = parser.parse(text, None) else {
+        return Vec::new();
+    };
+    let kinds: Vec<Option<(u32, u32)>> = query
+        .capture_names()
+        .iter()
+        .map(|name| capture_kind(name))
+        .collect();
+    let lines: Vec<&str> = text.split('\n').collect();
+    let utf16 = |s: &str| s.encode_utf16().count() as u32;
+
+    let mut tokens = Vec::new();
+    let mut cursor = QueryCursor::new();
+    let mut captures = cursor.captures(query, tree.root_node(), text.as_bytes());
+    while let Some((m, index)) = captures.next() {
+        let capture = m.captures[*index];
+        let Some((kind, modifiers)) = kinds[capture.index as usize] else {
+            continue;
+        };
+        let (start, end) = (capture.node.start_position(), capture.node.end_position());
+        for row in start.row..=end.row {
+            let Some(line) = lines.get(row) else {
+                break;
+            };
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let from = if row == start.row { start.column } else { 0 };
+            let to = if row == end.row {
+                end.column
+            } else {
+                line.len()
+            };
+            let (Some(before), Some(span)) = (line.get(..from), line.get(from..to.min(line.len())))
+            else {
+                continue;
+            };
+            if !span.is_empty() {
+                tokens.push(Token {
+                    line: row as u32,
+                    start: utf16(before),
+                    length: utf16(span),
+                    kind,
+                    modifiers,
+                });
+            }
+        }
+    }
+    // Stable, so the first capture at a position stays first.
+    tokens.sort_by_key(|t| (t.line, t.start));
+    let mut kept: Vec<Token> = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        if kept.last().map_or(true, |last| !overlaps(last, &token)) {
+            kept.push(token);
+        }
+    }
+    kept
+}
+
+// ----------------------------------------------------------------------------
+// Merging and encoding
+// ----------------------------------------------------------------------------
+
+fn overlaps(a: &Token, b: &Token) -> bool {
+    a.line == b.line && a.start < b.start + b.length && b.start < a.start + a.length
+}
+
+/// `lsp` tokens plus the `fallback` tokens none of them overlaps. Both
+/// inputs are sorted and free of overlaps.
+fn overlay(lsp: &[Token], fallback: &[Token]) -> Vec<Token> {
+    let mut out = Vec::with_capacity(lsp.len() + fallback.len());
+    let mut next = 0;
+    for token in fallback {
+        while next < lsp.len() && (lsp[next].line, lsp[next].start) < (token.line, token.start) {
+            out.push(lsp[next]);
+            next += 1;
+        }
+        // Only the last token before and the first after can overlap.
+        let covered = out.last().is_some_and(|t| overlaps(t, token))
+            || lsp.get(next).is_some_and(|t| overlaps(t, token));
+        if !covered {
+            out.push(*token);
+        }
+    }
+    out.extend_from_slice(&lsp[next..]);
+    out
+}
+
+/// Relative encoding of sorted tokens.
+fn encode(tokens: &[Token]) -> Vec<u32> {
+    let mut data = Vec::with_capacity(tokens.len() * 5);
+    let (mut line, mut start) = (0, 0);
+    for token in tokens {
+        let delta_line = token.line - line;
+        let delta_start = if delta_line == 0 {
+            token.start - start
+        } else {
+            token.start
+        };
+        data.extend([
+            delta_line,
+            delta_start,
+            token.length,
+            token.kind,
+            token.modifiers,
+        ]);
+        (line, start) = (token.line, token.start);
+    }
+    data
+}
+
+/// One edit turning `old` into `new`: everything between the common leading
+/// and trailing tokens.
+fn diff(old: &[u32], new: &[u32]) -> TokensEdit {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count() / 5 * 5;
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take(old.len().min(new.len()) - prefix)
+        .take_while(|(a, b)| a == b)
+        .count()
+        / 5
+        * 5;
+    TokensEdit {
+        start: prefix,
+        delete_count: old.len() - prefix - suffix,
+        data: new[prefix..new.len() - suffix].to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(line: u32, start: u32, length: u32, kind: &str) -> Token {
+        Token {
+            line,
+            start,
+            length,
+            kind: TOKEN_TYPES.iter().position(|t| *t == kind).unwrap() as u32,
+            modifiers: 0,
+        }
+    }
+
+    #[test]
+    fn fallback_highlights_rust_and_yields_to_server_tokens() {
+        let text = "/* a\n   b */\nfn main() { let s = \"é\"; }\n";
+        let fallback = fallback_tokens("rust", text);
+        let find = |line: u32, start: u32| {
+            fallback
+                .iter()
+                .find(|t| t.line == line && t.start == start)
+                .map(|t| TOKEN_TYPES[t.kind as usize])
+        };
+        // Block comments are split per line.
+        assert_eq!(find(0, 0), Some("comment"));
+        assert_eq!(find(1, 0), Some("comment"));
+        assert_eq!(find(2, 0), Some("keyword"));
+        assert_eq!(find(2, 3), Some("function"));
+        let string = fallback
+            .iter()
+            .find(|t| TOKEN_TYPES[t.kind as usize] == "string")
+            .unwrap();
+        assert_eq!((string.start, string.length), (20, 3));
+        assert!(fallback_tokens("mock", text).is_empty());
+
+        // A server token replaces the fallback tokens it overlaps.
+        let lsp = [token(2, 3, 4, "method"), token(2, 16, 1, "variable")];
+        let merged = overlay(&lsp, &fallback);
+        assert!(merged.contains(&lsp[0]) && merged.contains(&lsp[1]));
+        assert!(!merged.iter().any(|t| *t != lsp[0] && overlaps(t, &lsp[0])));
+        assert!(merged
+            .windows(2)
+            .all(|w| (w[0].line, w[0].start) < (w[1].line, w[1].start)));
+        assert_eq!(merged.len(), fallback.len() + 1);
+    }
+
+    #[test]
+    fn fallback_highlights_typescript_with_the_javascript_query() {
+        let kind_at = |tokens: &[Token], line: u32, start: u32| {
+            tokens
+                .iter()
+                .find(|t| t.line == line && t.start == start)
+                .map(|t| TOKEN_TYPES[t.kind as usize])
+        };
+        let ts = fallback_tokens("typescript", "function add(a: number) { return \"s\"; }\n");
+        // Keywords, identifiers and strings come from the JavaScript query,
+        // builtin types from the TypeScript one.
+        assert_eq!(kind_at(&ts, 0, 0), Some("keyword"));
+        assert_eq!(kind_at(&ts, 0, 9), Some("variable"));
+        assert_eq!(kind_at(&ts, 0, 16), Some("type"));
+        assert_eq!(kind_at(&ts, 0, 33), Some("string"));
+
+        assert!(highlights(Lang::Tsx).is_some());
+        let tsx = fallback_tokens("typescriptreact", "const el = <div />;\n");
+        assert_eq!(kind_at(&tsx, 0, 0), Some("keyword"));
+    }
+
+    #[test]
+    fn deltas_round_trip() {
+        let old = encode(&[token(0, 0, 2, "keyword"), token(1, 4, 3, "variable")]);
+        let new = encode(&[
+            token(0, 0, 2, "keyword"),
+            token(1, 0, 3, "type"),
+            token(1, 4, 3, "variable"),
+        ]);
+        assert_eq!(old, [0, 0, 2, 15, 0, 1, 4, 3, 8, 0]);
+        let edit = diff(&old, &new);
+        assert_eq!((edit.start, edit.delete_count), (5, 5));
+        assert_eq!(apply_edits(&old, &[edit]).unwrap(), new);
+        assert_eq!(diff(&new, &new).data, Vec::<u32>::new());
+        let bad = TokensEdit {
+            start: 8,
+            delete_count: 5,
+            data: Vec::new(),
+        };
+        assert!(apply_edits(&old, &[bad]).is_err());
+    }
+}
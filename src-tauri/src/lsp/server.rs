@@ -0,0 +1,1214 @@
+//! Supervised language-server processes.
+//!
+//! An [`LspServer`] lives as long as its (root, server id) slot in `SERVERS`;
+//! the [`Connection`] to the actual process is replaced on every restart.
+//!
+//! The backend owns the lifecycle: it sends `initialize` (client
+//! capabilities, `rootUri`, `workspaceFolders`, registry
+//! `initializationOptions`) and `initialized` itself and keeps the server's
+//! `InitializeResult`, so callers never craft the handshake.
+//!
+//! When the process exits unexpectedly the supervisor restarts it with
+//! exponential backoff, repeats the handshake and re-opens every tracked
+//! document. More than
+//! [`MAX_CRASHES`] crashes within [`CRASH_WINDOW`] mark the server as
+//! failed until it is restarted explicitly (`restart_lsp`).
+//!
+//! State changes are emitted as `lsp_server_state` events; [`LspServer::status`]
+//! snapshots the state together with running progress tasks (see
+//! [`super::progress`]) and the health the server reports about itself.
+//! Published diagnostics are kept per server (see [`super::diagnostics`]) and
+//! dropped together with the process that published them.
+//!
+//! [`LspServer::stop`] performs the `shutdown` / `exit` handshake, killing the
+//! process if it does not exit within [`SHUTDOWN_TIMEOUT`]. Stopped servers
+//! are never restarted.
+//!
+//! Server stderr is forwarded to the app log and kept in a per-server ring
+//! buffer ([`OUTPUT_LINES`]) that survives restarts; its tail is appended to
+//! crash and startup errors so failures come with the server's own
+//! explanation.
+//!
+//! Servers configured with `replay` run a [`Script`] in-process instead of a
+//! process; the rest of the supervisor cannot tell the difference.
+
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Runtime};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader};
+use tokio::process::{Child, ChildStderr, Command};
+use tokio::sync::{oneshot, watch, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout, Duration};
+use tower_lsp::lsp_types::Url;
+
+use super::diagnostics::{Diagnostics, DiagnosticsEvent};
+use super::discovery;
+use super::documents::Documents;
+use super::framing::{self, FrameReader, FramingError};
+use super::progress::{Progress, ProgressEvent, ProgressKind, ProgressTask};
+use super::recorder::{self, Direction};
+use super::registry::ServerConfig;
+use super::replay::Script;
+use super::semantic_tokens;
+use super::workspace_edit;
+
+/// Default upper bound for a request round-trip; see
+/// [`ServerConfig::request_timeout`] for per-method limits.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// JSON-RPC error code for requests cancelled by the client.
+const REQUEST_CANCELLED: i64 = -32800;
+/// `initialize` may index the whole project before answering.
+const INITIALIZE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Time for the `shutdown` response and for the process to exit afterwards.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Crashes tolerated within [`CRASH_WINDOW`] before giving up.
+const MAX_CRASHES: usize = 5;
+const CRASH_WINDOW: Duration = Duration::from_secs(180);
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+/// Stderr lines kept per server.
+pub const OUTPUT_LINES: usize = 500;
+/// Stderr lines appended to crash / startup errors.
+const ERROR_TAIL_LINES: usize = 20;
+/// How long an exit waits for the last stderr lines to be drained.
+const STDERR_DRAIN: Duration = Duration::from_millis(500);
+/// Buffer size of the in-memory pipe to a replayed server.
+const REPLAY_BUFFER: usize = 64 * 1024;
+
+type Reader = Box<dyn AsyncRead + Send + Unpin>;
+type Writer = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// A request waiting for its response.
+struct Pending {
+    /// Id the caller used, for cancellation by the frontend.
+    client_id: Value,
+    tx: oneshot::Sender<Value>,
+}
+
+/// Connection to one process incarnation. Shared between the request path
+/// (writes + pending map) and the process' reader task (routes responses by id).
+pub struct Connection {
+    /// `root` / server id of the owning [`LspServer`], for the recorder.
+    root: String,
+    server: String,
+    /// The server's stdin.
+    writer: Mutex<Writer>,
+    /// In-flight requests keyed by the backend-assigned JSON-RPC id.
+    pending: StdMutex<HashMap<i64, Pending>>,
+    next_id: AtomicI64,
+    /// Signals the exit-watch task to kill the process.
+    kill: StdMutex<Option<oneshot::Sender<()>>>,
+    /// Set by the exit-watch task once the process is gone.
+    exited: watch::Sender<bool>,
+}
+
+impl Connection {
+    /// Kill the process (no-op if it already exited).
+    pub fn kill(&self) {
+        if let Some(tx) = self.kill.lock().unwrap().take() {
+            let _ = tx.send(());
+        }
+    }
+
+    /// Write a single message (notification or response) to the server.
+    pub async fn write(&self, msg: &Value) -> Result<()> {
+        let mut writer = self.writer.lock().await;
+        recorder::record(Direction::Send, &self.root, &self.server, msg);
+        framing::write_frame(&mut *writer, msg).await?;
+        Ok(())
+    }
+
+    /// Send `request` and await its response. Ids are rewritten to a
+    /// per-process counter so concurrent callers can never collide, and
+    /// restored on the way back. Notifications (no `id`) are written and
+    /// answered with `null`. On timeout the server is told to stop working
+    /// on the request (`$/cancelRequest`).
+    pub async fn request(&self, mut request: Value, limit: Duration) -> Result<Value> {
+        let Some(client_id) = request.get("id").cloned() else {
+            self.write(&request).await?;
+            return Ok(Value::Null);
+        };
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        request["id"] = Value::from(id);
+        let (tx, rx) = oneshot::channel();
+        let entry = Pending {
+            client_id: client_id.clone(),
+            tx,
+        };
+        self.pending.lock().unwrap().insert(id, entry);
+
+        if let Err(err) = self.write(&request).await {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(err);
+        }
+
+        let mut response = match timeout(limit, rx).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => return Err(anyhow!("Language server exited")),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                self.send_cancel(id).await;
+                return Err(anyhow!("LSP timeout after {}ms", limit.as_millis()));
+            }
+        };
+        response["id"] = client_id;
+        Ok(response)
+    }
+
+    /// Cancel in-flight requests sent with `client_id`: the server gets
+    /// `$/cancelRequest` and the callers a `RequestCancelled` error response
+    /// right away. Returns whether any request matched.
+    pub async fn cancel(&self, client_id: &Value) -> bool {
+        let cancelled: Vec<(i64, Pending)> = {
+            let mut pending = self.pending.lock().unwrap();
+            let ids: Vec<i64> = pending
+                .iter()
+                .filter(|(_, p)| p.client_id == *client_id)
+                .map(|(id, _)| *id)
+                .collect();
+            ids.into_iter()
+                .filter_map(|id| pending.remove(&id).map(|p| (id, p)))
+                .collect()
+        };
+        let any = !cancelled.is_empty();
+        for (id, entry) in cancelled {
+            self.send_cancel(id).await;
+            let _ = entry.tx.send(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": REQUEST_CANCELLED, "message": "Request cancelled" },
+            }));
+        }
+        any
+    }
+
+    async fn send_cancel(&self, id: i64) {
+        let msg = json!({ "jsonrpc": "2.0", "method": "$/cancelRequest", "params": { "id": id } });
+        if let Err(err) = self.write(&msg).await {
+            warn!("[LSP] Failed to cancel request {id}: {err:#}");
+        }
+    }
+}
+
+/// What runs behind a [`Connection`].
+enum Process {
+    Child(Child),
+    /// In-process [`Script`] replaying a recording.
+    Replay(JoinHandle<()>),
+}
+
+impl Process {
+    /// Wait for the exit, killing on `kill`; describes how it ended.
+    async fn wait(self, kill: oneshot::Receiver<()>) -> String {
+        match self {
+            Process::Child(mut child) => {
+                let status = tokio::select! {
+                    status = child.wait() => status,
+                    _ = kill => {
+                        let _ = child.start_kill();
+                        child.wait().await
+                    }
+                };
+                match status {
+                    Ok(status) => format!("exited with {status}"),
+                    Err(err) => format!("wait failed: {err}"),
+                }
+            }
+            Process::Replay(mut task) => {
+                tokio::select! {
+                    _ = &mut task => "replay finished".to_string(),
+                    _ = kill => {
+                        task.abort();
+                        "replay stopped".to_string()
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Byte streams of a freshly started server.
+struct Transport {
+    process: Process,
+    /// The server's stdout.
+    reader: Reader,
+    /// The server's stdin.
+    writer: Writer,
+    stderr: Option<ChildStderr>,
+}
+
+impl Transport {
+    /// Spawn the configured command in `root`, located by
+    /// [`discovery::resolve_command`].
+    fn process(config: &ServerConfig, root: &str) -> Result<Self> {
+        let cmd = &config.command;
+        let (program, _) = discovery::resolve_command(config, Path::new(root))?;
+        let mut child = Command::new(program)
+            .args(&config.args)
+            .envs(&config.env)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to spawn language server `{cmd}`"))?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open stdin for language server"))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open stdout for language server"))?;
+        let stderr = child
+            .stderr
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open stderr for language server"))?;
+        Ok(Transport {
+            process: Process::Child(child),
+            reader: Box::new(stdout),
+            writer: Box::new(stdin),
+            stderr: Some(stderr),
+        })
+    }
+
+    /// Start a replay of the `server` messages in `recording`.
+    fn replay(recording: &Path, server: &str) -> Result<Self> {
+        let script = Script::load(recording, server)?;
+        let (client, replay) = tokio::io::duplex(REPLAY_BUFFER);
+        let (replay_reader, replay_writer) = tokio::io::split(replay);
+        let task = tokio::spawn(script.serve(replay_reader, replay_writer));
+        let (reader, writer) = tokio::io::split(client);
+        Ok(Transport {
+            process: Process::Replay(task),
+            reader: Box::new(reader),
+            writer: Box::new(writer),
+            stderr: None,
+        })
+    }
+}
+
+/// Lifecycle state reported to the UI.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum ServerState {
+    Starting,
+    Running,
+    Restarting,
+    Failed,
+    Stopped,
+}
+
+/// Payload of the `lsp_server_state` event.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStateEvent {
+    pub root: String,
+    pub server: String,
+    pub state: ServerState,
+    /// Automatic restarts since the server was (re)started explicitly.
+    pub restarts: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Health a server reports about itself – rust-analyzer's
+/// `experimental/serverStatus`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportedStatus {
+    /// `ok`, `warning` or `error`
+    pub health: String,
+    /// Whether the server finished all pending work
+    pub quiescent: bool,
+    pub message: Option<String>,
+}
+
+/// Status snapshot of a server, as returned by `lsp_status`.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStatus {
+    pub root: String,
+    pub server: String,
+    pub state: ServerState,
+    pub restarts: usize,
+    /// Exit status and stderr tail of the last crash
+    pub last_exit: Option<String>,
+    pub reported: Option<ReportedStatus>,
+    /// Running work-done progress tasks, oldest first
+    pub progress: Vec<ProgressTask>,
+}
+
+/// `TextDocumentSyncKind`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SyncKind {
+    None,
+    Full,
+    Incremental,
+}
+
+impl SyncKind {
+    fn from_lsp(kind: Option<u64>) -> Self {
+        match kind {
+            Some(1) => SyncKind::Full,
+            Some(2) => SyncKind::Incremental,
+            _ => SyncKind::None,
+        }
+    }
+}
+
+/// Which sync notifications a server wants.
+pub struct SyncOptions {
+    pub open_close: bool,
+    pub change: SyncKind,
+    /// `Some(include_text)` if the server wants `didSave`.
+    pub save: Option<bool>,
+}
+
+/// A supervised language server for one (root, server id) pair.
+pub struct LspServer {
+    pub root: String,
+    pub config: ServerConfig,
+    connection: StdMutex<Option<Arc<Connection>>>,
+    state: watch::Sender<ServerState>,
+    /// `InitializeResult` (`capabilities`, `serverInfo`) of the current process.
+    init_result: StdMutex<Value>,
+    /// Open documents, re-opened after a restart.
+    documents: StdMutex<Documents>,
+    /// Crash timestamps within the current window.
+    crashes: StdMutex<Vec<Instant>>,
+    restarts: StdMutex<usize>,
+    /// Recent stderr lines across all incarnations.
+    output: StdMutex<VecDeque<String>>,
+    /// Exit status and stderr tail of the last crash.
+    last_exit: StdMutex<Option<String>>,
+    /// Work-done progress of the current process.
+    progress: StdMutex<Progress>,
+    reported: StdMutex<Option<ReportedStatus>>,
+    diagnostics: StdMutex<Diagnostics>,
+}
+
+impl LspServer {
+    /// Spawn the server process in `Starting` state; [`LspServer::initialize`]
+    /// must be called next. Fails (without retrying) if the command cannot be
+    /// started at all, e.g. because the binary is missing.
+    pub fn spawn_new<R: Runtime>(
+        app: &AppHandle<R>,
+        root: &str,
+        config: &ServerConfig,
+    ) -> Result<Arc<Self>> {
+        if !Path::new(root).exists() {
+            return Err(anyhow!("Workspace root does not exist"));
+        }
+        let server = Arc::new(LspServer {
+            root: root.to_string(),
+            config: config.clone(),
+            connection: StdMutex::new(None),
+            state: watch::Sender::new(ServerState::Starting),
+            init_result: StdMutex::new(Value::Null),
+            documents: StdMutex::new(Documents::default()),
+            crashes: StdMutex::new(Vec::new()),
+            restarts: StdMutex::new(0),
+            output: StdMutex::new(VecDeque::new()),
+            last_exit: StdMutex::new(None),
+            progress: StdMutex::new(Progress::default()),
+            reported: StdMutex::new(None),
+            diagnostics: StdMutex::new(Diagnostics::default()),
+        });
+        server.set_state(app, ServerState::Starting, None);
+        server.spawn(app)?;
+        Ok(server)
+    }
+
+    /// Run the handshake on the freshly spawned process and mark it running.
+    /// On failure the process is killed, which hands it to the supervisor.
+    pub async fn initialize<R: Runtime>(&self, app: &AppHandle<R>) -> Result<()> {
+        let conn = self
+            .connection
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| anyhow!("`{}` is not running", self.config.id))?;
+        if let Err(err) = self.handshake(&conn).await {
+            conn.kill();
+            return Err(self.with_output(err));
+        }
+        self.set_state(app, ServerState::Running, None);
+        Ok(())
+    }
+
+    /// Connection of the running process. Waits while the server is still
+    /// initializing; fails while restarting or after the crash-loop cap.
+    pub async fn connection(&self) -> Result<Arc<Connection>> {
+        let mut rx = self.state.subscribe();
+        let state = timeout(
+            INITIALIZE_TIMEOUT,
+            rx.wait_for(|s| *s != ServerState::Starting),
+        )
+        .await
+        .map_err(|_| anyhow!("`{}` did not finish initializing", self.config.id))?
+        .map(|s| *s)
+        .map_err(|_| anyhow!("`{}` was dropped", self.config.id))?;
+
+        let last_exit = || {
+            self.last_exit
+                .lock()
+                .unwrap()
+                .as_deref()
+                .map(|exit| format!(" – last exit: {exit}"))
+                .unwrap_or_default()
+        };
+        match state {
+            ServerState::Stopped => Err(anyhow!("`{}` was stopped", self.config.id)),
+            ServerState::Failed => Err(anyhow!(
+                "`{}` crashed repeatedly and was not restarted{}",
+                self.config.id,
+                last_exit()
+            )),
+            _ => self
+                .connection
+                .lock()
+                .unwrap()
+                .clone()
+                .filter(|_| state == ServerState::Running)
+                .ok_or_else(|| anyhow!("`{}` is restarting{}", self.config.id, last_exit())),
+        }
+    }
+
+    /// The last `lines` lines the server wrote to stderr, oldest first.
+    pub fn output(&self, lines: usize) -> Vec<String> {
+        let output = self.output.lock().unwrap();
+        let skip = output.len().saturating_sub(lines);
+        output.iter().skip(skip).cloned().collect()
+    }
+
+    /// Recent stderr as an indented block, empty if there is none.
+    fn output_tail(&self) -> String {
+        let tail = self.output(ERROR_TAIL_LINES);
+        if tail.is_empty() {
+            return String::new();
+        }
+        format!("\nRecent server output:\n  {}", tail.join("\n  "))
+    }
+
+    fn with_output(&self, err: anyhow::Error) -> anyhow::Error {
+        anyhow!("{err:#}{}", self.output_tail())
+    }
+
+    /// Current lifecycle state.
+    pub fn state(&self) -> ServerState {
+        *self.state.borrow()
+    }
+
+    /// Snapshot of state, reported health and running progress tasks.
+    pub fn status(&self) -> ServerStatus {
+        ServerStatus {
+            root: self.root.clone(),
+            server: self.config.id.clone(),
+            state: self.state(),
+            restarts: *self.restarts.lock().unwrap(),
+            last_exit: self.last_exit.lock().unwrap().clone(),
+            reported: self.reported.lock().unwrap().clone(),
+            progress: self.progress.lock().unwrap().tasks(),
+        }
+    }
+
+    /// Track a `$/progress` notification and emit it as `lsp_progress`.
+    /// Returns `false` if it is not work-done progress of a known task (e.g.
+    /// partial results), which the caller emits as a plain notification.
+    fn on_progress<R: Runtime>(&self, app: &AppHandle<R>, params: &Value) -> bool {
+        let update = self.progress.lock().unwrap().update(params);
+        let Some((kind, task)) = update else {
+            return false;
+        };
+        self.emit_progress(app, kind, task);
+        true
+    }
+
+    /// Track the token of a `window/workDoneProgress/create` request and
+    /// emit it as a `create` `lsp_progress` event.
+    fn on_progress_create<R: Runtime>(&self, app: &AppHandle<R>, params: &Value) {
+        let task = self.progress.lock().unwrap().create(params);
+        if let Some(task) = task {
+            self.emit_progress(app, ProgressKind::Create, task);
+        }
+    }
+
+    /// End the progress tasks and reported status of a process that is gone.
+    fn reset_progress<R: Runtime>(&self, app: &AppHandle<R>) {
+        *self.reported.lock().unwrap() = None;
+        let tasks = self.progress.lock().unwrap().end_all();
+        for task in tasks {
+            self.emit_progress(app, ProgressKind::End, task);
+        }
+    }
+
+    fn emit_progress<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        kind: ProgressKind,
+        task: ProgressTask,
+    ) {
+        let _ = app.emit(
+            "lsp_progress",
+            ProgressEvent {
+                root: self.root.clone(),
+                server: self.config.id.clone(),
+                kind,
+                task,
+            },
+        );
+    }
+
+    /// Diagnostics the running process published, by URI.
+    pub fn diagnostics(&self) -> std::sync::MutexGuard<'_, Diagnostics> {
+        self.diagnostics.lock().unwrap()
+    }
+
+    /// Store a `textDocument/publishDiagnostics` notification and emit it as
+    /// `lsp_diagnostics`.
+    fn on_diagnostics<R: Runtime>(&self, app: &AppHandle<R>, params: &Value) {
+        if self.diagnostics().publish(params).is_some() {
+            self.emit_diagnostics(app, params.clone());
+        }
+    }
+
+    /// Drop the diagnostics of a process that is gone, clearing them in the UI.
+    fn clear_diagnostics<R: Runtime>(&self, app: &AppHandle<R>) {
+        let uris = self.diagnostics().clear();
+        for uri in uris {
+            self.emit_diagnostics(app, json!({ "uri": uri, "diagnostics": [] }));
+        }
+    }
+
+    fn emit_diagnostics<R: Runtime>(&self, app: &AppHandle<R>, params: Value) {
+        let _ = app.emit(
+            "lsp_diagnostics",
+            DiagnosticsEvent {
+                root: &self.root,
+                server: &self.config.id,
+                params,
+            },
+        );
+    }
+
+    /// `InitializeResult` of the running process (`null` until initialized).
+    pub fn init_result(&self) -> Value {
+        self.init_result.lock().unwrap().clone()
+    }
+
+    /// Cancel in-flight requests sent with `client_id` (see [`Connection::cancel`]).
+    pub async fn cancel(&self, client_id: &Value) -> bool {
+        let conn = self.connection.lock().unwrap().clone();
+        match conn {
+            Some(conn) => conn.cancel(client_id).await,
+            None => false,
+        }
+    }
+
+    /// Forward a client message. The backend owns the handshake: `initialize`
+    /// is answered with the stored result and `initialized` is swallowed.
+    /// Document sync notifications are tracked for replay after restarts –
+    /// even while the server is restarting, so the replay is never stale.
+    pub async fn request(&self, request: Value, limit: Duration) -> Result<Value> {
+        let method = request.get("method").and_then(Value::as_str).unwrap_or("");
+        match method {
+            "initialize" => {
+                self.connection().await?;
+                return Ok(json!({
+                    "jsonrpc": "2.0",
+                    "id": request.get("id").cloned().unwrap_or(Value::Null),
+                    "result": self.init_result(),
+                }));
+            }
+            "initialized" => return Ok(Value::Null),
+            _ => {}
+        }
+        let conn = self.connection().await;
+        let params = request.get("params").unwrap_or(&Value::Null);
+        self.documents.lock().unwrap().track(method, params);
+        conn?.request(request, limit).await
+    }
+
+    /// Send a notification through [`LspServer::request`].
+    pub async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        let msg = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        self.request(msg, REQUEST_TIMEOUT).await.map(|_| ())
+    }
+
+    /// Document sync options from the server's `textDocumentSync` capability.
+    pub fn sync_options(&self) -> SyncOptions {
+        let result = self.init_result.lock().unwrap();
+        match result.pointer("/capabilities/textDocumentSync") {
+            Some(Value::Number(kind)) => SyncOptions {
+                open_close: true,
+                change: SyncKind::from_lsp(kind.as_u64()),
+                save: Some(false),
+            },
+            Some(Value::Object(opts)) => SyncOptions {
+                open_close: opts
+                    .get("openClose")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false),
+                change: SyncKind::from_lsp(opts.get("change").and_then(Value::as_u64)),
+                save: match opts.get("save") {
+                    Some(Value::Bool(true)) => Some(false),
+                    Some(Value::Object(save)) => Some(
+                        save.get("includeText")
+                            .and_then(Value::as_bool)
+                            .unwrap_or(false),
+                    ),
+                    _ => None,
+                },
+            },
+            // Capability missing – assume full sync so the server is never stale.
+            _ => SyncOptions {
+                open_close: true,
+                change: SyncKind::Full,
+                save: None,
+            },
+        }
+    }
+
+    /// Publish a state change. `Stopped` is final – late transitions from an
+    /// in-flight handshake or restart are dropped.
+    fn set_state<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        state: ServerState,
+        message: Option<String>,
+    ) {
+        let stopped = !self.state.send_if_modified(|current| {
+            if *current == ServerState::Stopped {
+                return false;
+            }
+            *current = state;
+            true
+        });
+        if stopped {
+            return;
+        }
+        let _ = app.emit(
+            "lsp_server_state",
+            ServerStateEvent {
+                root: self.root.clone(),
+                server: self.config.id.clone(),
+                state,
+                restarts: *self.restarts.lock().unwrap(),
+                message,
+            },
+        );
+    }
+
+    /// Start a new process incarnation plus its reader and exit-watch tasks.
+    fn spawn<R: Runtime>(self: &Arc<Self>, app: &AppHandle<R>) -> Result<Arc<Connection>> {
+        let Transport {
+            process,
+            reader,
+            writer,
+            stderr,
+        } = match &self.config.replay {
+            Some(recording) => {
+                Transport::replay(&Path::new(&self.root).join(recording), &self.config.id)?
+            }
+            None => Transport::process(&self.config, &self.root)?,
+        };
+
+        let (kill_tx, kill_rx) = oneshot::channel();
+        let conn = Arc::new(Connection {
+            root: self.root.clone(),
+            server: self.config.id.clone(),
+            writer: Mutex::new(writer),
+            pending: StdMutex::new(HashMap::new()),
+            next_id: AtomicI64::new(1),
+            kill: StdMutex::new(Some(kill_tx)),
+            exited: watch::Sender::new(false),
+        });
+        *self.connection.lock().unwrap() = Some(conn.clone());
+
+        tokio::spawn(read_loop(
+            app.clone(),
+            self.clone(),
+            conn.clone(),
+            FrameReader::new(reader),
+        ));
+        let stderr_task =
+            stderr.map(|stderr| tokio::spawn(stderr_loop(self.clone(), BufReader::new(stderr))));
+
+        let server = self.clone();
+        let app = app.clone();
+        let watched = conn.clone();
+        tokio::spawn(async move {
+            let message = process.wait(kill_rx).await;
+            // Let the reader pick up the final lines, usually the reason for the exit.
+            if let Some(stderr_task) = stderr_task {
+                let _ = timeout(STDERR_DRAIN, stderr_task).await;
+            }
+            watched.exited.send_replace(true);
+            server.on_exit(app, watched, message).await;
+        });
+        Ok(conn)
+    }
+
+    /// Exit-watch handler: restart with backoff unless the crash-loop cap is hit.
+    async fn on_exit<R: Runtime>(
+        self: Arc<Self>,
+        app: AppHandle<R>,
+        conn: Arc<Connection>,
+        message: String,
+    ) {
+        {
+            let mut current = self.connection.lock().unwrap();
+            // Ignore exits of incarnations that were already replaced.
+            if !current.as_ref().is_some_and(|c| Arc::ptr_eq(c, &conn)) {
+                return;
+            }
+            *current = None;
+        }
+        self.reset_progress(&app);
+        self.clear_diagnostics(&app);
+        let message = format!("{message}{}", self.output_tail());
+        warn!("[LSP] `{}` for {} {message}", self.config.id, self.root);
+        *self.last_exit.lock().unwrap() = Some(message.clone());
+
+        loop {
+            let crashes = {
+                let mut crashes = self.crashes.lock().unwrap();
+                let now = Instant::now();
+                crashes.retain(|t| now.duration_since(*t) < CRASH_WINDOW);
+                crashes.push(now);
+                crashes.len()
+            };
+            if crashes > MAX_CRASHES {
+                self.set_state(
+                    &app,
+                    ServerState::Failed,
+                    Some(format!("crashed {crashes} times, giving up ({message})")),
+                );
+                return;
+            }
+
+            let delay = BACKOFF_BASE
+                .saturating_mul(1 << (crashes - 1).min(16))
+                .min(BACKOFF_MAX);
+            self.set_state(&app, ServerState::Restarting, Some(message.clone()));
+            sleep(delay).await;
+            if *self.state.borrow() == ServerState::Stopped {
+                return;
+            }
+
+            *self.restarts.lock().unwrap() += 1;
+            let conn = match self.spawn(&app) {
+                Ok(conn) => conn,
+                Err(err) => {
+                    warn!("[LSP] Restart of `{}` failed: {err:#}", self.config.id);
+                    continue;
+                }
+            };
+            match self.handshake(&conn).await {
+                Ok(()) => {
+                    info!("[LSP] Restarted `{}` for {}", self.config.id, self.root);
+                    self.set_state(&app, ServerState::Running, None);
+                }
+                Err(err) => {
+                    // Killing hands the new incarnation back to `on_exit`.
+                    warn!("[LSP] Handshake with `{}` failed: {err:#}", self.config.id);
+                    conn.kill();
+                }
+            }
+            return;
+        }
+    }
+
+    /// `initialize` / `initialized` on a fresh process, then re-open tracked documents.
+    async fn handshake(&self, conn: &Connection) -> Result<()> {
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "initialize",
+            "params": self.initialize_params(),
+        });
+        let response = conn.request(initialize, INITIALIZE_TIMEOUT).await?;
+        if let Some(error) = response.get("error") {
+            return Err(anyhow!("initialize failed: {error}"));
+        }
+        *self.init_result.lock().unwrap() = response.get("result").cloned().unwrap_or(Value::Null);
+
+        conn.write(&json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }))
+            .await?;
+        let reopen = self.documents.lock().unwrap().did_open_notifications();
+        for notification in reopen {
+            conn.write(&notification).await?;
+        }
+        Ok(())
+    }
+
+    fn initialize_params(&self) -> Value {
+        let root_uri = Url::from_directory_path(&self.root).ok();
+        let name = Path::new(&self.root)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut params = json!({
+            "processId": std::process::id(),
+            "clientInfo": { "name": "Glass IDE", "version": env!("CARGO_PKG_VERSION") },
+            "rootPath": self.root,
+            "rootUri": root_uri,
+            "workspaceFolders": [{ "uri": root_uri, "name": name }],
+            "capabilities": client_capabilities(),
+        });
+        if let Some(options) = &self.config.initialization_options {
+            params["initializationOptions"] = options.clone();
+        }
+        params
+    }
+
+    /// Gracefully stop the server: `shutdown` request, `exit` notification,
+    /// then kill if the process is still alive after [`SHUTDOWN_TIMEOUT`].
+    pub async fn stop<R: Runtime>(&self, app: &AppHandle<R>) {
+        self.set_state(app, ServerState::Stopped, None);
+        // Detached first, so the exit is not treated as a crash.
+        let Some(conn) = self.connection.lock().unwrap().take() else {
+            return;
+        };
+        let shutdown = json!({ "jsonrpc": "2.0", "id": 0, "method": "shutdown" });
+        match conn.request(shutdown, SHUTDOWN_TIMEOUT).await {
+            Ok(_) => {
+                let exit = json!({ "jsonrpc": "2.0", "method": "exit" });
+                let _ = conn.write(&exit).await;
+            }
+            Err(err) => warn!("[LSP] `{}` ignored shutdown: {err:#}", self.config.id),
+        }
+        let mut exited = conn.exited.subscribe();
+        if timeout(SHUTDOWN_TIMEOUT, exited.wait_for(|e| *e))
+            .await
+            .is_err()
+        {
+            warn!("[LSP] `{}` did not exit, killing it", self.config.id);
+            conn.kill();
+        }
+        self.reset_progress(app);
+        self.clear_diagnostics(app);
+        info!("[LSP] Stopped `{}` for {}", self.config.id, self.root);
+    }
+
+    /// Restart explicitly: clears the crash history and kills the current process.
+    pub async fn restart<R: Runtime>(self: &Arc<Self>, app: &AppHandle<R>) -> Result<()> {
+        self.crashes.lock().unwrap().clear();
+        *self.restarts.lock().unwrap() = 0;
+        // Detach the old incarnation first so its exit is not counted as a crash.
+        if let Some(old) = self.connection.lock().unwrap().take() {
+            old.kill();
+        }
+        self.reset_progress(app);
+        self.clear_diagnostics(app);
+        self.set_state(app, ServerState::Starting, None);
+        self.spawn(app)?;
+        self.initialize(app).await
+    }
+}
+
+/// Capabilities advertised by the IDE in `initialize`.
+fn client_capabilities() -> Value {
+    json!({
+        "general": { "positionEncodings": ["utf-16"] },
+        "workspace": {
+            "applyEdit": true,
+            "workspaceEdit": { "documentChanges": true },
+            "configuration": true,
+            "workspaceFolders": true,
+            "symbol": { "dynamicRegistration": false },
+            "didChangeConfiguration": { "dynamicRegistration": false },
+            "semanticTokens": { "refreshSupport": true },
+        },
+        "textDocument": {
+            "synchronization": {
+                "dynamicRegistration": false,
+                "didSave": true,
+                "willSave": false,
+                "willSaveWaitUntil": false,
+            },
+            "completion": {
+                "completionItem": {
+                    "snippetSupport": false,
+                    "documentationFormat": ["markdown", "plaintext"],
+                },
+                "contextSupport": true,
+            },
+            "hover": { "contentFormat": ["markdown", "plaintext"] },
+            "signatureHelp": {
+                "signatureInformation": { "documentationFormat": ["markdown", "plaintext"] },
+            },
+            "definition": { "linkSupport": true },
+            "typeDefinition": { "linkSupport": true },
+            "implementation": { "linkSupport": true },
+            "references": {},
+            "documentHighlight": {},
+            "documentSymbol": { "hierarchicalDocumentSymbolSupport": true },
+            "codeAction": {
+                "codeActionLiteralSupport": {
+                    "codeActionKind": {
+                        "valueSet": ["", "quickfix", "refactor", "refactor.extract",
+                                     "refactor.inline", "refactor.rewrite", "source",
+                                     "source.organizeImports"],
+                    },
+                },
+            },
+            "formatting": {},
+            "rangeFormatting": {},
+            "rename": { "prepareSupport": true },
+            "semanticTokens": {
+                "requests": { "full": { "delta": true } },
+                "tokenTypes": semantic_tokens::TOKEN_TYPES,
+                "tokenModifiers": semantic_tokens::TOKEN_MODIFIERS,
+                "formats": ["relative"],
+                "overlappingTokenSupport": false,
+                "multilineTokenSupport": false,
+            },
+            "publishDiagnostics": { "relatedInformation": true, "versionSupport": true },
+        },
+        "experimental": { "serverStatusNotification": true },
+        "window": {
+            "workDoneProgress": true,
+            "showMessage": {},
+            "showDocument": { "support": false },
+        },
+    })
+}
+
+/// Reader task: one per process. Routes responses to their waiting request and
+/// server-initiated messages to [`handle_server_message`]; exits when stdout
+/// closes, failing all pending requests.
+async fn read_loop<R: Runtime>(
+    app: AppHandle<R>,
+    server: Arc<LspServer>,
+    conn: Arc<Connection>,
+    mut stdout: FrameReader<Reader>,
+) {
+    loop {
+        let msg = match stdout.next().await {
+            Ok(msg) => msg,
+            Err(FramingError::Closed) => break,
+            Err(err) if err.is_fatal() => {
+                warn!("[LSP] `{}` reader stopped: {err}", server.config.id);
+                break;
+            }
+            Err(err) => {
+                warn!("[LSP] `{}` sent a malformed frame: {err}", server.config.id);
+                continue;
+            }
+        };
+        recorder::record(Direction::Receive, &server.root, &server.config.id, &msg);
+
+        // Server → client notification / request
+        if msg.get("method").is_some() {
+            handle_server_message(&app, &server, &conn, msg);
+            continue;
+        }
+
+        // Response – hand it to whoever is waiting for this id
+        let Some(id) = msg.get("id").and_then(Value::as_i64) else {
+            continue;
+        };
+        let waiter = conn.pending.lock().unwrap().remove(&id);
+        if let Some(waiter) = waiter {
+            let _ = waiter.tx.send(msg);
+        }
+    }
+    // Dropping the senders wakes every waiter with an error.
+    conn.pending.lock().unwrap().clear();
+}
+
+/// Per-incarnation stderr reader: every line goes to the app log and the
+/// server's ring buffer. Exits at EOF, i.e. when the process is gone.
+async fn stderr_loop(server: Arc<LspServer>, mut stderr: BufReader<ChildStderr>) {
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match stderr.read_until(b'\n', &mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let line = String::from_utf8_lossy(&buf).trim_end().to_string();
+        if line.is_empty() {
+            continue;
+        }
+        info!("[LSP {}] {line}", server.config.id);
+        let mut output = server.output.lock().unwrap();
+        if output.len() == OUTPUT_LINES {
+            output.pop_front();
+        }
+        output.push_back(line);
+    }
+}
+
+/// Server-initiated message as emitted to the frontend. `id` is only present
+/// for requests, which must be answered through `respond_lsp_request`.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct LspServerMessage {
+    root: String,
+    server: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<Value>,
+    method: String,
+    params: Value,
+}
+
+/// Dispatch a server → client message.
+///
+/// Notifications are emitted as `lsp_notification` (diagnostics additionally
+/// as `lsp_diagnostics`), except work-done `$/progress`, which becomes
+/// `lsp_progress`.
+/// Requests the backend can answer on its own are answered immediately
+/// (`workspace/applyEdit` by applying the edit, `workspace/semanticTokens/refresh`
+/// by also emitting it as a notification); everything else (e.g.
+/// `window/showMessageRequest`) is emitted as `lsp_request`.
+fn handle_server_message<R: Runtime>(
+    app: &AppHandle<R>,
+    server: &LspServer,
+    conn: &Arc<Connection>,
+    msg: Value,
+) {
+    let method = msg
+        .get("method")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let params = msg.get("params").cloned().unwrap_or(Value::Null);
+    let id = msg.get("id").cloned();
+
+    let Some(id) = id else {
+        match method.as_str() {
+            "textDocument/publishDiagnostics" => server.on_diagnostics(app, &params),
+            // Typed `lsp_progress` events instead of raw notifications.
+            "$/progress" if server.on_progress(app, &params) => return,
+            "experimental/serverStatus" => {
+                *server.reported.lock().unwrap() = serde_json::from_value(params.clone()).ok();
+            }
+            _ => {}
+        }
+        let _ = app.emit(
+            "lsp_notification",
+            LspServerMessage {
+                root: server.root.clone(),
+                server: server.config.id.clone(),
+                id: None,
+                method,
+                params,
+            },
+        );
+        return;
+    };
+
+    if method == "workspace/applyEdit" {
+        let app = app.clone();
+        let conn = conn.clone();
+        let roots = vec![PathBuf::from(&server.root)];
+        tokio::spawn(async move {
+            let edit = params.get("edit").cloned().unwrap_or(Value::Null);
+            let label = params
+                .get("label")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            let result = tokio::task::spawn_blocking(move || {
+                workspace_edit::apply(&app, &roots, &edit, label.as_deref())
+            })
+            .await;
+            let result = match result {
+                Ok(result) => json!(result),
+                Err(err) => json!({ "applied": false, "failureReason": err.to_string() }),
+            };
+            let response = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+            if let Err(err) = conn.write(&response).await {
+                warn!("[LSP] Failed to answer `{method}`: {err:#}");
+            }
+        });
+        return;
+    }
+
+    let result = match method.as_str() {
+        // Answered from the registry `settings` (`null` = server defaults).
+        "workspace/configuration" => {
+            let items = params.get("items").and_then(Value::as_array);
+            Some(Value::Array(
+                items
+                    .into_iter()
+                    .flatten()
+                    .map(|item| {
+                        server
+                            .config
+                            .setting(item.get("section").and_then(Value::as_str))
+                    })
+                    .collect(),
+            ))
+        }
+        "workspace/workspaceFolders" => Url::from_directory_path(&server.root).ok().map(|uri| {
+            let name = Path::new(&server.root)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            json!([{ "uri": uri, "name": name }])
+        }),
+        "client/registerCapability" | "client/unregisterCapability" => Some(Value::Null),
+        // Tracked as pending so the status shows the task before `begin`.
+        "window/workDoneProgress/create" => {
+            server.on_progress_create(app, &params);
+            Some(Value::Null)
+        }
+        // The editor re-requests `lsp_semantic_tokens` on the notification.
+        "workspace/semanticTokens/refresh" => {
+            let _ = app.emit(
+                "lsp_notification",
+                LspServerMessage {
+                    root: server.root.clone(),
+                    server: server.config.id.clone(),
+                    id: None,
+                    method: method.clone(),
+                    params: Value::Null,
+                },
+            );
+            Some(Value::Null)
+        }
+        _ => None,
+    };
+
+    match result {
+        Some(result) => {
+            let response = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+            let conn = conn.clone();
+            // Write from a separate task so the reader never blocks on stdin.
+            tokio::spawn(async move {
+                if let Err(err) = conn.write(&response).await {
+                    warn!("[LSP] Failed to answer `{method}`: {err:#}");
+                }
+            });
+        }
+        None => {
+            let _ = app.emit(
+                "lsp_request",
+                LspServerMessage {
+                    root: server.root.clone(),
+                    server: server.config.id.clone(),
+                    id: Some(id),
+                    method,
+                    params,
+                },
+            );
+        }
+    }
+}
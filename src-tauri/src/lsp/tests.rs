@@ -0,0 +1,809 @@
+//! Bridge tests against `tools/mock-lsp`, a scriptable stdio language server.
+//!
+//! Each test gets its own workspace root with a `.glass/lsp.json` that routes
+//! the `mock` language (`*.mock` files) to the mock server, running the given
+//! script. The server appends every message it receives to `received.jsonl`
+//! in the root, and emitted events are collected from a mock Tauri app.
+
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tauri::test::{mock_app, MockRuntime};
+use tauri::{App, AppHandle};
+
+use super::*;
+use crate::test_support::{self, wait_until, Events};
+
+/// Path of the mock server binary, built on first use. `MOCK_LSP` overrides
+/// it for runs without cargo.
+fn mock_lsp() -> &'static Path {
+    static PATH: Lazy<PathBuf> = Lazy::new(|| test_support::tool_binary("mock-lsp", "MOCK_LSP"));
+    &PATH
+}
+
+struct Fixture {
+    _app: App<MockRuntime>,
+    app: AppHandle<MockRuntime>,
+    root: String,
+    events: Events,
+}
+
+impl Fixture {
+    fn new(name: &str, script: Value) -> Self {
+        let dir = test_support::temp_root("lsp", name);
+        std::fs::create_dir_all(dir.join(".glass")).unwrap();
+        let script_path = test_support::write_script(&dir, script);
+        let config = json!({
+            "servers": {
+                "mock": {
+                    "command": mock_lsp(),
+                    "args": [script_path],
+                    "languages": ["mock"],
+                    "filePatterns": ["*.mock"],
+                    "timeouts": { "default": 2000 },
+                },
+            },
+        });
+        std::fs::write(registry::workspace_config_path(&dir), config.to_string()).unwrap();
+
+        let mock = mock_app();
+        let app = mock.handle().clone();
+        let events = Events::listen(
+            &app,
+            &[
+                "lsp_server_state",
+                "lsp_notification",
+                "lsp_diagnostics",
+                "lsp_progress",
+                "lsp_document_moved",
+            ],
+        );
+        Fixture {
+            _app: mock,
+            app,
+            root: dir.to_string_lossy().into_owned(),
+            events,
+        }
+    }
+
+    fn uri(&self, file: &str) -> String {
+        Url::from_file_path(Path::new(&self.root).join(file))
+            .unwrap()
+            .to_string()
+    }
+
+    fn config(&self) -> ServerConfig {
+        registry::resolve(Path::new(&self.root), None, Some("mock")).unwrap()
+    }
+
+    async fn invoke(&self, request: Value) -> tauri::Result<Value> {
+        let payload = LspInvokeRequest {
+            root: self.root.clone(),
+            request,
+            language: Some("mock".into()),
+            timeout_ms: None,
+        };
+        invoke_lsp(self.app.clone(), payload)
+            .await
+            .map(|r| r.response)
+    }
+
+    /// Methods the server received, in order.
+    fn received(&self) -> Vec<String> {
+        test_support::received(Path::new(&self.root))
+            .iter()
+            .filter_map(|msg| msg["method"].as_str().map(str::to_string))
+            .collect()
+    }
+
+    fn count_received(&self, method: &str) -> usize {
+        self.received().iter().filter(|m| *m == method).count()
+    }
+
+    /// Wait until the server received `method` `count` times.
+    async fn expect_received(&self, method: &str, count: usize) {
+        let received = wait_until(|| (self.count_received(method) == count).then_some(())).await;
+        assert!(
+            received.is_some(),
+            "expected {count} × `{method}`, got {:?}",
+            self.received()
+        );
+    }
+
+    /// Wait until an event `name` matching `pred` was emitted.
+    async fn event(&self, name: &str, pred: impl Fn(&Value) -> bool) -> Value {
+        self.events.wait_for(name, pred).await
+    }
+
+    async fn stop(self) {
+        stop_servers(&self.app, |(root, _)| *root == self.root).await;
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+fn request(id: i64, method: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": {} })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ensure_server_starts_and_initializes_once() {
+    let fx = Fixture::new(
+        "ensure",
+        json!({ "capabilities": { "hoverProvider": true, "textDocumentSync": 2 } }),
+    );
+    let config = fx.config();
+    let (a, b) = tokio::join!(
+        ensure_server(&fx.app, &fx.root, &config),
+        ensure_server(&fx.app, &fx.root, &config),
+    );
+    let (a, b) = (a.unwrap(), b.unwrap());
+    assert!(Arc::ptr_eq(&a, &b));
+    assert_eq!(a.init_result()["capabilities"]["hoverProvider"], true);
+    assert_eq!(a.sync_options().change, SyncKind::Incremental);
+
+    // `initialize` from the frontend is answered from the stored result.
+    let response = fx
+        .invoke(json!({ "jsonrpc": "2.0", "id": 7, "method": "initialize", "params": {} }))
+        .await
+        .unwrap();
+    assert_eq!(response["id"], 7);
+    assert_eq!(response["result"]["serverInfo"]["name"], "mock-lsp");
+    fx.expect_received("initialize", 1).await;
+    fx.expect_received("initialized", 1).await;
+
+    fx.event("lsp_server_state", |e| e["state"] == "running")
+        .await;
+    fx.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn concurrent_requests_are_matched_by_id() {
+    let fx = Fixture::new(
+        "concurrent",
+        json!({ "methods": { "custom/slow": { "result": "slow", "delayMs": 300 } } }),
+    );
+    // Same client id on purpose – the bridge must not mix them up.
+    let (slow, fast) = tokio::join!(fx.invoke(request(1, "custom/slow")), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let started = Instant::now();
+        let response = fx.invoke(request(1, "custom/fast")).await;
+        (response, started.elapsed())
+    });
+    let (fast, fast_elapsed) = fast;
+    assert_eq!(slow.unwrap()["result"], "slow");
+    let fast = fast.unwrap();
+    assert_eq!(fast["id"], 1);
+    assert_eq!(fast["result"]["method"], "custom/fast");
+    assert!(
+        fast_elapsed < Duration::from_millis(200),
+        "{fast_elapsed:?}"
+    );
+    fx.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn malformed_frames_are_skipped_and_large_ones_read() {
+    let large = "x".repeat(1 << 20);
+    let fx = Fixture::new(
+        "framing",
+        json!({
+            "methods": {
+                "custom/garbage": { "raw": "Content-Length: nope\r\n\r\n{}Content-Length: 5\r\n\r\n{oops" },
+                "custom/large": { "result": large },
+            },
+        }),
+    );
+    let response = fx.invoke(request(1, "custom/garbage")).await.unwrap();
+    assert_eq!(response["result"]["method"], "custom/garbage");
+    let response = fx.invoke(request(2, "custom/large")).await.unwrap();
+    assert_eq!(response["result"].as_str().map(str::len), Some(1 << 20));
+    fx.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn timed_out_requests_are_cancelled_on_the_server() {
+    let fx = Fixture::new(
+        "timeout",
+        json!({ "methods": { "custom/hang": { "delayMs": 3000 } } }),
+    );
+    let payload = LspInvokeRequest {
+        root: fx.root.clone(),
+        request: request(1, "custom/hang"),
+        language: Some("mock".into()),
+        timeout_ms: Some(100),
+    };
+    let started = Instant::now();
+    let err = invoke_lsp(fx.app.clone(), payload)
+        .await
+        .map(|r| r.response)
+        .unwrap_err();
+    assert!(err.to_string().contains("LSP timeout after 100ms"), "{err}");
+    assert!(started.elapsed() < Duration::from_secs(1));
+    fx.expect_received("$/cancelRequest", 1).await;
+
+    // The server keeps working.
+    let response = fx.invoke(request(2, "custom/next")).await.unwrap();
+    assert_eq!(response["result"]["method"], "custom/next");
+    fx.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn diagnostics_are_forwarded_as_events() {
+    let diagnostic = json!({
+        "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 1 } },
+        "message": "bad",
+    });
+    let fx = Fixture::new(
+        "diagnostics",
+        json!({
+            "methods": {
+                "textDocument/didOpen": {
+                    "send": [{
+                        "method": "textDocument/publishDiagnostics",
+                        "params": { "uri": "$uri", "diagnostics": [diagnostic] },
+                    }],
+                },
+            },
+        }),
+    );
+    let uri = fx.uri("a.mock");
+    let payload = LspOpenDocument {
+        root: fx.root.clone(),
+        uri: uri.clone(),
+        language_id: "mock".into(),
+        text: "a".into(),
+    };
+    lsp_open_document(fx.app.clone(), payload).await.unwrap();
+
+    let event = fx
+        .event("lsp_diagnostics", |e| e["uri"] == uri.as_str())
+        .await;
+    assert_eq!(event["diagnostics"][0]["message"], "bad");
+    let event = fx
+        .event("lsp_notification", |e| {
+            e["method"] == "textDocument/publishDiagnostics"
+        })
+        .await;
+    assert_eq!(event["server"], "mock");
+    assert_eq!(event["root"], fx.root.as_str());
+    fx.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn crashed_servers_restart_and_reopen_documents() {
+    let fx = Fixture::new(
+        "restart",
+        json!({ "methods": { "custom/crash": { "stderr": "mock panicked", "crash": 3 } } }),
+    );
+    let uri = fx.uri("a.mock");
+    let payload = LspOpenDocument {
+        root: fx.root.clone(),
+        uri,
+        language_id: "mock".into(),
+        text: "a".into(),
+    };
+    lsp_open_document(fx.app.clone(), payload).await.unwrap();
+
+    let err = fx.invoke(request(1, "custom/crash")).await.unwrap_err();
+    assert!(err.to_string().contains("exited"), "{err}");
+    let event = fx
+        .event("lsp_server_state", |e| e["state"] == "restarting")
+        .await;
+    assert!(event["message"]
+        .as_str()
+        .is_some_and(|m| m.contains("mock panicked")));
+    fx.event("lsp_server_state", |e| {
+        e["state"] == "running" && e["restarts"] == 1
+    })
+    .await;
+
+    fx.expect_received("initialize", 2).await;
+    fx.expect_received("textDocument/didOpen", 2).await;
+    let response = fx.invoke(request(2, "custom/after")).await.unwrap();
+    assert_eq!(response["result"]["method"], "custom/after");
+    fx.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn concurrent_opens_send_one_did_open() {
+    let fx = Fixture::new(
+        "open-twice",
+        json!({ "capabilities": { "textDocumentSync": 1 } }),
+    );
+    let open = |text: &str| LspOpenDocument {
+        root: fx.root.clone(),
+        uri: fx.uri("a.mock"),
+        language_id: "mock".into(),
+        text: text.into(),
+    };
+    let (a, b) = tokio::join!(
+        lsp_open_document(fx.app.clone(), open("first")),
+        lsp_open_document(fx.app.clone(), open("second")),
+    );
+    let versions = [a.unwrap().version, b.unwrap().version];
+    assert!(
+        versions.contains(&0) && versions.contains(&1),
+        "{versions:?}"
+    );
+
+    // The later open became a full-text change.
+    fx.expect_received("textDocument/didChange", 1).await;
+    assert_eq!(fx.count_received("textDocument/didOpen"), 1);
+    let doc_text = documents::open_text(&fx.uri("a.mock")).unwrap();
+    assert!(doc_text == "first" || doc_text == "second");
+    lsp_close_document(LspDocumentRef {
+        uri: fx.uri("a.mock"),
+    })
+    .await
+    .unwrap();
+    fx.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn workspace_edits_move_and_close_open_documents() {
+    let fx = Fixture::new("move", json!({}));
+    std::fs::write(Path::new(&fx.root).join("a.mock"), "on disk").unwrap();
+    lsp_open_document(
+        fx.app.clone(),
+        LspOpenDocument {
+            root: fx.root.clone(),
+            uri: fx.uri("a.mock"),
+            language_id: "mock".into(),
+            text: "buffer".into(),
+        },
+    )
+    .await
+    .unwrap();
+    let apply = |change: Value| {
+        apply_workspace_edit(
+            fx.app.clone(),
+            ApplyWorkspaceEditRequest {
+                root: fx.root.clone(),
+                edit: json!({ "documentChanges": [change] }),
+                label: None,
+            },
+        )
+    };
+
+    let rename =
+        json!({ "kind": "rename", "oldUri": fx.uri("a.mock"), "newUri": fx.uri("b.mock") });
+    assert!(apply(rename).await.unwrap().applied);
+    let event = fx
+        .event("lsp_document_moved", |e| e["oldUri"] == fx.uri("a.mock"))
+        .await;
+    assert_eq!(event["newUri"], fx.uri("b.mock"));
+    fx.expect_received("textDocument/didOpen", 2).await;
+    assert_eq!(fx.count_received("textDocument/didClose"), 1);
+    assert_eq!(documents::open_text(&fx.uri("a.mock")), None);
+    assert_eq!(
+        documents::open_text(&fx.uri("b.mock")).as_deref(),
+        Some("buffer")
+    );
+
+    let delete = json!({ "kind": "delete", "uri": fx.uri("b.mock") });
+    assert!(apply(delete).await.unwrap().applied);
+    let event = fx
+        .event("lsp_document_moved", |e| e["oldUri"] == fx.uri("b.mock"))
+        .await;
+    assert_eq!(event["newUri"], Value::Null);
+    fx.expect_received("textDocument/didClose", 2).await;
+    assert_eq!(documents::open_text(&fx.uri("b.mock")), None);
+    fx.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn health_check_reports_servers_and_missing_languages() {
+    let fx = Fixture::new("health", json!({}));
+    // A configured server whose command is missing, matched by path only.
+    let config_path = registry::workspace_config_path(Path::new(&fx.root));
+    let mut config: Value =
+        serde_json::from_str(&std::fs::read_to_string(&config_path).unwrap()).unwrap();
+    config["servers"]["ghost"] = json!({
+        "command": "glass-no-such-server",
+        "filePatterns": ["*.zig"],
+    });
+    std::fs::write(&config_path, config.to_string()).unwrap();
+    std::fs::write(Path::new(&fx.root).join("Main.java"), "class Main {}").unwrap();
+    std::fs::create_dir_all(Path::new(&fx.root).join("src")).unwrap();
+    std::fs::write(
+        Path::new(&fx.root).join("src/main.zig"),
+        "pub fn main() void {}",
+    )
+    .unwrap();
+    let payload = LspHealthRequest {
+        root: fx.root.clone(),
+    };
+    let report = lsp_health(payload).await.unwrap();
+    let root = &report[0];
+    let mock = root.servers.iter().find(|s| s.id == "mock").unwrap();
+    assert_eq!(mock.status, discovery::Status::Installed);
+    assert_eq!(mock.source, Some(discovery::Source::Config));
+    assert_eq!(mock.version.as_deref(), Some("mock-lsp 0.0.0"));
+    assert_eq!(mock.state, None);
+    let java = root
+        .missing_languages
+        .iter()
+        .find(|l| l.language == "java")
+        .unwrap();
+    assert_eq!(java.reason, discovery::MissingReason::NotConfigured);
+    assert_eq!(java.files, 1);
+    assert!(java.servers.is_empty());
+    let zig = root
+        .missing_languages
+        .iter()
+        .find(|l| l.language == "zig")
+        .unwrap();
+    assert_eq!(zig.reason, discovery::MissingReason::NotInstalled);
+    assert_eq!(zig.servers, ["ghost"]);
+    let ghost = root.servers.iter().find(|s| s.id == "ghost").unwrap();
+    assert_eq!(ghost.status, discovery::Status::Missing);
+    fx.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn progress_is_emitted_and_tracked_in_status() {
+    let progress = |value: Value| json!({ "method": "$/progress", "params": { "token": "index", "value": value } });
+    let fx = Fixture::new(
+        "progress",
+        json!({
+            "methods": {
+                "custom/index": {
+                    "send": [
+                        { "id": 900, "method": "window/workDoneProgress/create",
+                          "params": { "token": "index" } },
+                        progress(json!({ "kind": "begin", "title": "Indexing", "percentage": 0 })),
+                        progress(json!({ "kind": "report", "message": "1/4", "percentage": 25 })),
+                    ],
+                },
+                "custom/done": {
+                    "send": [progress(json!({ "kind": "end", "message": "done" }))],
+                },
+                // Created, never begun: pending.
+                "custom/later": {
+                    "send": [{ "id": 901, "method": "window/workDoneProgress/create",
+                               "params": { "token": "later" } }],
+                },
+                // Never began, and partial results: plain notifications.
+                "custom/stray": {
+                    "send": [
+                        { "method": "$/progress",
+                          "params": { "token": 7, "value": { "kind": "report" } } },
+                        { "method": "$/progress",
+                          "params": { "token": "partial", "value": [{ "name": "main" }] } },
+                    ],
+                },
+            },
+        }),
+    );
+    fx.invoke(request(1, "custom/index")).await.unwrap();
+    let event = fx.event("lsp_progress", |e| e["kind"] == "report").await;
+    assert_eq!(event["server"], "mock");
+    assert_eq!(event["token"], "index");
+    assert_eq!(event["title"], "Indexing");
+    assert_eq!(event["percentage"], 25);
+    let status = lsp_status(LspStatusRequest {
+        root: Some(fx.root.clone()),
+    })
+    .await
+    .unwrap();
+    assert_eq!(status.len(), 1);
+    assert_eq!(status[0].progress.len(), 1);
+    assert_eq!(status[0].progress[0].message.as_deref(), Some("1/4"));
+
+    fx.invoke(request(2, "custom/stray")).await.unwrap();
+    let event = fx
+        .event("lsp_notification", |e| e["params"]["token"] == "partial")
+        .await;
+    assert_eq!(event["method"], "$/progress");
+    assert_eq!(event["params"]["value"][0]["name"], "main");
+    fx.invoke(request(3, "custom/done")).await.unwrap();
+    let event = fx.event("lsp_progress", |e| e["kind"] == "end").await;
+    assert_eq!(event["message"], "done");
+    let status = lsp_status(LspStatusRequest {
+        root: Some(fx.root.clone()),
+    })
+    .await
+    .unwrap();
+    assert!(status[0].progress.is_empty());
+    let kinds: Vec<Value> = fx
+        .events
+        .named("lsp_progress")
+        .iter()
+        .map(|e| e["kind"].clone())
+        .collect();
+    assert_eq!(kinds, ["create", "begin", "report", "end"]);
+
+    fx.invoke(request(4, "custom/later")).await.unwrap();
+    let event = fx.event("lsp_progress", |e| e["token"] == "later").await;
+    assert_eq!(event["kind"], "create");
+    assert_eq!(event["pending"], true);
+    let status = lsp_status(LspStatusRequest {
+        root: Some(fx.root.clone()),
+    })
+    .await
+    .unwrap();
+    assert_eq!(status[0].progress.len(), 1);
+    assert!(status[0].progress[0].pending);
+    fx.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn diagnostics_are_stored_queried_and_cleared_on_crash() {
+    let diagnostic = |line: u64, severity: u64, source: &str| {
+        json!({
+            "range": { "start": { "line": line, "character": 0 },
+                       "end": { "line": line, "character": 1 } },
+            "severity": severity,
+            "source": source,
+            "message": format!("{source} {line}"),
+        })
+    };
+    let publish = |diagnostics: Vec<Value>| {
+        json!({ "send": [{
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": "$uri", "diagnostics": diagnostics },
+        }] })
+    };
+    let fx = Fixture::new(
+        "problems",
+        json!({
+            "methods": {
+                "custom/errors": publish(vec![diagnostic(3, 2, "lint"), diagnostic(1, 1, "check")]),
+                "custom/hints": publish(vec![diagnostic(0, 4, "lint")]),
+                "custom/crash": { "crash": 1 },
+            },
+        }),
+    );
+    let (a, b) = (fx.uri("src/a.mock"), fx.uri("b.mock"));
+    let in_file = |method: &str, uri: &str| {
+        json!({ "jsonrpc": "2.0", "id": 1, "method": method,
+                "params": { "textDocument": { "uri": uri } } })
+    };
+    fx.invoke(in_file("custom/errors", &a)).await.unwrap();
+    fx.invoke(in_file("custom/hints", &b)).await.unwrap();
+    fx.event("lsp_diagnostics", |e| e["uri"] == b.as_str())
+        .await;
+
+    let query =
+        |severity: Option<u64>, path: Option<&str>, source: Option<&str>| LspProblemsRequest {
+            root: Some(fx.root.clone()),
+            severity,
+            path: path.map(str::to_string),
+            source: source.map(str::to_string),
+            limit: None,
+        };
+    let problems = |request| async { lsp_problems(request).await.unwrap() };
+    let all = problems(query(None, None, None)).await;
+    let messages: Vec<_> = all
+        .iter()
+        .map(|p| p.diagnostic["message"].clone())
+        .collect();
+    assert_eq!(
+        messages,
+        [json!("lint 0"), json!("check 1"), json!("lint 3")]
+    );
+    assert_eq!(all[1].path.as_deref(), Some("src/a.mock"));
+    assert_eq!(all[1].server, "mock");
+    assert_eq!(problems(query(Some(2), None, None)).await.len(), 2);
+    assert_eq!(problems(query(None, Some("src/**"), None)).await.len(), 2);
+    assert_eq!(problems(query(None, None, Some("lint"))).await.len(), 2);
+    assert_eq!(problems(query(None, None, Some("mock"))).await.len(), 3);
+
+    let counts = lsp_problem_counts(query(None, None, None)).await.unwrap();
+    assert_eq!(counts.len(), 2);
+    assert_eq!((counts[0].hints, counts[0].errors), (1, 0));
+    assert_eq!((counts[1].errors, counts[1].warnings), (1, 1));
+
+    let _ = fx.invoke(request(2, "custom/crash")).await;
+    fx.event("lsp_diagnostics", |e| {
+        e["uri"] == a.as_str() && e["diagnostics"] == json!([])
+    })
+    .await;
+    assert!(problems(query(None, None, None)).await.is_empty());
+    fx.stop().await;
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn formatting_prefers_the_server_and_falls_back_to_external_formatters() {
+    let edit = json!({
+        "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 2 } },
+        "newText": "fn",
+    });
+    let fx = Fixture::new(
+        "format",
+        json!({
+            "capabilities": { "documentFormattingProvider": true, "textDocumentSync": 1 },
+            "methods": { "textDocument/formatting": { "result": [edit] } },
+        }),
+    );
+    let root = Path::new(&fx.root);
+    let formatters = json!({
+        "formatters": {
+            "upper": { "command": "tr", "args": ["a-z", "A-Z"], "filePatterns": ["*.txt"] },
+        },
+        "languages": { "mock": { "formatter": "upper" } },
+    });
+    std::fs::write(
+        formatting::workspace_config_path(root),
+        formatters.to_string(),
+    )
+    .unwrap();
+    std::fs::write(
+        root.join(".editorconfig"),
+        "root = true\n\n[*.txt]\ntrim_trailing_whitespace = true\ninsert_final_newline = true\n",
+    )
+    .unwrap();
+    let format = |uri: &str, text: Option<&str>| {
+        lsp_format_document(LspFormatRequest {
+            root: fx.root.clone(),
+            uri: uri.to_string(),
+            language_id: None,
+            text: text.map(str::to_string),
+        })
+    };
+
+    // The open buffer goes to the server, which can format.
+    let a = fx.uri("a.mock");
+    let payload = LspOpenDocument {
+        root: fx.root.clone(),
+        uri: a.clone(),
+        language_id: "mock".into(),
+        text: "FN main() {}\n".into(),
+    };
+    lsp_open_document(fx.app.clone(), payload).await.unwrap();
+    let formatted = format(&a, None).await.unwrap();
+    assert_eq!(formatted.formatter.as_deref(), Some("mock"));
+    assert_eq!(formatted.edits, [edit]);
+    fx.expect_received("textDocument/formatting", 1).await;
+
+    // Other text is not what the server has open – the language's formatter runs.
+    let formatted = format(&a, Some("fn x() {}")).await.unwrap();
+    assert_eq!(formatted.formatter.as_deref(), Some("upper"));
+    assert_eq!(formatted.edits[0]["newText"], "FN X() {}");
+
+    // Formatter by file pattern, then the `.editorconfig` whitespace rules.
+    let formatted = format(&fx.uri("notes.txt"), Some("keep  \nthis"))
+        .await
+        .unwrap();
+    assert_eq!(formatted.formatter.as_deref(), Some("upper"));
+    assert_eq!(
+        formatted.edits,
+        [json!({
+            "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 1, "character": 4 } },
+            "newText": "KEEP\nTHIS\n",
+        })]
+    );
+    fx.stop().await;
+}
+
+#[tokio::test]
+async fn semantic_tokens_are_remapped_cached_and_sent_as_deltas() {
+    let fx = Fixture::new(
+        "semantic-tokens",
+        json!({
+            "capabilities": {
+                "textDocumentSync": 1,
+                "semanticTokensProvider": {
+                    "legend": {
+                        "tokenTypes": ["function", "lifetime", "variable"],
+                        "tokenModifiers": ["declaration"],
+                    },
+                    "full": { "delta": true },
+                },
+            },
+            "methods": {
+                "textDocument/semanticTokens/full": {
+                    "result": { "resultId": "1", "data": [0, 3, 4, 0, 1, 0, 5, 1, 1, 0, 1, 0, 3, 2, 0] },
+                },
+                "textDocument/semanticTokens/full/delta": {
+                    "result": {
+                        "resultId": "2",
+                        "edits": [{ "start": 10, "deleteCount": 5, "data": [2, 0, 2, 0, 0] }],
+                    },
+                },
+            },
+        }),
+    );
+    let uri = fx.uri("a.mock");
+    let payload = LspOpenDocument {
+        root: fx.root.clone(),
+        uri: uri.clone(),
+        language_id: "mock".into(),
+        text: "fn main() {}\nlet\n\nfn\n".into(),
+    };
+    lsp_open_document(fx.app.clone(), payload).await.unwrap();
+    let tokens = |previous_result_id: Option<String>| {
+        lsp_semantic_tokens(LspSemanticTokensRequest {
+            uri: uri.clone(),
+            previous_result_id,
+        })
+    };
+    let index = |name: &str| {
+        semantic_tokens::TOKEN_TYPES
+            .iter()
+            .position(|t| *t == name)
+            .unwrap() as u32
+    };
+    let function = index("function");
+
+    // Server types map onto the merged legend; `lifetime` is not in it.
+    let first = tokens(None).await.unwrap();
+    assert_eq!(first.server.as_deref(), Some("mock"));
+    assert_eq!(
+        first.data.as_deref(),
+        Some(&[0, 3, 4, function, 1, 1, 0, 3, index("variable"), 0][..])
+    );
+    assert!(first.edits.is_none());
+
+    // The server is asked for a delta against its last result, the editor
+    // gets one against the result it holds.
+    let second = tokens(Some(first.result_id.clone())).await.unwrap();
+    assert!(second.data.is_none());
+    let edits = second.edits.unwrap();
+    assert_eq!(edits.len(), 1);
+    assert_eq!((edits[0].start, edits[0].delete_count), (5, 5));
+    assert_eq!(edits[0].data, [2, 0, 2, function, 0]);
+    fx.expect_received("textDocument/semanticTokens/full/delta", 1)
+        .await;
+    let log = std::fs::read_to_string(Path::new(&fx.root).join("received.jsonl")).unwrap();
+    assert!(log.lines().any(|line| {
+        let msg: Value = serde_json::from_str(line).unwrap();
+        msg["method"] == "textDocument/semanticTokens/full/delta"
+            && msg["params"]["previousResultId"] == "1"
+    }));
+
+    // An unknown result id gets the full array; closing drops the cache.
+    let third = tokens(Some(first.result_id)).await.unwrap();
+    assert!(third.data.is_some());
+    lsp_close_document(LspDocumentRef { uri: uri.clone() })
+        .await
+        .unwrap();
+    assert!(tokens(None).await.is_err());
+    fx.stop().await;
+}
+
+#[test]
+fn offline_workspace_symbols_cover_every_root() {
+    let dir = test_support::temp_root("lsp", "fallback-roots");
+    for (folder, file, text) in [
+        ("a", "lib.rs", "fn shared_alpha() {}\n"),
+        ("b", "main.rs", "fn shared_beta() {}\n"),
+    ] {
+        std::fs::create_dir_all(dir.join(folder)).unwrap();
+        std::fs::write(dir.join(folder).join(file), text).unwrap();
+    }
+    let workspace = dir.join("project.glass-workspace");
+    let folders = json!({ "folders": [{ "path": "a" }, { "path": "b" }] });
+    std::fs::write(&workspace, folders.to_string()).unwrap();
+
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "workspace/symbol",
+        "params": { "query": "shared" },
+    });
+    // Routed to root `a`, but the other root is searched too.
+    let response = fallback::respond(&workspace, &dir.join("a"), &request).unwrap();
+    let mut found: Vec<(String, String)> = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| {
+            (
+                s["name"].as_str().unwrap().to_string(),
+                s["location"]["uri"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    found.sort();
+    let uri = |rel: &str| Url::from_file_path(dir.join(rel)).unwrap().to_string();
+    assert_eq!(
+        found,
+        [
+            ("shared_alpha".to_string(), uri("a/lib.rs")),
+            ("shared_beta".to_string(), uri("b/main.rs")),
+        ]
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,1074 @@
+//! Backend applier for LSP `WorkspaceEdit`s.
+//!
+//! Rename and code actions return edits for files the editor does not have
+//! open, so they are applied here:
+//! • `changes` and `documentChanges` (text edits, `create` / `rename` /
+//!   `delete` file operations, in order);
+//! • documents open in the editor are not written – their edits are checked
+//!   against the synchronised version and forwarded as `lsp_document_edit`
+//!   events, so the editor applies them on its own undo stack;
+//! • everything else is first applied to an in-memory overlay and only
+//!   written if the whole edit is valid, each file via temp file + rename.
+//!   A failing write rolls back the files written so far.
+//!
+//! Open documents that a `rename` or `delete` moves are closed on their
+//! servers, renamed ones re-opened under the new URI, and each is reported
+//! as an `lsp_document_moved` event so the editor can retarget the tab.
+//!
+//! Every applied edit is recorded as one undoable operation
+//! ([`undo`]), which restores all touched files at once and reverts the
+//! forwarded edits of open documents through `lsp_document_edit`.
+
+use anyhow::{anyhow, Context, Result};
+use log::warn;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Runtime};
+use tower_lsp::lsp_types::Url;
+use walkdir::WalkDir;
+
+use super::documents::{self, byte_range, OPEN_DOCUMENTS};
+use super::formatting::diff_edits;
+use super::uri_to_path;
+
+/// Undoable operations kept.
+const UNDO_LIMIT: usize = 20;
+
+/// File contents by path; `None` = file does not exist.
+type Snapshot = BTreeMap<PathBuf, Option<Vec<u8>>>;
+
+/// One applied workspace edit.
+struct UndoEntry {
+    id: u64,
+    label: String,
+    roots: Vec<PathBuf>,
+    before: Snapshot,
+    after: Snapshot,
+    /// Open documents edited through `lsp_document_edit`: buffer text before
+    /// and after, by URI.
+    documents: BTreeMap<String, (String, String)>,
+}
+
+static UNDO_STACK: Lazy<Mutex<Vec<UndoEntry>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static NEXT_UNDO_ID: AtomicU64 = AtomicU64::new(1);
+static NEXT_TEMP_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum FileStatus {
+    /// Written to disk.
+    Applied,
+    /// Document is open in the editor; edits were sent as `lsp_document_edit`.
+    Forwarded,
+    /// Not applied because another change failed, or skipped by its options
+    /// (`ignoreIfExists` / `ignoreIfNotExists`).
+    Skipped,
+    Failed,
+}
+
+/// Outcome of one entry of the edit.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileResult {
+    pub uri: String,
+    /// `edit`, `create`, `rename` or `delete`
+    pub operation: &'static str,
+    pub status: FileStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Mirrors LSP's `ApplyWorkspaceEditResult`, plus per-file results.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceEditResult {
+    pub applied: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<String>,
+    /// Index of the change that failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed_change: Option<usize>,
+    pub files: Vec<FileResult>,
+    /// Id for [`undo`]; `None` if nothing was written or forwarded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub undo_id: Option<u64>,
+}
+
+/// Payload of the `lsp_document_edit` event.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct DocumentEdit {
+    uri: String,
+    /// Synchronised version the edits apply to.
+    version: i64,
+    edits: Value,
+}
+
+/// An open document a workspace edit renamed or deleted; payload of the
+/// `lsp_document_moved` event.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MovedDocument {
+    pub old_uri: String,
+    /// `None` if the document was deleted.
+    pub new_uri: Option<String>,
+    /// Buffer text after the edits forwarded for the document, if any.
+    #[serde(skip)]
+    pub text: Option<String>,
+}
+
+/// One normalised entry of a `WorkspaceEdit`.
+enum Change {
+    Edit {
+        uri: String,
+        version: Option<i64>,
+        edits: Vec<Value>,
+    },
+    Create {
+        uri: String,
+        overwrite: bool,
+        ignore_if_exists: bool,
+    },
+    Rename {
+        old_uri: String,
+        new_uri: String,
+        overwrite: bool,
+        ignore_if_exists: bool,
+    },
+    Delete {
+        uri: String,
+        recursive: bool,
+        ignore_if_not_exists: bool,
+    },
+}
+
+impl Change {
+    fn uri(&self) -> &str {
+        match self {
+            Change::Edit { uri, .. } | Change::Create { uri, .. } | Change::Delete { uri, .. } => {
+                uri
+            }
+            Change::Rename { old_uri, .. } => old_uri,
+        }
+    }
+
+    fn operation(&self) -> &'static str {
+        match self {
+            Change::Edit { .. } => "edit",
+            Change::Create { .. } => "create",
+            Change::Rename { .. } => "rename",
+            Change::Delete { .. } => "delete",
+        }
+    }
+}
+
+/// Normalise `documentChanges` (preferred) or `changes` into a list.
+fn parse_changes(edit: &Value) -> Vec<Change> {
+    let str_at = |v: &Value, ptr: &str| {
+        v.pointer(ptr)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    let flag = |v: &Value, ptr: &str| v.pointer(ptr).and_then(Value::as_bool).unwrap_or(false);
+
+    if let Some(changes) = edit.get("documentChanges").and_then(Value::as_array) {
+        return changes
+            .iter()
+            .map(|c| match c.get("kind").and_then(Value::as_str) {
+                Some("create") => Change::Create {
+                    uri: str_at(c, "/uri"),
+                    overwrite: flag(c, "/options/overwrite"),
+                    ignore_if_exists: flag(c, "/options/ignoreIfExists"),
+                },
+                Some("rename") => Change::Rename {
+                    old_uri: str_at(c, "/oldUri"),
+                    new_uri: str_at(c, "/newUri"),
+                    overwrite: flag(c, "/options/overwrite"),
+                    ignore_if_exists: flag(c, "/options/ignoreIfExists"),
+                },
+                Some("delete") => Change::Delete {
+                    uri: str_at(c, "/uri"),
+                    recursive: flag(c, "/options/recursive"),
+                    ignore_if_not_exists: flag(c, "/options/ignoreIfNotExists"),
+                },
+                _ => Change::Edit {
+                    uri: str_at(c, "/textDocument/uri"),
+                    version: c.pointer("/textDocument/version").and_then(Value::as_i64),
+                    edits: c
+                        .get("edits")
+                        .and_then(Value::as_array)
+                        .cloned()
+                        .unwrap_or_default(),
+                },
+            })
+            .collect();
+    }
+
+    edit.get("changes")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .map(|(uri, edits)| Change::Edit {
+            uri: uri.clone(),
+            version: None,
+            edits: edits.as_array().cloned().unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Pending file system state on top of the disk.
+#[derive(Default)]
+struct Overlay {
+    files: HashMap<PathBuf, Option<Vec<u8>>>,
+    /// Directories emptied by renames / deletes, removed after committing.
+    emptied_dirs: Vec<PathBuf>,
+}
+
+impl Overlay {
+    fn read(&self, path: &Path) -> Option<Vec<u8>> {
+        match self.files.get(path) {
+            Some(contents) => contents.clone(),
+            None => fs::read(path).ok().filter(|_| path.is_file()),
+        }
+    }
+
+    /// Existing files at or below `path`.
+    fn files_under(&self, path: &Path) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = WalkDir::new(path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.into_path())
+            .filter(|p| !matches!(self.files.get(p), Some(None)))
+            .collect();
+        for (p, contents) in &self.files {
+            if contents.is_some() && p.starts_with(path) && !files.contains(p) {
+                files.push(p.clone());
+            }
+        }
+        files.sort();
+        files
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.read(path).is_some() || !self.files_under(path).is_empty() || path.is_dir()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.read(path).is_none() && (path.is_dir() || !self.files_under(path).is_empty())
+    }
+}
+
+/// Apply `TextEdit`s (ranges relative to the original `text`). Edits at the
+/// same position are inserted in the given order; overlapping edits fail.
+pub fn apply_text_edits(text: &str, edits: &[Value]) -> Result<String> {
+    let mut ranged: Vec<(usize, usize, &str)> = edits
+        .iter()
+        .map(|edit| {
+            let (start, end) = byte_range(text, edit.get("range").unwrap_or(&Value::Null));
+            let new_text = edit
+                .get("newText")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            (start, end, new_text)
+        })
+        .collect();
+    // Stable sort keeps same-position inserts in their original order.
+    ranged.sort_by_key(|(start, end, _)| (*start, *end));
+    if ranged.windows(2).any(|w| w[0].1 > w[1].0) {
+        return Err(anyhow!("Overlapping text edits"));
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut cursor = 0;
+    for (start, end, new_text) in ranged {
+        out.push_str(&text[cursor..start]);
+        out.push_str(new_text);
+        cursor = end;
+    }
+    out.push_str(&text[cursor..]);
+    Ok(out)
+}
+
+/// Resolve a change's URI to a path inside one of `roots`.
+fn checked_path(uri: &str, roots: &[PathBuf]) -> Result<PathBuf> {
+    let path = uri_to_path(uri).ok_or_else(|| anyhow!("Not a file URI: {uri}"))?;
+    if !roots.iter().any(|root| path.starts_with(root)) {
+        return Err(anyhow!("{} is outside the workspace", path.display()));
+    }
+    Ok(path)
+}
+
+/// Edits for an open document, to be forwarded to the editor.
+struct Forward {
+    uri: String,
+    version: i64,
+    edits: Vec<Value>,
+    /// Buffer text the edits apply to, and the result.
+    before: String,
+    after: String,
+}
+
+/// Validate and stage one change in `overlay`.
+fn stage(
+    change: &Change,
+    roots: &[PathBuf],
+    overlay: &mut Overlay,
+    forwards: &mut Vec<Forward>,
+) -> Result<FileStatus> {
+    match change {
+        Change::Edit {
+            uri,
+            version,
+            edits,
+        } => {
+            let open_version = OPEN_DOCUMENTS.lock().unwrap().get(uri).map(|d| d.version);
+            if let Some(open_version) = open_version {
+                if version.is_some_and(|v| v != open_version) {
+                    return Err(anyhow!(
+                        "Document changed since the edit was computed (version {open_version}, edit for {})",
+                        version.unwrap_or_default()
+                    ));
+                }
+                // Later edits of the same document apply on top of earlier ones.
+                let before = forwards
+                    .iter()
+                    .rev()
+                    .find(|f| f.uri == *uri)
+                    .map(|f| f.after.clone())
+                    .or_else(|| documents::open_text(uri))
+                    .unwrap_or_default();
+                let after = apply_text_edits(&before, edits)?;
+                forwards.push(Forward {
+                    uri: uri.clone(),
+                    version: open_version,
+                    edits: edits.clone(),
+                    before,
+                    after,
+                });
+                return Ok(FileStatus::Forwarded);
+            }
+
+            let path = checked_path(uri, roots)?;
+            let bytes = overlay
+                .read(&path)
+                .ok_or_else(|| anyhow!("{} does not exist", path.display()))?;
+            let text = String::from_utf8(bytes)
+                .map_err(|_| anyhow!("{} is not valid UTF-8", path.display()))?;
+            let edited = apply_text_edits(&text, edits)?;
+            overlay.files.insert(path, Some(edited.into_bytes()));
+        }
+        Change::Create {
+            uri,
+            overwrite,
+            ignore_if_exists,
+        } => {
+            let path = checked_path(uri, roots)?;
+            if overlay.exists(&path) {
+                if *overwrite && !overlay.is_dir(&path) {
+                    overlay.files.insert(path, Some(Vec::new()));
+                    return Ok(FileStatus::Applied);
+                }
+                if *ignore_if_exists {
+                    return Ok(FileStatus::Skipped);
+                }
+                return Err(anyhow!("{} already exists", path.display()));
+            }
+            overlay.files.insert(path, Some(Vec::new()));
+        }
+        Change::Rename {
+            old_uri,
+            new_uri,
+            overwrite,
+            ignore_if_exists,
+        } => {
+            let from = checked_path(old_uri, roots)?;
+            let to = checked_path(new_uri, roots)?;
+            if !overlay.exists(&from) {
+                return Err(anyhow!("{} does not exist", from.display()));
+            }
+            if overlay.exists(&to) {
+                if *ignore_if_exists && !*overwrite {
+                    return Ok(FileStatus::Skipped);
+                }
+                if !*overwrite {
+                    return Err(anyhow!("{} already exists", to.display()));
+                }
+                for file in overlay.files_under(&to) {
+                    overlay.files.insert(file, None);
+                }
+            }
+            let moved = if overlay.is_dir(&from) {
+                overlay.emptied_dirs.push(from.clone());
+                overlay.files_under(&from)
+            } else {
+                vec![from.clone()]
+            };
+            for file in moved {
+                let contents = overlay.read(&file);
+                let target = to.join(file.strip_prefix(&from).unwrap_or(Path::new("")));
+                let target = if file == from { to.clone() } else { target };
+                overlay.files.insert(file, None);
+                overlay.files.insert(target, contents);
+            }
+        }
+        Change::Delete {
+            uri,
+            recursive,
+            ignore_if_not_exists,
+        } => {
+            let path = checked_path(uri, roots)?;
+            if !overlay.exists(&path) {
+                if *ignore_if_not_exists {
+                    return Ok(FileStatus::Skipped);
+                }
+                return Err(anyhow!("{} does not exist", path.display()));
+            }
+            if overlay.is_dir(&path) {
+                let files = overlay.files_under(&path);
+                if !files.is_empty() && !*recursive {
+                    return Err(anyhow!("{} is not empty", path.display()));
+                }
+                for file in files {
+                    overlay.files.insert(file, None);
+                }
+                overlay.emptied_dirs.push(path);
+            } else {
+                overlay.files.insert(path, None);
+            }
+        }
+    }
+    Ok(FileStatus::Applied)
+}
+
+/// Write one file atomically (temp file in the same directory + rename),
+/// keeping the permissions of the file it replaces. Symlinks are followed,
+/// so the link stays and its target is replaced; files with several hard
+/// links are written in place, as a rename would split them off.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let path = &fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let existing = fs::metadata(path).ok();
+    if existing.as_ref().is_some_and(has_other_links) {
+        return fs::write(path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()));
+    }
+    let parent = path
+        .parent()
+        .ok_or_else(|| anyhow!("{} has no parent", path.display()))?;
+    fs::create_dir_all(parent)?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    // Unique per write, so concurrent writes of one file never share it.
+    let tmp = parent.join(format!(
+        ".{name}.{}-{}.glass-tmp",
+        std::process::id(),
+        NEXT_TEMP_ID.fetch_add(1, Ordering::Relaxed)
+    ));
+    let written = fs::write(&tmp, contents)
+        .with_context(|| format!("Failed to write {}", tmp.display()))
+        .and_then(|()| match &existing {
+            Some(meta) => fs::set_permissions(&tmp, meta.permissions())
+                .with_context(|| format!("Failed to set permissions of {}", tmp.display())),
+            None => Ok(()),
+        })
+        .and_then(|()| {
+            fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+        });
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    written
+}
+
+#[cfg(unix)]
+fn has_other_links(meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    meta.is_file() && meta.nlink() > 1
+}
+
+#[cfg(not(unix))]
+fn has_other_links(_meta: &fs::Metadata) -> bool {
+    false
+}
+
+fn write_state(path: &Path, contents: &Option<Vec<u8>>) -> Result<()> {
+    match contents {
+        Some(bytes) => write_atomic(path, bytes),
+        None if path.exists() => {
+            fs::remove_file(path).with_context(|| format!("Failed to delete {}", path.display()))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Current disk state of `paths`.
+fn snapshot<'a>(paths: impl Iterator<Item = &'a PathBuf>) -> Snapshot {
+    paths
+        .map(|p| (p.clone(), fs::read(p).ok().filter(|_| p.is_file())))
+        .collect()
+}
+
+/// Bring the disk to `target`, restoring `before` if any write fails.
+/// Creations and updates go first so renames never lose data.
+fn commit(target: &Snapshot, before: &Snapshot, emptied_dirs: &[PathBuf]) -> Result<()> {
+    let ordered = target
+        .iter()
+        .filter(|(_, c)| c.is_some())
+        .chain(target.iter().filter(|(_, c)| c.is_none()));
+    let mut written: Vec<&PathBuf> = Vec::new();
+    for (path, contents) in ordered {
+        if let Err(err) = write_state(path, contents) {
+            for path in written {
+                if let Err(err) = write_state(path, &before[path]) {
+                    warn!("[LSP] Rollback of {} failed: {err:#}", path.display());
+                }
+            }
+            return Err(err);
+        }
+        written.push(path);
+    }
+    // Deepest first, so nested emptied directories go before their parents.
+    let mut dirs: Vec<&PathBuf> = emptied_dirs.iter().collect();
+    dirs.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
+    for dir in dirs {
+        remove_empty_dirs(dir);
+    }
+    Ok(())
+}
+
+/// Remove `dir` and its subdirectories if they contain no files.
+fn remove_empty_dirs(dir: &Path) {
+    let mut subdirs: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+        .map(|e| e.into_path())
+        .collect();
+    subdirs.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
+    for sub in subdirs {
+        // Fails (and is ignored) for directories that still have entries.
+        let _ = fs::remove_dir(sub);
+    }
+}
+
+/// Apply `edit` to files inside `roots`. Nothing is written unless every
+/// change is valid; the result carries per-change outcomes.
+pub fn apply<R: Runtime>(
+    app: &AppHandle<R>,
+    roots: &[PathBuf],
+    edit: &Value,
+    label: Option<&str>,
+) -> WorkspaceEditResult {
+    let changes = parse_changes(edit);
+    let mut overlay = Overlay::default();
+    let mut forwards = Vec::new();
+    let mut files: Vec<FileResult> = changes
+        .iter()
+        .map(|c| FileResult {
+            uri: c.uri().to_string(),
+            operation: c.operation(),
+            status: FileStatus::Skipped,
+            error: None,
+        })
+        .collect();
+
+    let mut failed = None;
+    for (i, change) in changes.iter().enumerate() {
+        match stage(change, roots, &mut overlay, &mut forwards) {
+            Ok(status) => files[i].status = status,
+            Err(err) => {
+                failed = Some((i, format!("{err:#}")));
+                break;
+            }
+        }
+    }
+    let mut written = (Snapshot::new(), Snapshot::new());
+    if failed.is_none() && !overlay.files.is_empty() {
+        let before = snapshot(overlay.files.keys());
+        let after: Snapshot = overlay.files.clone().into_iter().collect();
+        match commit(&after, &before, &overlay.emptied_dirs) {
+            Ok(()) => written = (before, after),
+            Err(err) => failed = Some((files.len(), format!("{err:#}"))),
+        }
+    }
+
+    match failed {
+        Some((i, reason)) => {
+            for file in files.iter_mut() {
+                file.status = FileStatus::Skipped;
+            }
+            if let Some(file) = files.get_mut(i) {
+                file.status = FileStatus::Failed;
+                file.error = Some(reason.clone());
+            }
+            WorkspaceEditResult {
+                applied: false,
+                failure_reason: Some(reason),
+                failed_change: (i < changes.len()).then_some(i),
+                files,
+                undo_id: None,
+            }
+        }
+        None => {
+            let (before, after) = written;
+            let undo = UndoEntry {
+                id: NEXT_UNDO_ID.fetch_add(1, Ordering::Relaxed),
+                label: label.unwrap_or("Workspace edit").to_string(),
+                roots: roots.to_vec(),
+                before,
+                after,
+                documents: BTreeMap::new(),
+            };
+            let moved = moved_documents(&changes, &files, &forwards);
+            let result = finish(app, files, forwards, undo);
+            if !moved.is_empty() {
+                let app = app.clone();
+                tokio::spawn(async move { super::move_documents(&app, moved).await });
+            }
+            result
+        }
+    }
+}
+
+/// Open documents the applied `changes` renamed or deleted. Deletions come
+/// first, so a rename onto an open document closes that one before the
+/// renamed document takes its URI.
+fn moved_documents(
+    changes: &[Change],
+    files: &[FileResult],
+    forwards: &[Forward],
+) -> Vec<MovedDocument> {
+    let open: Vec<String> = OPEN_DOCUMENTS.lock().unwrap().keys().cloned().collect();
+    // Where each open document is after the changes so far; `None` = deleted.
+    let mut current: Vec<(String, Option<PathBuf>)> = open
+        .into_iter()
+        .filter_map(|uri| {
+            let path = uri_to_path(&uri)?;
+            Some((uri, Some(path)))
+        })
+        .collect();
+    for (change, file) in changes.iter().zip(files) {
+        if file.status != FileStatus::Applied {
+            continue;
+        }
+        let (from, to) = match change {
+            Change::Rename {
+                old_uri, new_uri, ..
+            } => (uri_to_path(old_uri), uri_to_path(new_uri)),
+            Change::Delete { uri, .. } => (uri_to_path(uri), None),
+            _ => continue,
+        };
+        let Some(from) = from else {
+            continue;
+        };
+        for (_, path) in current.iter_mut() {
+            let Some(old) = path.clone() else {
+                continue;
+            };
+            match old.strip_prefix(&from) {
+                Ok(rel) if rel.as_os_str().is_empty() => *path = to.clone(),
+                Ok(rel) => *path = to.as_ref().map(|to| to.join(rel)),
+                // Replaced by an overwriting rename.
+                Err(_) if to.as_ref().is_some_and(|to| old.starts_with(to)) => *path = None,
+                Err(_) => {}
+            }
+        }
+    }
+
+    let mut moved: Vec<MovedDocument> = current
+        .into_iter()
+        .filter_map(|(old_uri, path)| {
+            let new_uri = match path {
+                Some(path) => Some(Url::from_file_path(path).ok()?.to_string()),
+                None => None,
+            };
+            if new_uri.as_deref() == Some(old_uri.as_str()) {
+                return None;
+            }
+            let text = forwards
+                .iter()
+                .rev()
+                .find(|f| f.uri == old_uri)
+                .map(|f| f.after.clone());
+            Some(MovedDocument {
+                old_uri,
+                new_uri,
+                text,
+            })
+        })
+        .collect();
+    moved.sort_by_key(|m| m.new_uri.is_some());
+    moved
+}
+
+/// Forward open-document edits and record the undo entry, unless the edit
+/// changed nothing.
+fn finish<R: Runtime>(
+    app: &AppHandle<R>,
+    files: Vec<FileResult>,
+    forwards: Vec<Forward>,
+    mut undo: UndoEntry,
+) -> WorkspaceEditResult {
+    for forward in forwards {
+        undo.documents
+            .entry(forward.uri.clone())
+            .or_insert_with(|| (forward.before, String::new()))
+            .1 = forward.after;
+        let _ = app.emit(
+            "lsp_document_edit",
+            DocumentEdit {
+                uri: forward.uri,
+                version: forward.version,
+                edits: json!(forward.edits),
+            },
+        );
+    }
+    let undo_id = (!undo.after.is_empty() || !undo.documents.is_empty()).then(|| {
+        let id = undo.id;
+        let mut stack = UNDO_STACK.lock().unwrap();
+        stack.push(undo);
+        if stack.len() > UNDO_LIMIT {
+            stack.remove(0);
+        }
+        id
+    });
+    WorkspaceEditResult {
+        applied: true,
+        failure_reason: None,
+        failed_change: None,
+        files,
+        undo_id,
+    }
+}
+
+/// Revert the files written and the documents edited by workspace edit `id`
+/// (default: the most recent one). Refuses if any of them changed since.
+/// Returns the label.
+pub fn undo<R: Runtime>(app: &AppHandle<R>, id: Option<u64>) -> Result<String> {
+    let mut stack = UNDO_STACK.lock().unwrap();
+    let index = match id {
+        Some(id) => stack.iter().position(|e| e.id == id),
+        None => stack.len().checked_sub(1),
+    }
+    .ok_or_else(|| anyhow!("Nothing to undo"))?;
+
+    let entry = &stack[index];
+    let current = snapshot(entry.after.keys());
+    if let Some((path, _)) = entry
+        .after
+        .iter()
+        .find(|(path, contents)| current[*path] != **contents)
+    {
+        return Err(anyhow!(
+            "Cannot undo `{}`: {} changed since",
+            entry.label,
+            path.display()
+        ));
+    }
+    let reverts: Vec<DocumentEdit> = {
+        let open_documents = OPEN_DOCUMENTS.lock().unwrap();
+        entry
+            .documents
+            .iter()
+            .map(|(uri, (before, after))| match open_documents.get(uri) {
+                Some(doc) if doc.text == *after => Ok(DocumentEdit {
+                    uri: uri.clone(),
+                    version: doc.version,
+                    edits: json!(diff_edits(after, before)),
+                }),
+                Some(_) => Err(anyhow!(
+                    "Cannot undo `{}`: {uri} changed since",
+                    entry.label
+                )),
+                None => Err(anyhow!("Cannot undo `{}`: {uri} was closed", entry.label)),
+            })
+            .collect::<Result<_>>()?
+    };
+    commit(&entry.before, &current, &[])?;
+    for revert in reverts {
+        let _ = app.emit("lsp_document_edit", revert);
+    }
+    // Drop directories that only existed for files the edit created.
+    for (path, _) in entry.before.iter().filter(|(_, c)| c.is_none()) {
+        for dir in path.ancestors().skip(1) {
+            let inside_root = entry
+                .roots
+                .iter()
+                .any(|root| dir.starts_with(root) && dir != root);
+            if !inside_root || fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(stack.remove(index).label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, Events};
+    use tauri::test::mock_app;
+
+    fn temp_root(name: &str) -> PathBuf {
+        test_support::temp_root("edit", name)
+    }
+
+    fn uri(path: &Path) -> String {
+        Url::from_file_path(path).unwrap().to_string()
+    }
+
+    fn text_edit(start: (u32, u32), end: (u32, u32), new_text: &str) -> Value {
+        json!({
+            "range": {
+                "start": { "line": start.0, "character": start.1 },
+                "end": { "line": end.0, "character": end.1 },
+            },
+            "newText": new_text,
+        })
+    }
+
+    fn document_edit(uri: &str, version: Option<i64>, edits: &[Value]) -> Value {
+        json!({ "textDocument": { "uri": uri, "version": version }, "edits": edits })
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn overlapping_edits_are_rejected() {
+        let text = "one\ntwo\n";
+        let overlapping = [
+            text_edit((0, 0), (0, 3), "1"),
+            text_edit((0, 2), (1, 1), "x"),
+        ];
+        let err = apply_text_edits(text, &overlapping).unwrap_err();
+        assert!(err.to_string().contains("Overlapping"));
+        // Touching ranges are fine; inserts at one position keep their order.
+        let touching = [
+            text_edit((1, 0), (1, 0), "b"),
+            text_edit((0, 0), (0, 3), "1"),
+            text_edit((1, 0), (1, 0), "c"),
+            text_edit((0, 3), (1, 0), " "),
+        ];
+        assert_eq!(apply_text_edits(text, &touching).unwrap(), "1 bctwo\n");
+
+        // The whole workspace edit fails and nothing is written.
+        let root = temp_root("overlap");
+        let file = root.join("a.txt");
+        fs::write(&file, text).unwrap();
+        let mock = mock_app();
+        let edit = json!({ "changes": { uri(&file): overlapping } });
+        let result = apply(mock.handle(), &[root], &edit, None);
+        assert!(!result.applied);
+        assert_eq!(result.failed_change, Some(0));
+        assert_eq!(result.files[0].status, FileStatus::Failed);
+        assert!(result.undo_id.is_none());
+        assert_eq!(read(&file), text);
+    }
+
+    #[test]
+    fn file_operations_apply_in_order_and_undo_together() {
+        let root = temp_root("order");
+        fs::write(root.join("old.txt"), "old\n").unwrap();
+        fs::create_dir_all(root.join("gone")).unwrap();
+        fs::write(root.join("gone/x.txt"), "x\n").unwrap();
+        let new = uri(&root.join("new.txt"));
+        let edit_new = document_edit(&new, None, &[text_edit((0, 0), (0, 0), "hello\n")]);
+        let changes = json!([
+            { "kind": "create", "uri": new },
+            edit_new,
+            { "kind": "rename", "oldUri": new, "newUri": uri(&root.join("dir/moved.txt")) },
+            document_edit(
+                &uri(&root.join("old.txt")),
+                None,
+                &[text_edit((0, 0), (0, 3), "new")]
+            ),
+            {
+                "kind": "delete",
+                "uri": uri(&root.join("gone")),
+                "options": { "recursive": true },
+            },
+        ]);
+        let mock = mock_app();
+        let app = mock.handle();
+        let roots = [root.clone()];
+
+        // Editing the file before creating it fails as a whole.
+        let mut reordered = changes.clone();
+        reordered.as_array_mut().unwrap().swap(0, 1);
+        let result = apply(app, &roots, &json!({ "documentChanges": reordered }), None);
+        assert_eq!(result.failed_change, Some(0));
+        assert!(!root.join("new.txt").exists() && root.join("gone/x.txt").exists());
+
+        let result = apply(app, &roots, &json!({ "documentChanges": changes }), None);
+        assert!(result.applied, "{:?}", result.failure_reason);
+        assert!(result.files.iter().all(|f| f.status == FileStatus::Applied));
+        assert_eq!(read(&root.join("dir/moved.txt")), "hello\n");
+        assert_eq!(read(&root.join("old.txt")), "new\n");
+        assert!(!root.join("new.txt").exists() && !root.join("gone").exists());
+
+        // Undo restores every file and drops the directory the edit created.
+        undo(app, result.undo_id).unwrap();
+        assert_eq!(read(&root.join("old.txt")), "old\n");
+        assert_eq!(read(&root.join("gone/x.txt")), "x\n");
+        assert!(!root.join("new.txt").exists() && !root.join("dir").exists());
+    }
+
+    #[test]
+    fn failed_write_rolls_back_written_files() {
+        let root = temp_root("rollback");
+        let file = root.join("a.txt");
+        fs::write(&file, "a\n").unwrap();
+        // A file where the created file's directory should go.
+        fs::write(root.join("blocker"), "").unwrap();
+        let changes = json!([
+            document_edit(&uri(&file), None, &[text_edit((0, 0), (0, 1), "b")]),
+            { "kind": "create", "uri": uri(&root.join("blocker/x.txt")) },
+        ]);
+        let mock = mock_app();
+        let result = apply(
+            mock.handle(),
+            std::slice::from_ref(&root),
+            &json!({ "documentChanges": changes }),
+            None,
+        );
+        assert!(!result.applied);
+        assert!(result.failure_reason.is_some() && result.failed_change.is_none());
+        assert!(result.undo_id.is_none());
+        assert_eq!(read(&file), "a\n");
+        let mut left: Vec<_> = fs::read_dir(&root)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["a.txt", "blocker"]);
+    }
+
+    #[test]
+    fn undo_refuses_after_an_external_change() {
+        let root = temp_root("external");
+        let file = root.join("a.txt");
+        fs::write(&file, "a\n").unwrap();
+        let edit = json!({ "changes": { uri(&file): [text_edit((0, 0), (0, 1), "b")] } });
+        let mock = mock_app();
+        let app = mock.handle();
+        let result = apply(app, &[root], &edit, Some("Rename a"));
+        assert_eq!(read(&file), "b\n");
+
+        fs::write(&file, "c\n").unwrap();
+        let err = undo(app, result.undo_id).unwrap_err().to_string();
+        assert!(
+            err.contains("Rename a") && err.contains("changed since"),
+            "{err}"
+        );
+        assert_eq!(read(&file), "c\n");
+
+        // The entry is kept, so undo works once the file is back.
+        fs::write(&file, "b\n").unwrap();
+        assert_eq!(undo(app, result.undo_id).unwrap(), "Rename a");
+        assert_eq!(read(&file), "a\n");
+        assert!(undo(app, result.undo_id).is_err());
+    }
+
+    #[test]
+    fn forwarded_edits_are_recorded_for_undo() {
+        let root = temp_root("forward");
+        let file = root.join("open.ts");
+        fs::write(&file, "on disk\n").unwrap();
+        let uri = uri(&file);
+        OPEN_DOCUMENTS.lock().unwrap().insert(
+            uri.clone(),
+            documents::OpenDocument {
+                root: root.to_string_lossy().into_owned(),
+                language_id: "typescript".to_string(),
+                version: 3,
+                text: "let a = 1;\n".to_string(),
+                servers: Vec::new(),
+            },
+        );
+        let mock = mock_app();
+        let app = mock.handle();
+        let events = Events::listen(app, &["lsp_document_edit"]);
+
+        // The second edit applies on top of the first.
+        let changes = json!([
+            document_edit(&uri, Some(3), &[text_edit((0, 4), (0, 5), "b")]),
+            document_edit(&uri, Some(3), &[text_edit((1, 0), (1, 0), "b;\n")]),
+        ]);
+        let result = apply(app, &[root], &json!({ "documentChanges": changes }), None);
+        assert!(result
+            .files
+            .iter()
+            .all(|f| f.status == FileStatus::Forwarded));
+        assert_eq!(read(&file), "on disk\n");
+        assert_eq!(events.named("lsp_document_edit").len(), 2);
+
+        // Not yet applied by the editor.
+        assert!(undo(app, result.undo_id).is_err());
+
+        let edited = "let b = 1;\nb;\n";
+        if let Some(doc) = OPEN_DOCUMENTS.lock().unwrap().get_mut(&uri) {
+            doc.text = edited.to_string();
+            doc.version = 4;
+        }
+        undo(app, result.undo_id).unwrap();
+        let revert = events.named("lsp_document_edit").pop().unwrap();
+        assert_eq!(revert["version"], 4);
+        let edits = revert["edits"].as_array().unwrap();
+        assert_eq!(apply_text_edits(edited, edits).unwrap(), "let a = 1;\n");
+        OPEN_DOCUMENTS.lock().unwrap().remove(&uri);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn atomic_writes_keep_permissions_and_clean_up() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = temp_root("atomic");
+        let script = root.join("run.sh");
+        fs::write(&script, "old").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        write_atomic(&script, b"new").unwrap();
+        assert_eq!(read(&script), "new");
+        let mode = fs::metadata(&script).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+
+        // Replacing a non-empty directory fails without leaving the temp file.
+        fs::create_dir_all(root.join("dir/inner")).unwrap();
+        assert!(write_atomic(&root.join("dir"), b"x").is_err());
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn atomic_writes_keep_symlinks_and_hard_links() {
+        let root = temp_root("links");
+        let target = root.join("target.txt");
+        fs::write(&target, "old").unwrap();
+        let link = root.join("link.txt");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        write_atomic(&link, b"through link").unwrap();
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(read(&target), "through link");
+
+        let hard = root.join("hard.txt");
+        fs::hard_link(&target, &hard).unwrap();
+        write_atomic(&hard, b"shared").unwrap();
+        assert_eq!(read(&target), "shared");
+        // No temp files left behind.
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 3);
+    }
+}
@@ -2,8 +2,11 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod commands;
+mod dap;
 mod lsp;
 mod menu;
+#[cfg(test)]
+mod test_support;
 
 use tauri::Manager;
 
@@ -25,16 +28,56 @@ fn main() {
             commands::fs::read_dir_children,
             commands::fs::start_fs_watch,
             commands::fs::read_file_text,
+            commands::fs::write_file_text,
+            commands::editorconfig::resolve_editorconfig,
+            // Workspace
+            commands::workspace::open_workspace,
+            commands::workspace::save_workspace,
             // Indexer
             commands::indexer::build_index,
             commands::indexer::query_index,
             // Content indexer
             commands::content_indexer::build_content_index,
             commands::content_indexer::query_content_index,
+            // Symbol indexer
+            commands::symbol_indexer::build_symbol_index,
+            commands::symbol_indexer::query_symbols,
+            // Task scanner
+            commands::task_scanner::scan_tasks,
             // Frontend logging
             commands::logger::frontend_log,
             // ---------------- LSP ----------------
             lsp::invoke_lsp,
+            lsp::cancel_lsp_request,
+            lsp::respond_lsp_request,
+            lsp::restart_lsp,
+            lsp::stop_lsp,
+            lsp::lsp_server_output,
+            lsp::lsp_health,
+            lsp::lsp_status,
+            lsp::lsp_problems,
+            lsp::lsp_problem_counts,
+            lsp::lsp_capabilities,
+            lsp::lsp_open_document,
+            lsp::lsp_change_document,
+            lsp::lsp_save_document,
+            lsp::lsp_close_document,
+            lsp::lsp_format_document,
+            lsp::lsp_semantic_tokens,
+            lsp::lsp_semantic_tokens_legend,
+            lsp::apply_workspace_edit,
+            lsp::undo_workspace_edit,
+            lsp::lsp_set_recording,
+            lsp::lsp_recording,
+            // ---------------- DAP ----------------
+            dap::dap_adapters,
+            dap::dap_start,
+            dap::dap_sessions,
+            dap::dap_request,
+            dap::respond_dap_request,
+            dap::dap_stop,
+            dap::dap_breakpoints,
+            dap::dap_set_breakpoints,
         ])
         .setup(|app| {
             #[cfg_attr(
@@ -77,6 +120,15 @@ fn main() {
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("Error while running Tauri application");
+        .build(tauri::generate_context!())
+        .expect("Error while building Tauri application")
+        .run(|app, event| {
+            // Give language servers and debug adapters a chance to shut down cleanly.
+            if let tauri::RunEvent::Exit = event {
+                tauri::async_runtime::block_on(async {
+                    lsp::shutdown_all(app).await;
+                    dap::shutdown_all().await;
+                });
+            }
+        });
 }
@@ -0,0 +1,110 @@
+//! Fixtures shared by the bridge tests against the scriptable stdio tools in
+//! `tools/` (`mock-lsp`, `mock-dap`).
+//!
+//! A test gets a fresh temp root, writes the tool's script there (with the
+//! `log` the tool appends every received message to), and collects the
+//! events the bridge emits on a mock Tauri app.
+
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Listener, Runtime};
+
+/// Path of the binary of workspace package `package`, built with cargo.
+/// The environment variable `env_override` names the binary instead, for
+/// runs without cargo. Callers cache the result.
+pub fn tool_binary(package: &str, env_override: &str) -> PathBuf {
+    if let Some(path) = std::env::var_os(env_override) {
+        return path.into();
+    }
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--quiet", "--package", package])
+        .current_dir(manifest_dir)
+        .status()
+        .expect("failed to run cargo");
+    assert!(status.success(), "failed to build {package}");
+    let target_dir = std::env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| manifest_dir.join("../target"));
+    target_dir
+        .join("debug")
+        .join(format!("{package}{}", std::env::consts::EXE_SUFFIX))
+}
+
+/// Empty, canonicalised directory `glass-{prefix}-{pid}-{name}` in the
+/// system temp dir, for one test.
+pub fn temp_root(prefix: &str, name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("glass-{prefix}-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+}
+
+/// Write a tool `script` to `root`, logging received messages to
+/// `received.jsonl` (see [`received`]). Returns the script path.
+pub fn write_script(root: &Path, mut script: Value) -> PathBuf {
+    script["log"] = json!(root.join("received.jsonl"));
+    let path = root.join("script.json");
+    std::fs::write(&path, script.to_string()).unwrap();
+    path
+}
+
+/// Messages the tool running in `root` received, in order.
+pub fn received(root: &Path) -> Vec<Value> {
+    let log = std::fs::read_to_string(root.join("received.jsonl")).unwrap_or_default();
+    log.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Payloads of events emitted on an app, in order.
+#[derive(Clone, Default)]
+pub struct Events(Arc<Mutex<Vec<(&'static str, Value)>>>);
+
+impl Events {
+    /// Collect the events `names` emitted on `app`.
+    pub fn listen<R: Runtime>(app: &AppHandle<R>, names: &[&'static str]) -> Self {
+        let events = Events::default();
+        for &name in names {
+            let sink = events.0.clone();
+            app.listen_any(name, move |event| {
+                let payload = serde_json::from_str(event.payload()).unwrap();
+                sink.lock().unwrap().push((name, payload));
+            });
+        }
+        events
+    }
+
+    /// Payloads of the events `name` emitted so far.
+    pub fn named(&self, name: &str) -> Vec<Value> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(n, _)| *n == name)
+            .map(|(_, payload)| payload.clone())
+            .collect()
+    }
+
+    /// Wait until an event `name` matching `pred` was emitted.
+    pub async fn wait_for(&self, name: &str, pred: impl Fn(&Value) -> bool) -> Value {
+        wait_until(|| self.named(name).into_iter().find(|payload| pred(payload)))
+            .await
+            .unwrap_or_else(|| panic!("no matching `{name}` event"))
+    }
+}
+
+/// Poll `f` until it returns something, for up to five seconds.
+pub async fn wait_until<T>(f: impl Fn() -> Option<T>) -> Option<T> {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(5) {
+        if let Some(value) = f() {
+            return Some(value);
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    None
+}
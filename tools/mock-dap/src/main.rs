@@ -0,0 +1,186 @@
+//! Scriptable stdio debug adapter for the DAP bridge tests.
+//!
+//! ```text
+//! mock-dap [SCRIPT.json]
+//! ```
+//!
+//! The script decides how each command is answered; without one every
+//! request succeeds with `{ "command", "arguments" }` as its body.
+//!
+//! ```json
+//! {
+//!   "capabilities": { "supportsConfigurationDoneRequest": true },
+//!   "log": "/tmp/received.jsonl",
+//!   "commands": {
+//!     "launch": { "deferUntil": "configurationDone" },
+//!     "configurationDone": {
+//!       "events": [{ "event": "stopped", "body": { "reason": "entry", "threadId": 1 } }]
+//!     },
+//!     "evaluate": { "body": { "result": "42", "variablesReference": 0 } },
+//!     "next": { "error": "cannot step" },
+//!     "custom/terminal": {
+//!       "reverse": [{ "command": "runInTerminal", "arguments": { "args": ["ls"] } }]
+//!     },
+//!     "custom/crash": { "crash": 3 }
+//!   }
+//! }
+//! ```
+//!
+//! Per command, in this order: `crash` exits with the given code, the
+//! response is written (`error` makes it unsuccessful, otherwise `body`),
+//! then the `events` and `reverse` requests. With `deferUntil` the response
+//! is held back until a request with that command arrived.
+//!
+//! Built in: `initialize` is answered with `capabilities` (default: supports
+//! `configurationDone`) and followed by the `initialized` event,
+//! `setBreakpoints` verifies every breakpoint, `disconnect` ends the process
+//! after answering. Every received message is appended as one JSON line to
+//! the `log` file, if given.
+
+use serde_json::{json, Value};
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::process;
+
+fn main() {
+    let script = match std::env::args().nth(1) {
+        Some(path) => {
+            let text = fs::read_to_string(&path)
+                .unwrap_or_else(|err| fail(&format!("cannot read script {path}: {err}")));
+            serde_json::from_str(&text)
+                .unwrap_or_else(|err| fail(&format!("invalid script {path}: {err}")))
+        }
+        None => json!({}),
+    };
+    let mut log = script["log"].as_str().map(|path| {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap_or_else(|err| fail(&format!("cannot open log {path}: {err}")))
+    });
+
+    let mut out = Output {
+        stdout: io::stdout(),
+        seq: 0,
+    };
+    // Responses held back by `deferUntil`: (command to wait for, response).
+    let mut deferred: Vec<(String, Value)> = Vec::new();
+    let mut stdin = BufReader::new(io::stdin());
+    while let Some(msg) = read_message(&mut stdin) {
+        if let Some(log) = log.as_mut() {
+            let _ = writeln!(log, "{msg}");
+        }
+        if msg["type"] != "request" {
+            // Response to one of our reverse requests.
+            continue;
+        }
+        let command = msg["command"].as_str().unwrap_or_default();
+        let action = &script["commands"][command];
+        if let Some(code) = action["crash"].as_i64() {
+            process::exit(code as i32);
+        }
+
+        let mut response = json!({
+            "type": "response",
+            "request_seq": msg["seq"],
+            "command": command,
+            "success": true,
+        });
+        if let Some(error) = action["error"].as_str() {
+            response["success"] = json!(false);
+            response["message"] = json!(error);
+        } else {
+            response["body"] = match (command, action.get("body")) {
+                (_, Some(body)) => body.clone(),
+                ("initialize", None) => script
+                    .get("capabilities")
+                    .cloned()
+                    .unwrap_or_else(|| json!({ "supportsConfigurationDoneRequest": true })),
+                ("setBreakpoints", None) => {
+                    let breakpoints: Vec<Value> = msg["arguments"]["breakpoints"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .enumerate()
+                        .map(|(i, bp)| json!({ "id": i + 1, "verified": true, "line": bp["line"] }))
+                        .collect();
+                    json!({ "breakpoints": breakpoints })
+                }
+                (_, None) => json!({ "command": command, "arguments": msg["arguments"] }),
+            };
+        }
+        match action["deferUntil"].as_str() {
+            Some(until) => deferred.push((until.to_string(), response)),
+            None => out.send(response),
+        }
+
+        if command == "initialize" {
+            out.send(json!({ "type": "event", "event": "initialized" }));
+        }
+        for event in action["events"].as_array().into_iter().flatten() {
+            let mut event = event.clone();
+            event["type"] = json!("event");
+            out.send(event);
+        }
+        for request in action["reverse"].as_array().into_iter().flatten() {
+            let mut request = request.clone();
+            request["type"] = json!("request");
+            out.send(request);
+        }
+        let (ready, waiting) = deferred
+            .into_iter()
+            .partition(|(until, _)| until == command);
+        deferred = waiting;
+        for (_, response) in ready {
+            out.send(response);
+        }
+        if command == "disconnect" {
+            process::exit(0);
+        }
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("mock-dap: {message}");
+    process::exit(2)
+}
+
+/// Stdout plus the adapter's `seq` counter.
+struct Output {
+    stdout: io::Stdout,
+    seq: u64,
+}
+
+impl Output {
+    fn send(&mut self, mut msg: Value) {
+        self.seq += 1;
+        msg["seq"] = json!(self.seq);
+        let body = msg.to_string();
+        let _ = write!(self.stdout, "Content-Length: {}\r\n\r\n{body}", body.len());
+        let _ = self.stdout.flush();
+    }
+}
+
+/// Read one `Content-Length` framed message; `None` at EOF or on bad input.
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let mut body = vec![0; length?];
+    reader.read_exact(&mut body).ok()?;
+    serde_json::from_slice(&body).ok()
+}
@@ -0,0 +1,209 @@
+//! Scriptable stdio language server for the LSP bridge tests.
+//!
+//! ```text
+//! mock-lsp [SCRIPT.json]
+//! mock-lsp --version
+//! ```
+//!
+//! The script decides how each method is answered; without one every request
+//! is echoed back as `{ "method", "params" }`.
+//!
+//! ```json
+//! {
+//!   "capabilities": { "hoverProvider": true },
+//!   "stderr": ["printed to stderr on start-up"],
+//!   "log": "/tmp/received.jsonl",
+//!   "methods": {
+//!     "textDocument/hover": { "result": { "contents": "hi" }, "delayMs": 200 },
+//!     "textDocument/rename": { "error": { "code": -32603, "message": "nope" } },
+//!     "textDocument/didOpen": {
+//!       "send": [{ "method": "textDocument/publishDiagnostics",
+//!                  "params": { "uri": "$uri", "diagnostics": [] } }]
+//!     },
+//!     "custom/crash": { "stderr": "panicked", "crash": 3 },
+//!     "custom/garbage": { "raw": "Content-Length: nope\r\n\r\n" }
+//!   }
+//! }
+//! ```
+//!
+//! Per method, in this order: `stderr` is printed, `raw` is written to stdout
+//! verbatim, the `send` messages are written (notifications, or server
+//! requests if they carry an `id`; the string `"$uri"` is replaced by the
+//! request's `textDocument.uri`), `crash` exits with the given code, and
+//! after `delayMs` requests get `result` / `error`. Delayed requests are
+//! answered from their own thread and honour `$/cancelRequest`.
+//!
+//! `initialize` is answered with `capabilities` (default: full sync, hover),
+//! `shutdown` with `null`; `exit` ends the process. Every received message is
+//! appended as one JSON line to the `log` file, if given.
+
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// JSON-RPC error code for cancelled requests.
+const REQUEST_CANCELLED: i64 = -32800;
+
+type Output = Arc<Mutex<io::Stdout>>;
+
+fn main() {
+    let script = match std::env::args().nth(1) {
+        Some(arg) if arg == "--version" => {
+            println!("mock-lsp {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Some(path) => {
+            let text = fs::read_to_string(&path)
+                .unwrap_or_else(|err| fail(&format!("cannot read script {path}: {err}")));
+            serde_json::from_str(&text)
+                .unwrap_or_else(|err| fail(&format!("invalid script {path}: {err}")))
+        }
+        None => json!({}),
+    };
+    for line in script["stderr"].as_array().into_iter().flatten() {
+        eprintln!("{}", line.as_str().unwrap_or_default());
+    }
+    let mut log = script["log"].as_str().map(|path| {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap_or_else(|err| fail(&format!("cannot open log {path}: {err}")))
+    });
+
+    let out: Output = Arc::new(Mutex::new(io::stdout()));
+    let cancelled = Arc::new(Mutex::new(HashSet::new()));
+    let mut stdin = BufReader::new(io::stdin());
+    while let Some(msg) = read_message(&mut stdin) {
+        if let Some(log) = log.as_mut() {
+            let _ = writeln!(log, "{msg}");
+        }
+        let Some(method) = msg["method"].as_str() else {
+            // Response to one of our requests.
+            continue;
+        };
+        match method {
+            "exit" => process::exit(0),
+            "$/cancelRequest" => {
+                cancelled
+                    .lock()
+                    .unwrap()
+                    .insert(msg["params"]["id"].to_string());
+                continue;
+            }
+            _ => {}
+        }
+
+        let action = &script["methods"][method];
+        if let Some(line) = action["stderr"].as_str() {
+            eprintln!("{line}");
+        }
+        if let Some(raw) = action["raw"].as_str() {
+            let mut out = out.lock().unwrap();
+            let _ = out.write_all(raw.as_bytes());
+            let _ = out.flush();
+        }
+        let uri = &msg["params"]["textDocument"]["uri"];
+        for send in action["send"].as_array().into_iter().flatten() {
+            let mut send = substitute(send, uri);
+            send["jsonrpc"] = json!("2.0");
+            write_message(&out, &send);
+        }
+        if let Some(code) = action["crash"].as_i64() {
+            process::exit(code as i32);
+        }
+
+        let Some(id) = msg.get("id").cloned() else {
+            continue;
+        };
+        let mut response = json!({ "jsonrpc": "2.0", "id": id });
+        if let Some(error) = action.get("error") {
+            response["error"] = error.clone();
+        } else {
+            response["result"] = match (method, action.get("result")) {
+                (_, Some(result)) => result.clone(),
+                ("initialize", None) => json!({
+                    "capabilities": script.get("capabilities").cloned().unwrap_or_else(
+                        || json!({ "textDocumentSync": 1, "hoverProvider": true })
+                    ),
+                    "serverInfo": { "name": "mock-lsp" },
+                }),
+                ("shutdown", None) => Value::Null,
+                (_, None) => json!({ "method": method, "params": msg["params"] }),
+            };
+        }
+
+        match action["delayMs"].as_u64() {
+            Some(delay) => {
+                let out = out.clone();
+                let cancelled = cancelled.clone();
+                thread::spawn(move || {
+                    thread::sleep(Duration::from_millis(delay));
+                    if cancelled.lock().unwrap().remove(&id.to_string()) {
+                        response = json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "error": { "code": REQUEST_CANCELLED, "message": "cancelled" },
+                        });
+                    }
+                    write_message(&out, &response);
+                });
+            }
+            None => write_message(&out, &response),
+        }
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("mock-lsp: {message}");
+    process::exit(2)
+}
+
+/// Replace every `"$uri"` string in `value` with `uri`.
+fn substitute(value: &Value, uri: &Value) -> Value {
+    match value {
+        Value::String(s) if s == "$uri" => uri.clone(),
+        Value::Array(items) => Value::Array(items.iter().map(|v| substitute(v, uri)).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), substitute(v, uri)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Read one `Content-Length` framed message; `None` at EOF or on bad input.
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let mut body = vec![0; length?];
+    reader.read_exact(&mut body).ok()?;
+    serde_json::from_slice(&body).ok()
+}
+
+fn write_message(out: &Output, msg: &Value) {
+    let body = msg.to_string();
+    let mut out = out.lock().unwrap();
+    let _ = write!(out, "Content-Length: {}\r\n\r\n{body}", body.len());
+    let _ = out.flush();
+}